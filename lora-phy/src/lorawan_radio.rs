@@ -4,9 +4,11 @@ use super::mod_params::{PacketParams, RadioError};
 use super::mod_traits::RadioKind;
 use super::{DelayNs, LoRa, RxMode};
 
-use lora_modulation::BaseBandModulationParams;
 use lorawan_device::async_device::{
-    radio::{PhyRxTx, RxConfig, RxMode as LorawanRxMode, RxQuality, RxStatus, TxConfig},
+    radio::{
+        ChannelAssessment, ClearChannelConfig, PhyRxTx, RxConfig, RxMode as LorawanRxMode, RxQuality, RxStatus,
+        TxConfig,
+    },
     Timings,
 };
 
@@ -62,7 +64,7 @@ where
     DLY: DelayNs,
 {
     fn get_rx_window_buffer(&self) -> u32 {
-        self.rx_window_lead_time
+        self.rx_window_buffer
     }
 
     fn get_rx_window_lead_time_ms(&self) -> u32 {
@@ -110,6 +112,7 @@ where
             .prepare_for_tx(&mdltn_params, &mut tx_pkt_params, config.pw.into(), buffer)
             .await?;
         self.lora.tx().await?;
+        // the transmission completes on the TX done interrupt
         Ok(0)
     }
 
@@ -124,7 +127,7 @@ where
             .lora
             .create_rx_packet_params(8, false, 255, true, true, &mdltn_params)?;
         self.lora
-            .prepare_for_rx(RxMode::from(config.mode, config.rf.bb), &mdltn_params, &rx_pkt_params)
+            .prepare_for_rx(RxMode::from(config.mode), &mdltn_params, &rx_pkt_params)
             .await?;
         self.rx_pkt_params = Some(rx_pkt_params);
         Ok(())
@@ -159,19 +162,32 @@ where
     async fn low_power(&mut self) -> Result<(), Self::PhyError> {
         self.lora.sleep(false).await.map_err(|e| e.into())
     }
+
+    async fn clear_channel(&mut self, config: ClearChannelConfig) -> Result<ChannelAssessment, Self::PhyError> {
+        self.lora.listen(config.rf.frequency, config.rf.bb.bw).await?;
+        // Sample the RSSI every millisecond of the sensing time
+        let mut remaining_us = config.sensing_time_us;
+        let assessment = loop {
+            if self.lora.get_rssi().await? > config.threshold_dbm {
+                break ChannelAssessment::Busy;
+            }
+            if remaining_us == 0 {
+                break ChannelAssessment::Clear;
+            }
+            let step_us = remaining_us.min(1000);
+            self.lora.delay.delay_us(step_us).await;
+            remaining_us -= step_us;
+        };
+        self.lora.enter_standby().await?;
+        Ok(assessment)
+    }
 }
 
-impl RxMode {
-    fn from(mode: LorawanRxMode, bb: BaseBandModulationParams) -> Self {
+impl From<LorawanRxMode> for RxMode {
+    fn from(mode: LorawanRxMode) -> Self {
         match mode {
             LorawanRxMode::Continuous => RxMode::Continuous,
-            LorawanRxMode::Single { ms } => {
-                // Since both sx126x and sx127x have a preamble-based timeout, we translate
-                // the additional millisecond delay into symbols and add it to the amount of preamble symbols.
-                const PREAMBLE_SYMBOLS: u16 = 13; // 12.25
-                let num_symbols = PREAMBLE_SYMBOLS + bb.delay_in_symbols(ms);
-                RxMode::Single(num_symbols)
-            }
+            LorawanRxMode::Single { symbols } => RxMode::Single(symbols),
         }
     }
 }
@@ -2,19 +2,25 @@
 //! allowing for asynchronous radio implementations. Requires the `async` feature.
 use super::mac::Mac;
 
-use super::mac::{self, Frame, Window};
+use super::mac::{self, Frame, Reception, Window};
 pub use super::{
-    mac::{NetworkCredentials, SendData, Session},
+    mac::{JoinRetryConfig, NetworkCredentials, SendData, Session},
     region::{self, Region},
-    Downlink, JoinMode,
+    Downlink, JoinMode, LorawanDevice, OverflowPolicy, RxWindow,
 };
 use core::marker::PhantomData;
-use heapless::Vec;
 use lorawan::{self, keys::CryptoFactory};
 use rand_core::RngCore;
 
 pub use crate::region::DR;
-use crate::{radio::RadioBuffer, rng};
+use crate::{
+    device::impl_lorawan_device,
+    downlink::DownlinkQueue,
+    events::{EventSink, MacEvent},
+    radio::RadioBuffer,
+    region::{ChannelPlan, SpreadingFactor},
+    rng,
+};
 
 pub mod radio;
 
@@ -23,10 +29,16 @@ mod embassy_time;
 #[cfg(feature = "embassy-time")]
 pub use embassy_time::EmbassyTimer;
 
+#[cfg(feature = "embassy-sync")]
+pub mod split;
+
 #[cfg(test)]
 mod test;
 
-use self::radio::RxStatus;
+use self::radio::{ChannelAssessment, RxConfig, RxStatus, TxConfig};
+
+/// Number of channels assessed before giving up with `Error::ChannelBusy`.
+pub(crate) const CLEAR_CHANNEL_ATTEMPTS: u8 = 3;
 
 /// Type representing a LoRaWAN capable device.
 ///
@@ -38,35 +50,86 @@ use self::radio::RxStatus;
 ///   providing a random seed
 /// - N: The size of the radio buffer. Generally, this should be set to 256 to support the largest possible LoRa frames.
 /// - D: The amount of downlinks that may be buffered. This is used to support Class C operation. See below for more.
+/// - E: An [`EventSink`] receiving [`MacEvent`]s. Defaults to `()`, which discards all events. See
+///   [`with_event_sink`](Device::with_event_sink).
+/// - P: The [`ChannelPlan`]. Defaults to [`region::Configuration`], which
+///   covers the built-in regions. A single region (eg: [`region::EU868`]) keeps the code of the
+///   others out of the firmware. See [`new_with_channel_plan`](Device::new_with_channel_plan).
 ///
 /// Note that the const generics N and D are used to configure the size of the radio buffer and the number of downlinks
 /// that may be buffered. The defaults are 256 and 1 respectively which should be fine for Class A devices. **For Class
 /// C operation**, it is recommended to increase D to at least 2, if not 3. This is because during the RX1/RX2 windows
 /// after a Class A transmit, it is possible to receive Class C downlinks (in additional to any RX1/RX2 responses!).
-pub struct Device<R, C, T, G, const N: usize = 256, const D: usize = 1>
-where
+///
+/// # Cancel safety
+///
+/// The futures of [`join`](Device::join), [`join_with_retries`](Device::join_with_retries) and
+/// [`send`](Device::send) may be dropped at any await point, eg: when they lose a `select`
+/// against a button press. The device then stays consistent, but the cleanup waits for the next
+/// operation, or for [`recover`](Device::recover):
+/// - a frame which may have been transmitted consumes its FCnt, as if its RX windows went by
+///   without a downlink, so that the next uplink is not rejected as a replay. A JoinRequest is
+///   treated as unanswered; the device is not joined anymore.
+/// - the radio, which may have been left transmitting or receiving, is put back to low power, or
+///   to Class C listening.
+///
+/// [`send_with_deadline`](Device::send_with_deadline) recovers right away. Dropping the future of
+/// [`rxc_listen`](Device::rxc_listen) requires no cleanup.
+pub struct Device<
+    R,
+    C,
+    T,
+    G,
+    const N: usize = 256,
+    const D: usize = 1,
+    E = (),
+    P = region::Configuration,
+> where
     R: radio::PhyRxTx + Timings,
     T: radio::Timer,
     C: CryptoFactory + Default,
     G: RngCore,
+    E: EventSink,
+    P: ChannelPlan,
 {
     crypto: PhantomData<C>,
     radio: R,
     /// Access to provided (pseudo)-random number generator.
     pub rng: G,
     timer: T,
-    mac: Mac,
+    mac: Mac<P>,
     radio_buffer: RadioBuffer<N>,
-    downlink: Vec<Downlink, D>,
+    downlink: DownlinkQueue<D>,
+    events: E,
+    pending: Pending,
     #[cfg(feature = "class-c")]
     class_c: bool,
 }
 
+/// Cleanup owed by a `join` or `send` which did not complete, because its future was dropped or
+/// it failed midway.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Pending {
+    Nothing,
+    /// The radio may be left transmitting or receiving.
+    Radio,
+    /// In addition, a frame may have been transmitted without its RX windows being accounted for
+    /// by the MAC.
+    RxWindows,
+}
+
 #[cfg_attr(feature = "defmt-03", derive(defmt::Format))]
 #[derive(Debug)]
 pub enum Error<R> {
     Radio(R),
     Mac(mac::Error),
+    /// Listen-before-talk found every channel tried busy, so nothing was sent.
+    ChannelBusy,
+    /// Listen-before-talk is required, but the radio does not implement it.
+    ClearChannelUnsupported,
+    /// The deadline of [`Device::send_with_deadline`] expired before the RX windows went by. The
+    /// device was recovered, as described in [`Device`].
+    DeadlineExpired,
 }
 
 #[cfg_attr(feature = "defmt-03", derive(defmt::Format))]
@@ -155,7 +218,28 @@ where
         rng: G,
         session: Option<Session>,
     ) -> Self {
-        let mut mac = Mac::new(region, R::MAX_RADIO_POWER, R::ANTENNA_GAIN);
+        Device::new_with_channel_plan(region, radio, timer, rng, session)
+    }
+}
+
+impl<R, C, T, G, const N: usize, const D: usize, P> Device<R, C, T, G, N, D, (), P>
+where
+    R: radio::PhyRxTx + Timings,
+    C: CryptoFactory + Default,
+    T: radio::Timer,
+    G: RngCore,
+    P: ChannelPlan,
+{
+    /// Create a new [`Device`] using a custom [`ChannelPlan`] instead of one
+    /// of the built-in regions, and provide an optional [`Session`].
+    pub fn new_with_channel_plan(
+        plan: P,
+        radio: R,
+        timer: T,
+        rng: G,
+        session: Option<Session>,
+    ) -> Self {
+        let mut mac = Mac::new(plan, R::MAX_RADIO_POWER, R::ANTENNA_GAIN);
         if let Some(session) = session {
             mac.set_session(session);
         }
@@ -166,11 +250,64 @@ where
             mac,
             radio_buffer: RadioBuffer::new(),
             timer,
-            downlink: Vec::new(),
+            downlink: DownlinkQueue::new(),
+            events: (),
+            pending: Pending::Nothing,
             #[cfg(feature = "class-c")]
             class_c: false,
         }
     }
+}
+
+impl<R, C, T, G, const N: usize, const D: usize, E, P> Device<R, C, T, G, N, D, E, P>
+where
+    R: radio::PhyRxTx + Timings,
+    C: CryptoFactory + Default,
+    T: radio::Timer,
+    G: RngCore,
+    E: EventSink,
+    P: ChannelPlan,
+{
+    /// Replace the [`EventSink`] which receives the [`MacEvent`]s of this device.
+    pub fn with_event_sink<S: EventSink>(self, events: S) -> Device<R, C, T, G, N, D, S, P> {
+        Device {
+            crypto: PhantomData,
+            radio: self.radio,
+            rng: self.rng,
+            timer: self.timer,
+            mac: self.mac,
+            radio_buffer: self.radio_buffer,
+            downlink: self.downlink,
+            events,
+            pending: self.pending,
+            #[cfg(feature = "class-c")]
+            class_c: self.class_c,
+        }
+    }
+
+    /// Largest application payload which can be passed to `send` with the current data rate, dwell
+    /// time and pending MAC commands.
+    pub fn max_payload_len(&self) -> usize {
+        self.mac.max_payload_len::<N>()
+    }
+
+    /// Statistics accumulated since the device was created or since the last
+    /// [`reset_statistics`](Self::reset_statistics).
+    pub fn get_statistics(&self) -> &mac::Statistics {
+        self.mac.get_statistics()
+    }
+
+    pub fn reset_statistics(&mut self) {
+        self.mac.reset_statistics()
+    }
+
+    pub fn get_event_sink(&self) -> &E {
+        &self.events
+    }
+
+    pub fn get_mut_event_sink(&mut self) -> &mut E {
+        &mut self.events
+    }
 
     /// Enables Class C behavior. Note that Class C downlinks are not possible until a confirmed
     /// uplink is sent to the LNS.
@@ -186,15 +323,47 @@ where
         self.class_c = false;
     }
 
-    pub fn get_session(&mut self) -> Option<&Session> {
+    /// Senses the channel before every transmission, even if the region does not mandate
+    /// listen-before-talk (eg: AS923 in Japan). A channel with a signal stronger than
+    /// `threshold_dbm` during `sensing_time_us` is busy. These parameters are also used instead
+    /// of the ones of regions mandating listen-before-talk.
+    pub fn enable_listen_before_talk(&mut self, threshold_dbm: i16, sensing_time_us: u32) {
+        self.mac.set_listen_before_talk(Some((threshold_dbm, sensing_time_us)));
+    }
+
+    /// Only senses the channel before transmitting if the region mandates listen-before-talk.
+    pub fn disable_listen_before_talk(&mut self) {
+        self.mac.set_listen_before_talk(None);
+    }
+
+    pub fn get_session(&self) -> Option<&Session> {
         self.mac.get_session()
     }
 
-    pub fn get_region(&mut self) -> &region::Configuration {
+    /// Restores a session, eg: one persisted before a reset. The device is then joined.
+    pub fn set_session(&mut self, session: Session) {
+        self.mac.set_session(session)
+    }
+
+    pub fn get_session_keys(&self) -> Option<mac::SessionKeys> {
+        self.mac.get_session_keys()
+    }
+
+    pub fn get_fcnt_up(&self) -> Option<mac::FcntUp> {
+        self.mac.get_fcnt_up()
+    }
+
+    /// Whether the device is joined. Unlike `nb_device::Device`, it is never busy with an uplink
+    /// when this can be called.
+    pub fn ready_to_send_data(&self) -> bool {
+        self.mac.is_joined()
+    }
+
+    pub fn get_region(&self) -> &P {
         &self.mac.region
     }
 
-    pub fn get_radio(&mut self) -> &R {
+    pub fn get_radio(&self) -> &R {
         &self.radio
     }
 
@@ -203,7 +372,7 @@ where
     }
 
     /// Retrieve the current data rate being used by this device.
-    pub fn get_datarate(&mut self) -> DR {
+    pub fn get_datarate(&self) -> DR {
         self.mac.configuration.data_rate
     }
 
@@ -219,25 +388,14 @@ where
     ///
     /// Note that for a Class C enabled device, you must repeatedly send *confirmed* uplink until
     /// LoRaWAN Network Server (LNS) confirmation after joining.
+    ///
+    /// The returned future is cancel-safe, as described in [`Device`].
     pub async fn join(&mut self, join_mode: &JoinMode) -> Result<JoinResponse, Error<R::PhyError>> {
+        self.recover().await?;
+        self.mac.stop_join_retries();
         match join_mode {
             JoinMode::OTAA { deveui, appeui, appkey } => {
-                let (tx_config, _) = self.mac.join_otaa::<C, G, N>(
-                    &mut self.rng,
-                    NetworkCredentials::new(*appeui, *deveui, *appkey),
-                    &mut self.radio_buffer,
-                );
-
-                // Transmit the join payload
-                let ms = self
-                    .radio
-                    .tx(tx_config, self.radio_buffer.as_ref_for_read())
-                    .await
-                    .map_err(Error::Radio)?;
-
-                // Receive join response within RX window
-                self.timer.reset();
-                Ok(self.rx_downlink(&Frame::Join, ms).await?.try_into()?)
+                self.join_otaa(NetworkCredentials::new(*appeui, *deveui, *appkey)).await
             }
             JoinMode::ABP { nwkskey, appskey, devaddr } => {
                 self.mac.join_abp(*nwkskey, *appskey, *devaddr);
@@ -246,6 +404,62 @@ where
         }
     }
 
+    /// Join the LoRaWAN network, retrying OTAA joins which are not answered according to
+    /// `config`. Retransmissions are delayed so that JoinRequests respect the duty cycle limits
+    /// of the LoRaWAN specification, plus a random jitter. Returns
+    /// [`JoinResponse::NoJoinAccept`] once `config.max_attempts` JoinRequests went unanswered.
+    ///
+    /// ABP joins do not involve the network and behave as with [`join`](Self::join).
+    pub async fn join_with_retries(
+        &mut self,
+        join_mode: &JoinMode,
+        config: JoinRetryConfig,
+    ) -> Result<JoinResponse, Error<R::PhyError>> {
+        let JoinMode::OTAA { deveui, appeui, appkey } = join_mode else {
+            return self.join(join_mode).await;
+        };
+        self.recover().await?;
+        self.mac.start_join_retries(config, NetworkCredentials::new(*appeui, *deveui, *appkey));
+        while let Some(credentials) = self.mac.next_join_attempt(&mut self.rng) {
+            match self.join_otaa(credentials).await {
+                Ok(JoinResponse::NoJoinAccept) => match self.mac.join_backoff_ms() {
+                    Some(delay) => self.timer.delay_ms(delay.into()).await,
+                    None => break,
+                },
+                result => {
+                    self.mac.stop_join_retries();
+                    return result;
+                }
+            }
+        }
+        Ok(JoinResponse::NoJoinAccept)
+    }
+
+    async fn join_otaa(
+        &mut self,
+        credentials: NetworkCredentials,
+    ) -> Result<JoinResponse, Error<R::PhyError>> {
+        let (tx_config, dev_nonce) =
+            self.mac.join_otaa::<C, G, N>(&mut self.rng, credentials, &mut self.radio_buffer)?;
+
+        // Transmit the join payload
+        let (tx_config, tx_done_ms) = self.transmit(tx_config, &Frame::Join).await?;
+        self.mac.emit(
+            &mut self.events,
+            MacEvent::JoinRequestSent {
+                dev_nonce,
+                tx_config,
+                time_on_air_us: mac::time_on_air_us(
+                    &tx_config,
+                    self.radio_buffer.as_ref_for_read().len(),
+                ),
+            },
+        );
+
+        // Receive join response within RX window
+        Ok(self.rx_downlink(&Frame::Join, tx_done_ms).await?.try_into()?)
+    }
+
     /// Send data on a given port with the expected confirmation. If downlink data is provided, the
     /// data is copied into the provided byte slice.
     ///
@@ -256,42 +470,146 @@ where
     /// In Class C mode, it is possible to get one or more downlinks and `Reponse::DownlinkReceived`
     /// maybe not even be indicated. It is recommended to call `take_downlink` after `send` until
     /// it returns `None`.
+    ///
+    /// The returned future is cancel-safe, as described in [`Device`].
     pub async fn send(
         &mut self,
         data: &[u8],
         fport: u8,
         confirmed: bool,
     ) -> Result<SendResponse, Error<R::PhyError>> {
+        self.recover().await?;
         // Prepare transmission buffer
-        let (tx_config, _fcnt_up) = self.mac.send::<C, G, N>(
+        let (tx_config, fcnt_up) = self.mac.send::<C, G, N>(
             &mut self.rng,
             &mut self.radio_buffer,
             &SendData { data, fport, confirmed },
         )?;
         // Transmit our data packet
-        let ms = self
-            .radio
-            .tx(tx_config, self.radio_buffer.as_ref_for_read())
-            .await
-            .map_err(Error::Radio)?;
+        let (tx_config, tx_done_ms) = self.transmit(tx_config, &Frame::Data).await?;
+        self.mac.emit(
+            &mut self.events,
+            MacEvent::UplinkSent {
+                fcnt_up,
+                tx_config,
+                time_on_air_us: mac::time_on_air_us(
+                    &tx_config,
+                    self.radio_buffer.as_ref_for_read().len(),
+                ),
+            },
+        );
 
         // Wait for received data within window
-        self.timer.reset();
-        Ok(self.rx_downlink(&Frame::Data, ms).await?.try_into()?)
+        Ok(self.rx_downlink(&Frame::Data, tx_done_ms).await?.try_into()?)
+    }
+
+    /// Like [`send`](Self::send), but gives up with [`Error::DeadlineExpired`] once `deadline`
+    /// completes (eg: an `embassy_time::Timer`), after recovering the device. Whether the frame
+    /// was transmitted is then unknown, but its FCnt is consumed either way.
+    pub async fn send_with_deadline(
+        &mut self,
+        data: &[u8],
+        fport: u8,
+        confirmed: bool,
+        deadline: impl futures::Future<Output = ()>,
+    ) -> Result<SendResponse, Error<R::PhyError>> {
+        use futures::{future::select, future::Either, pin_mut};
+
+        {
+            let send = self.send(data, fport, confirmed);
+            pin_mut!(send, deadline);
+            if let Either::Left((response, _)) = select(send, deadline).await {
+                return response;
+            }
+        }
+        self.recover().await?;
+        Err(Error::DeadlineExpired)
+    }
+
+    /// Cleans up after a `join` or `send` whose future was dropped or which failed midway, as
+    /// described in [`Device`]. Every operation starts with this, so calling it is only needed
+    /// to stop the radio right away. Does nothing otherwise.
+    pub async fn recover(&mut self) -> Result<(), Error<R::PhyError>> {
+        match self.pending {
+            Pending::Nothing => return Ok(()),
+            Pending::Radio => (),
+            Pending::RxWindows => {
+                debug!("Accounting for the RX windows of an interrupted transmission.");
+                let _ = self.mac.rx2_complete();
+                self.pending = Pending::Radio;
+            }
+        }
+        self.window_complete().await?;
+        self.pending = Pending::Nothing;
+        Ok(())
     }
 
     /// Take the downlink data from the device. This is typically called after a
     /// `Response::DownlinkReceived` is returned from `send`. This call consumes the downlink
-    /// data. If no downlink data is available, `None` is returned.
+    /// data. If no downlink data is available, `None` is returned. Downlinks are returned in the
+    /// order they were received.
     pub fn take_downlink(&mut self) -> Option<Downlink> {
         self.downlink.pop()
     }
 
+    /// Chooses which downlink to discard when one is received while the queue of `D` downlinks is
+    /// full. Defaults to [`OverflowPolicy::DropNewest`]. Discarded downlinks are reported as
+    /// [`MacEvent::DownlinkDropped`].
+    pub fn set_downlink_overflow_policy(&mut self, policy: OverflowPolicy) {
+        self.downlink.set_policy(policy)
+    }
+
+    /// Transmits the radio buffer, after making sure the channel is clear if listen-before-talk
+    /// is required. A busy channel is marked as such in the channel plan and the frame is moved
+    /// to another channel, up to `CLEAR_CHANNEL_ATTEMPTS` times. Returns the configuration used
+    /// and how long ago the transmission ended, as reported by the radio.
+    async fn transmit(
+        &mut self,
+        mut tx_config: TxConfig,
+        frame: &Frame,
+    ) -> Result<(TxConfig, u32), Error<R::PhyError>> {
+        self.pending = Pending::Radio;
+        let mut attempts = 1;
+        while let Some(config) = self.mac.get_clear_channel_config(tx_config.rf) {
+            match self.radio.clear_channel(config).await.map_err(Error::Radio)? {
+                ChannelAssessment::Clear => {
+                    self.mac.region.set_last_channel_busy(false);
+                    break;
+                }
+                ChannelAssessment::Busy => {
+                    self.mac.region.set_last_channel_busy(true);
+                    if attempts == CLEAR_CHANNEL_ATTEMPTS {
+                        return Err(Error::ChannelBusy);
+                    }
+                    attempts += 1;
+                    tx_config = self.mac.create_tx_config(&mut self.rng, frame)?;
+                }
+                ChannelAssessment::Unsupported => return Err(Error::ClearChannelUnsupported),
+            }
+        }
+        // from here on, the frame may be on air
+        self.pending = Pending::RxWindows;
+        self.mac.uplink_sent();
+        let tx_done_ms = self
+            .radio
+            .tx(tx_config, self.radio_buffer.as_ref_for_read())
+            .await
+            .map_err(Error::Radio)?;
+        // RX windows are timed from here
+        self.timer.reset();
+        Ok((tx_config, tx_done_ms))
+    }
+
     async fn window_complete(&mut self) -> Result<(), Error<R::PhyError>> {
         #[cfg(feature = "class-c")]
         if self.class_c {
-            let rf_config = self.mac.get_rxc_config();
-            return self.radio.setup_rx(rf_config).await.map_err(Error::Radio);
+            let rx_config = self.mac.get_rxc_config();
+            self.radio.setup_rx(rx_config).await.map_err(Error::Radio)?;
+            self.mac.emit(
+                &mut self.events,
+                MacEvent::RxWindowOpened { window: RxWindow::Rxc, rf: rx_config.rf },
+            );
+            return Ok(());
         }
 
         self.radio.low_power().await.map_err(Error::Radio)
@@ -312,7 +630,6 @@ where
         &mut self,
         duration: u32,
     ) -> Result<Option<mac::Response>, Error<R::PhyError>> {
-        use self::radio::RxQuality;
         use futures::{future::select, future::Either, pin_mut};
 
         if !self.class_c {
@@ -321,6 +638,8 @@ where
             return Ok(None);
         }
 
+        use self::radio::RxQuality;
+
         #[allow(unused)]
         enum RxcWindowResponse<F: futures::Future<Output = ()> + Sized + Unpin> {
             Rx(usize, RxQuality, F),
@@ -360,6 +679,10 @@ where
         let rx_config = self.mac.get_rxc_config();
         debug!("Configuring RXC window with config {}.", rx_config);
         self.radio.setup_rx(rx_config).await.map_err(Error::Radio)?;
+        self.mac.emit(
+            &mut self.events,
+            MacEvent::RxWindowOpened { window: RxWindow::Rxc, rf: rx_config.rf },
+        );
         let mut response = None;
         let timeout_fut = self.timer.at(duration.into());
         pin_mut!(timeout_fut);
@@ -375,13 +698,15 @@ where
             )
             .await
             {
-                RxcWindowResponse::Rx(sz, _, timeout_fut) => {
+                RxcWindowResponse::Rx(sz, quality, timeout_fut) => {
                     debug!("RXC window received {} bytes.", sz);
                     self.radio_buffer.set_pos(sz);
-                    match self
-                        .mac
-                        .handle_rxc::<C, N, D>(&mut self.radio_buffer, &mut self.downlink)?
-                    {
+                    match self.mac.handle_rxc::<C, N, D>(
+                        &mut self.radio_buffer,
+                        &mut self.downlink,
+                        quality,
+                        &mut self.events,
+                    )? {
                         mac::Response::NoUpdate => {
                             debug!("RXC frame was invalid.");
                             self.radio_buffer.clear();
@@ -397,9 +722,10 @@ where
                         }
                     }
                 }
-                RxcWindowResponse::Timeout(_) => return Ok(response),
+                RxcWindowResponse::Timeout(_) => break,
             };
         }
+        self.mac.emit(&mut self.events, MacEvent::RxWindowClosed { window: RxWindow::Rxc });
         Ok(response)
     }
 
@@ -408,61 +734,91 @@ where
     async fn rx_downlink(
         &mut self,
         frame: &Frame,
-        window_delay: u32,
+        tx_done_ms: u32,
     ) -> Result<mac::Response, Error<R::PhyError>> {
         self.radio_buffer.clear();
 
-        let rx1_start_delay = self.mac.get_rx_delay(frame, &Window::_1) + window_delay
-            - self.radio.get_rx_window_lead_time_ms();
+        let (rx1_start, rx_config) = self.get_rx_window(frame, &Window::_1);
+        let rx1_start_delay = rx1_start.saturating_sub(tx_done_ms);
 
         debug!("Starting RX1 in {} ms.", rx1_start_delay);
         // sleep or RXC
         let _ = self.between_windows(rx1_start_delay).await?;
 
         // RX1
-        let rx_config =
-            self.mac.get_rx_config(self.radio.get_rx_window_buffer(), frame, &Window::_1);
         debug!("Configuring RX1 window with config {}.", rx_config);
         self.radio.setup_rx(rx_config).await.map_err(Error::Radio)?;
+        self.mac.emit(
+            &mut self.events,
+            MacEvent::RxWindowOpened { window: RxWindow::Rx1, rf: rx_config.rf },
+        );
 
-        if let Some(response) = self.rx_listen().await? {
+        if let Some(response) = self.rx_listen(RxWindow::Rx1).await? {
             debug!("RX1 received {}", response);
+            self.pending = Pending::Nothing;
             return Ok(response);
         }
 
-        let rx2_start_delay = self.mac.get_rx_delay(frame, &Window::_2) + window_delay
-            - self.radio.get_rx_window_lead_time_ms();
+        let (rx2_start, rx_config) = self.get_rx_window(frame, &Window::_2);
+        let rx2_start_delay = rx2_start.saturating_sub(tx_done_ms);
         debug!("RX1 did not receive anything. Awaiting RX2 for {} ms.", rx2_start_delay);
         // sleep or RXC
         let _ = self.between_windows(rx2_start_delay).await?;
 
         // RX2
-        let rx_config =
-            self.mac.get_rx_config(self.radio.get_rx_window_buffer(), frame, &Window::_2);
         debug!("Configuring RX2 window with config {}.", rx_config);
         self.radio.setup_rx(rx_config).await.map_err(Error::Radio)?;
+        self.mac.emit(
+            &mut self.events,
+            MacEvent::RxWindowOpened { window: RxWindow::Rx2, rf: rx_config.rf },
+        );
 
-        if let Some(response) = self.rx_listen().await? {
+        if let Some(response) = self.rx_listen(RxWindow::Rx2).await? {
             debug!("RX2 received {}", response);
+            self.pending = Pending::Nothing;
             return Ok(response);
         }
         debug!("RX2 did not receive anything.");
+        self.pending = Pending::Nothing;
         Ok(self.mac.rx2_complete())
     }
 
-    async fn rx_listen(&mut self) -> Result<Option<mac::Response>, Error<R::PhyError>> {
+    /// When to start the radio for an RX window, in milliseconds after the timer reset at the end
+    /// of the transmission, and its configuration.
+    fn get_rx_window(&self, frame: &Frame, window: &Window) -> (u32, RxConfig) {
+        self.mac.get_rx_window(frame, window, |sf| mac::RxTiming {
+            wakeup_time_ms: self.radio.get_rx_window_lead_time_ms(),
+            max_rx_error_ms: self.radio.get_rx_window_buffer(),
+            clock_error_ppm: self.timer.clock_error_ppm(),
+            min_rx_symbols: self.radio.get_min_rx_symbols(sf),
+        })
+    }
+
+    async fn rx_listen(
+        &mut self,
+        window: RxWindow,
+    ) -> Result<Option<mac::Response>, Error<R::PhyError>> {
         let response =
             match self.radio.rx_single(self.radio_buffer.as_mut()).await.map_err(Error::Radio)? {
-                RxStatus::Rx(s, _q) => {
+                RxStatus::Rx(s, quality) => {
                     self.radio_buffer.set_pos(s);
-                    match self.mac.handle_rx::<C, N, D>(&mut self.radio_buffer, &mut self.downlink)
-                    {
+                    match self.mac.handle_rx::<C, N, D>(
+                        &mut self.radio_buffer,
+                        &mut self.downlink,
+                        Reception { window, quality },
+                        &mut self.events,
+                    ) {
                         mac::Response::NoUpdate => None,
                         r => Some(r),
                     }
                 }
                 RxStatus::RxTimeout => None,
             };
+        if response.is_some() {
+            // the MAC accounted for the frame
+            self.pending = Pending::Radio;
+        }
+        self.mac.emit(&mut self.events, MacEvent::RxWindowClosed { window });
         self.radio_buffer.clear();
         self.window_complete().await?;
         Ok(response)
@@ -471,11 +827,17 @@ where
     /// When not involved in sending and RX1/RX2 windows, a class C configured device will be
     /// listening to RXC frames. The caller is expected to be awaiting this message at all times.
     pub async fn rxc_listen(&mut self) -> Result<mac::Response, Error<R::PhyError>> {
+        self.recover().await?;
         loop {
-            let (sz, _rx_quality) =
+            let (sz, quality) =
                 self.radio.rx_continuous(self.radio_buffer.as_mut()).await.map_err(Error::Radio)?;
             self.radio_buffer.set_pos(sz);
-            match self.mac.handle_rxc::<C, N, D>(&mut self.radio_buffer, &mut self.downlink)? {
+            match self.mac.handle_rxc::<C, N, D>(
+                &mut self.radio_buffer,
+                &mut self.downlink,
+                quality,
+                &mut self.events,
+            )? {
                 mac::Response::NoUpdate => {
                     self.radio_buffer.clear();
                 }
@@ -488,17 +850,44 @@ where
     }
 }
 
+impl<R, C, T, G, const N: usize, const D: usize, E, P> LorawanDevice
+    for Device<R, C, T, G, N, D, E, P>
+where
+    R: radio::PhyRxTx + Timings,
+    C: CryptoFactory + Default,
+    T: radio::Timer,
+    G: RngCore,
+    E: EventSink,
+    P: ChannelPlan,
+{
+    type Radio = R;
+    type ChannelPlan = P;
+    type EventSink = E;
+
+    impl_lorawan_device!();
+}
+
 /// Allows to fine-tune the beginning and end of the receive windows for a specific board and runtime.
+///
+/// The radio is started early enough and listens long enough to detect
+/// `Self::get_min_rx_symbols` preamble symbols despite a timing error of up to
+/// `Self::get_rx_window_buffer` milliseconds plus the drift of the timer (see
+/// [`Timer::clock_error_ppm`](radio::Timer::clock_error_ppm)), in either direction.
 pub trait Timings {
     /// How many milliseconds before the RX window should the SPI transaction start?
-    /// This value needs to account for the time it takes to wake up the radio and start the SPI transaction, as
-    /// well as any non-deterministic delays in the system.
+    /// This value needs to account for the time it takes to wake up the radio and start the SPI transaction.
     fn get_rx_window_lead_time_ms(&self) -> u32;
 
-    /// Explicitly set the amount of milliseconds to listen before the window starts. By default, the pessimistic assumption
-    /// of `Self::get_rx_window_lead_time_ms` will be used. If you override, be sure that: `Self::get_rx_window_buffer
-    /// < Self::get_rx_window_lead_time_ms`.
+    /// Maximum error (in milliseconds) of the timing of the RX windows regardless of the delay
+    /// since the transmission, such as non-deterministic delays in the system. Defaults to
+    /// `Self::get_rx_window_lead_time_ms`, which is pessimistic.
     fn get_rx_window_buffer(&self) -> u32 {
         self.get_rx_window_lead_time_ms()
     }
+
+    /// Minimum number of preamble symbols the radio needs to detect a frame at the spreading
+    /// factor `sf`. Defaults to 6.
+    fn get_min_rx_symbols(&self, _sf: SpreadingFactor) -> u16 {
+        6
+    }
 }
@@ -1,4 +1,6 @@
-pub use crate::radio::{RfConfig, RxConfig, RxMode, RxQuality, TxConfig};
+pub use crate::radio::{
+    ChannelAssessment, ClearChannelConfig, RfConfig, RxConfig, RxMode, RxQuality, TxConfig,
+};
 
 #[cfg_attr(feature = "defmt-03", derive(defmt::Format))]
 pub struct Error<E>(pub E);
@@ -25,6 +27,13 @@ pub trait Timer {
 
     /// Delay for millis milliseconds
     async fn delay_ms(&mut self, millis: u64);
+
+    /// Worst drift of the clock of the timer, in parts per million. RX windows are widened
+    /// accordingly, which matters for RC oscillators and long delays (eg: join accepts). Defaults
+    /// to 0.
+    fn clock_error_ppm(&self) -> u32 {
+        0
+    }
 }
 
 /// An asynchronous radio implementation that can transmit and receive data.
@@ -40,7 +49,9 @@ pub trait PhyRxTx: Sized {
     const MAX_RADIO_POWER: u8;
 
     /// Transmit data buffer with the given transceiver configuration. The returned future
-    /// should only complete once data have been transmitted.
+    /// should only complete once data have been transmitted, and returns how many milliseconds
+    /// ago the transmission ended (eg: if the TX done interrupt was serviced late), from which
+    /// the RX windows are timed.
     async fn tx(&mut self, config: TxConfig, buf: &[u8]) -> Result<u32, Self::PhyError>;
 
     /// Configures the radio to receive data. This future should not actually await the data itself.
@@ -62,4 +73,16 @@ pub trait PhyRxTx: Sized {
     async fn low_power(&mut self) -> Result<(), Self::PhyError> {
         Ok(())
     }
+
+    /// Senses the channel for `config.sensing_time_us` and reports whether it is clear for
+    /// transmitting. Called before every transmission in regions mandating listen-before-talk
+    /// (eg: KR920) or once enabled with `Device::enable_listen_before_talk`, in which case
+    /// transmissions fail with `Error::ClearChannelUnsupported` if the radio keeps this default
+    /// implementation.
+    async fn clear_channel(
+        &mut self,
+        _config: ClearChannelConfig,
+    ) -> Result<ChannelAssessment, Self::PhyError> {
+        Ok(ChannelAssessment::Unsupported)
+    }
 }
@@ -0,0 +1,211 @@
+//! A [`Runner`] owning a [`Device`] and cloneable [`Client`]s, so that several tasks send uplinks
+//! and receive downlinks while the runner keeps listening for Class C downlinks.
+//!
+//! ```ignore
+//! static CHANNELS: Channels<CriticalSectionRawMutex, RadioError> = Channels::new();
+//!
+//! let (runner, client) = device.split(&CHANNELS);
+//! spawner.spawn(lorawan_task(runner)).unwrap(); // which awaits `runner.run()`
+//! spawner.spawn(sensor_task(client)).unwrap();
+//! spawner.spawn(actuator_task(client)).unwrap();
+//! ```
+use embassy_sync::{
+    blocking_mutex::raw::RawMutex,
+    channel::{Channel, TrySendError},
+    mutex::Mutex,
+    signal::Signal,
+};
+use heapless::Vec;
+
+use super::{radio, Device, Downlink, Error, EventSink, SendResponse, Timings};
+use crate::{events::MacEvent, mac, region::ChannelPlan};
+use lorawan::keys::CryptoFactory;
+use rand_core::RngCore;
+
+/// Largest application payload a [`Client`] can pass to the runner.
+const MAX_UPLINK_LEN: usize = 242;
+
+struct Uplink {
+    id: u32,
+    data: Vec<u8, MAX_UPLINK_LEN>,
+    fport: u8,
+    confirmed: bool,
+}
+
+/// Channels between a [`Runner`] and its [`Client`]s, for a radio failing with `E`. Up to `Q`
+/// downlinks wait for a client to take them; newer ones are dropped while they are not taken.
+///
+/// Typically a `static`, with a `CriticalSectionRawMutex` if the clients run in interrupt
+/// executors, or a `ThreadModeRawMutex` otherwise.
+pub struct Channels<M: RawMutex, E, const Q: usize = 4> {
+    /// Serializes the uplinks of the clients and numbers them.
+    uplink_id: Mutex<M, u32>,
+    uplinks: Channel<M, Uplink, 1>,
+    /// Response to the last uplink, overwritten by the next one if its client went away.
+    responses: Signal<M, (u32, Result<SendResponse, Error<E>>)>,
+    downlinks: Channel<M, Downlink, Q>,
+}
+
+impl<M: RawMutex, E, const Q: usize> Channels<M, E, Q> {
+    pub const fn new() -> Self {
+        Self {
+            uplink_id: Mutex::new(0),
+            uplinks: Channel::new(),
+            responses: Signal::new(),
+            downlinks: Channel::new(),
+        }
+    }
+}
+
+impl<M: RawMutex, E, const Q: usize> Default for Channels<M, E, Q> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<R, C, T, G, const N: usize, const D: usize, E, P> Device<R, C, T, G, N, D, E, P>
+where
+    R: radio::PhyRxTx + Timings,
+    C: CryptoFactory + Default,
+    T: radio::Timer,
+    G: RngCore,
+    E: EventSink,
+    P: ChannelPlan,
+{
+    /// Hands the device over to a [`Runner`], which the [`Client`]s drive through `channels`.
+    /// Join the network before splitting the device.
+    #[allow(clippy::type_complexity)]
+    pub fn split<M: RawMutex, const Q: usize>(
+        self,
+        channels: &Channels<M, R::PhyError, Q>,
+    ) -> (Runner<'_, M, R, C, T, G, N, D, E, P, Q>, Client<'_, M, R::PhyError, Q>) {
+        (Runner { device: self, channels }, Client { channels })
+    }
+}
+
+/// Owns the [`Device`] and sends the uplinks of the [`Client`]s, listening for Class C downlinks
+/// in between if enabled. See [`Device::split`].
+pub struct Runner<'a, M, R, C, T, G, const N: usize, const D: usize, E, P, const Q: usize>
+where
+    M: RawMutex,
+    R: radio::PhyRxTx + Timings,
+    C: CryptoFactory + Default,
+    T: radio::Timer,
+    G: RngCore,
+    E: EventSink,
+    P: ChannelPlan,
+{
+    device: Device<R, C, T, G, N, D, E, P>,
+    channels: &'a Channels<M, R::PhyError, Q>,
+}
+
+impl<M, R, C, T, G, const N: usize, const D: usize, E, P, const Q: usize>
+    Runner<'_, M, R, C, T, G, N, D, E, P, Q>
+where
+    M: RawMutex,
+    R: radio::PhyRxTx + Timings,
+    C: CryptoFactory + Default,
+    T: radio::Timer,
+    G: RngCore,
+    E: EventSink,
+    P: ChannelPlan,
+{
+    /// Runs the device until listening for Class C downlinks fails, which returns the error.
+    /// Errors of uplinks are returned to the client which sent them.
+    pub async fn run(&mut self) -> Error<R::PhyError> {
+        loop {
+            let uplink = match self.next_uplink().await {
+                Ok(uplink) => uplink,
+                Err(e) => return e,
+            };
+            let response = self.device.send(&uplink.data, uplink.fport, uplink.confirmed).await;
+            self.forward_downlinks();
+            self.channels.responses.signal((uplink.id, response));
+        }
+    }
+
+    /// Waits for the next uplink of the clients, receiving Class C downlinks meanwhile.
+    async fn next_uplink(&mut self) -> Result<Uplink, Error<R::PhyError>> {
+        #[cfg(feature = "class-c")]
+        while self.device.class_c {
+            use futures::{future::select, future::Either, pin_mut};
+
+            let listened = {
+                let uplink = self.channels.uplinks.receive();
+                // dropping this future on an uplink requires no cleanup
+                let rxc = self.device.rxc_listen();
+                pin_mut!(uplink, rxc);
+                match select(uplink, rxc).await {
+                    Either::Left((uplink, _)) => return Ok(uplink),
+                    Either::Right((listened, _)) => listened,
+                }
+            };
+            listened?;
+            self.forward_downlinks();
+        }
+        Ok(self.channels.uplinks.receive().await)
+    }
+
+    /// Passes the downlinks queued by the device on to the clients.
+    fn forward_downlinks(&mut self) {
+        while let Some(downlink) = self.device.take_downlink() {
+            if let Err(TrySendError::Full(dropped)) = self.channels.downlinks.try_send(downlink) {
+                self.device.mac.emit(
+                    &mut self.device.events,
+                    MacEvent::DownlinkDropped { fport: dropped.fport },
+                );
+            }
+        }
+    }
+}
+
+/// Sends uplinks through the [`Runner`] and receives the downlinks. Copies share the same
+/// runner. See [`Device::split`].
+pub struct Client<'a, M: RawMutex, E, const Q: usize> {
+    channels: &'a Channels<M, E, Q>,
+}
+
+impl<M: RawMutex, E, const Q: usize> Clone for Client<'_, M, E, Q> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<M: RawMutex, E, const Q: usize> Copy for Client<'_, M, E, Q> {}
+
+impl<M: RawMutex, E, const Q: usize> Client<'_, M, E, Q> {
+    /// Sends data as [`Device::send`] would, once the uplinks of other clients are sent.
+    ///
+    /// The returned future is cancel-safe: if it is dropped, the uplink may still be sent, but
+    /// its response does not reach another client.
+    pub async fn send(
+        &self,
+        data: &[u8],
+        fport: u8,
+        confirmed: bool,
+    ) -> Result<SendResponse, Error<E>> {
+        let data = Vec::from_slice(data)
+            .map_err(|_| Error::Mac(mac::Error::PayloadTooLarge { max: MAX_UPLINK_LEN }))?;
+        let mut uplink_id = self.channels.uplink_id.lock().await;
+        *uplink_id = uplink_id.wrapping_add(1);
+        self.channels.uplinks.send(Uplink { id: *uplink_id, data, fport, confirmed }).await;
+        loop {
+            // skip the response to an uplink whose client went away
+            let (id, response) = self.channels.responses.wait().await;
+            if id == *uplink_id {
+                return response;
+            }
+        }
+    }
+
+    /// Waits for the next downlink, from any receive window. Each downlink is received by a
+    /// single client.
+    pub async fn receive_downlink(&self) -> Downlink {
+        self.channels.downlinks.receive().await
+    }
+
+    /// Takes the oldest downlink not received by a client yet, if any.
+    pub fn take_downlink(&self) -> Option<Downlink> {
+        self.channels.downlinks.try_receive().ok()
+    }
+}
@@ -1,5 +1,6 @@
 use super::*;
 use crate::{
+    events::{EventSink, MacEvent},
     radio::{RxQuality, TxConfig},
     region,
     test_util::*,
@@ -15,7 +16,7 @@ mod radio;
 use radio::TestRadio;
 
 mod util;
-use util::{setup, setup_with_session};
+use util::{setup, setup_with_session, setup_with_session_in};
 
 type Device =
     crate::async_device::Device<TestRadio, DefaultFactory, TestTimer, rand_core::OsRng, 512, 4>;
@@ -89,6 +90,68 @@ async fn test_no_join_accept() {
     }
 }
 
+#[tokio::test]
+async fn test_join_with_retries() {
+    let (radio, timer, mut async_device) = setup();
+    // Run the device
+    let async_device = tokio::spawn(async move {
+        let config = JoinRetryConfig { max_attempts: 2, ..Default::default() };
+        let response = async_device.join_with_retries(&get_otaa_credentials(), config).await;
+        (async_device, response)
+    });
+
+    // First attempt goes unanswered
+    timer.fire_most_recent().await;
+    radio.handle_timeout().await;
+    timer.fire_most_recent().await;
+    radio.handle_timeout().await;
+    // Trigger end of backoff
+    timer.fire_most_recent().await;
+    // Second attempt is accepted in RX1
+    timer.fire_most_recent().await;
+    radio.handle_rxtx(handle_join_request::<3>).await;
+
+    let (device, response) = async_device.await.unwrap();
+    assert!(matches!(response, Ok(JoinResponse::JoinSuccess)));
+    assert_eq!(4, timer.get_armed_count().await);
+    assert_eq!(device.get_statistics().join_attempts(), 2);
+}
+
+#[tokio::test]
+async fn test_join_with_retries_exhausted() {
+    let (radio, timer, mut async_device) = setup();
+    // Run the device
+    let async_device = tokio::spawn(async move {
+        let config = JoinRetryConfig { max_attempts: 1, ..Default::default() };
+        async_device.join_with_retries(&get_otaa_credentials(), config).await
+    });
+
+    timer.fire_most_recent().await;
+    radio.handle_timeout().await;
+    timer.fire_most_recent().await;
+    radio.handle_timeout().await;
+
+    let response = async_device.await.unwrap();
+    assert!(matches!(response, Ok(JoinResponse::NoJoinAccept)));
+    assert_eq!(2, timer.get_armed_count().await);
+}
+
+#[tokio::test]
+async fn test_restore_session() {
+    let (radio, timer, mut async_device) = setup();
+    restore_session(&mut async_device);
+    // the restored frame counter is used by the next uplink
+    let async_device = tokio::spawn(async move {
+        async_device.send(&[1, 2, 3], 3, false).await.unwrap();
+        async_device
+    });
+    timer.fire_most_recent().await;
+    radio.handle_timeout().await;
+    timer.fire_most_recent().await;
+    radio.handle_timeout().await;
+    assert_eq!(async_device.await.unwrap().get_fcnt_up(), Some(8));
+}
+
 #[tokio::test]
 async fn test_unconfirmed_uplink_no_downlink() {
     let (radio, timer, mut async_device) = setup_with_session();
@@ -215,6 +278,235 @@ async fn test_confirmed_uplink_with_ack_rx2() {
     }
 }
 
+#[tokio::test]
+async fn test_send_with_deadline() {
+    use crate::async_device::radio::Timer as _;
+    let (radio, timer, mut device) = setup_with_session();
+    let mut deadline_timer = timer.timer();
+    let task = tokio::spawn(async move {
+        // armed once the uplink waits for RX1
+        let deadline = deadline_timer.at(0);
+        let response = device.send_with_deadline(&[1, 2, 3], 3, false, deadline).await;
+        (device, response)
+    });
+    // the deadline expires before RX1
+    timer.fire_most_recent().await;
+    let (mut device, response) = task.await.unwrap();
+    assert!(matches!(response, Err(Error::DeadlineExpired)));
+    // the frame may have been sent: its FCnt is consumed
+    assert_eq!(device.get_fcnt_up(), Some(1));
+    assert!(device.get_radio().is_low_power());
+
+    // the device carries on
+    let task = tokio::spawn(async move {
+        let response = device.send(&[1, 2, 3], 3, false).await;
+        (device, response)
+    });
+    timer.fire_most_recent().await;
+    radio.handle_timeout().await;
+    timer.fire_most_recent().await;
+    radio.handle_timeout().await;
+    let (device, response) = task.await.unwrap();
+    assert!(matches!(response, Ok(SendResponse::RxComplete)));
+    assert_eq!(device.get_fcnt_up(), Some(2));
+}
+
+#[cfg(feature = "class-c")]
+#[tokio::test]
+async fn test_class_c_send_cancelled() {
+    let (_radio, _timer, mut device) = util::setup_with_session_class_c().await;
+    assert_eq!(device.get_fcnt_up(), Some(1));
+    // drop the future while waiting for RX1
+    use futures::FutureExt;
+    assert!(device.send(&[1, 2, 3], 3, false).now_or_never().is_none());
+    device.recover().await.unwrap();
+    assert_eq!(device.get_fcnt_up(), Some(2));
+    // back to listening for Class C downlinks
+    let rx_config = device.get_radio().current_config().unwrap();
+    assert_eq!(rx_config.mode, crate::radio::RxMode::Continuous);
+    assert!(!device.get_radio().is_low_power());
+}
+
+#[cfg(feature = "embassy-sync")]
+#[tokio::test]
+async fn test_split() {
+    use embassy_sync::blocking_mutex::raw::NoopRawMutex;
+    use futures::{future::select, future::Either, pin_mut};
+    use tokio::join;
+
+    let (radio, timer, device) = setup_with_session();
+    let channels = split::Channels::<NoopRawMutex, _, 2>::new();
+    let (mut runner, client) = device.split(&channels);
+    let other_client = client;
+    let clients = async {
+        let rx_windows = async {
+            timer.fire_most_recent().await;
+            radio.handle_timeout().await;
+            timer.fire_most_recent().await;
+            radio.handle_timeout().await;
+        };
+        let (response, ()) = join!(client.send(&[1, 2, 3], 3, false), rx_windows);
+        assert!(matches!(response, Ok(SendResponse::RxComplete)));
+        assert!(client.take_downlink().is_none());
+
+        let rx_windows = async {
+            timer.fire_most_recent().await;
+            radio.handle_rxtx(handle_data_uplink_with_link_adr_req::<1, 0>).await;
+        };
+        let (response, ()) = join!(other_client.send(&[1, 2, 3], 3, true), rx_windows);
+        assert!(matches!(response, Ok(SendResponse::DownlinkReceived(0))));
+        // any client receives the downlink
+        let downlink = client.receive_downlink().await;
+        assert_eq!((downlink.fport, downlink.window), (4, RxWindow::Rx1));
+    };
+    let run = runner.run();
+    pin_mut!(run, clients);
+    assert!(matches!(select(run, clients).await, Either::Right(_)));
+}
+
+#[cfg(all(feature = "embassy-sync", feature = "class-c"))]
+#[tokio::test]
+async fn test_split_class_c() {
+    use embassy_sync::blocking_mutex::raw::NoopRawMutex;
+    use futures::{future::select, future::Either, pin_mut};
+    use tokio::join;
+
+    let (radio, timer, device) = util::setup_with_session_class_c().await;
+    let channels = split::Channels::<NoopRawMutex, _>::new();
+    let (mut runner, client) = device.split(&channels);
+    let clients = async {
+        // the runner listens for Class C downlinks while no uplink is pending
+        radio.handle_rxtx(class_c_downlink::<1>).await;
+        let downlink = client.receive_downlink().await;
+        assert_eq!((downlink.fcnt_down, downlink.window), (1, RxWindow::Rxc));
+
+        let rx_windows = async {
+            timer.fire_most_recent().await;
+            radio.handle_timeout().await;
+            timer.fire_most_recent().await;
+            radio.handle_timeout().await;
+        };
+        let (response, ()) = join!(client.send(&[1, 2, 3], 3, false), rx_windows);
+        assert!(matches!(response, Ok(SendResponse::RxComplete)));
+
+        // and after the uplink
+        radio.handle_rxtx(class_c_downlink::<2>).await;
+        let downlink = client.receive_downlink().await;
+        assert_eq!((downlink.fcnt_down, downlink.window), (2, RxWindow::Rxc));
+    };
+    let run = runner.run();
+    pin_mut!(run, clients);
+    assert!(matches!(select(run, clients).await, Either::Right(_)));
+}
+
+#[cfg(all(feature = "embassy-sync", feature = "class-c"))]
+#[tokio::test]
+async fn test_split_send_dropped() {
+    use embassy_sync::blocking_mutex::raw::NoopRawMutex;
+    use futures::{future::select, future::Either, pin_mut, FutureExt};
+
+    let (radio, timer, device) = util::setup_with_session_class_c().await;
+    let channels = split::Channels::<NoopRawMutex, _>::new();
+    let (mut runner, client) = device.split(&channels);
+    let clients = async {
+        // the responses of both uplinks are left to the runner
+        for _ in 0..2 {
+            assert!(client.send(&[1, 2, 3], 3, false).now_or_never().is_none());
+            timer.fire_most_recent().await;
+            radio.handle_timeout().await;
+            timer.fire_most_recent().await;
+            radio.handle_timeout().await;
+        }
+        // which still listens for Class C downlinks
+        radio.handle_rxtx(class_c_downlink::<1>).await;
+        let downlink = client.receive_downlink().await;
+        assert_eq!((downlink.fcnt_down, downlink.window), (1, RxWindow::Rxc));
+    };
+    let run = runner.run();
+    pin_mut!(run, clients);
+    assert!(matches!(select(run, clients).await, Either::Right(_)));
+}
+
+#[tokio::test]
+async fn test_uplink_payload_too_large() {
+    let (_radio, _timer, mut async_device) = setup_with_session();
+    // US915 DR0 allows a MACPayload of 19 bytes
+    assert_eq!(async_device.max_payload_len(), 11);
+    match async_device.send(&[0; 12], 3, false).await {
+        Err(Error::Mac(mac::Error::PayloadTooLarge { max: 11 })) => (),
+        r => panic!("Unexpected response: {r:?}"),
+    }
+    assert_eq!(async_device.get_session().unwrap().fcnt_up, 0);
+}
+
+#[derive(Default)]
+struct EventLog(std::vec::Vec<MacEvent>);
+
+impl EventSink for EventLog {
+    fn event(&mut self, event: MacEvent) {
+        self.0.push(event);
+    }
+}
+
+#[tokio::test]
+async fn test_events_confirmed_uplink_with_ack_rx1() {
+    let (radio, timer, async_device) = setup_with_session();
+    let mut async_device = async_device.with_event_sink(EventLog::default());
+
+    // Run the device
+    let task = tokio::spawn(async move {
+        let response = async_device.send(&[1, 2, 3], 3, true).await;
+        (async_device, response)
+    });
+    // Trigger beginning of RX1
+    timer.fire_most_recent().await;
+    // Send a downlink with confirmation
+    radio.handle_rxtx(handle_data_uplink_with_link_adr_req::<0, 0>).await;
+
+    let (mut device, response) = task.await.unwrap();
+    assert!(matches!(response, Ok(SendResponse::DownlinkReceived(0))));
+    let events = &device.get_event_sink().0;
+    assert!(
+        matches!(events[0], MacEvent::UplinkSent { fcnt_up: 0, time_on_air_us, .. } if time_on_air_us > 0)
+    );
+    assert!(matches!(events[1], MacEvent::RxWindowOpened { window: RxWindow::Rx1, .. }));
+    assert!(events.contains(&MacEvent::MacCommandApplied { cid: 0x03 }));
+    assert!(events.iter().any(|e| matches!(
+        e,
+        MacEvent::DownlinkReceived { window: RxWindow::Rx1, fcnt_down: 0, .. }
+    )));
+    assert_eq!(events.last(), Some(&MacEvent::RxWindowClosed { window: RxWindow::Rx1 }));
+
+    let stats = device.get_statistics();
+    assert_eq!(stats.uplinks(), 1);
+    assert!(stats.time_on_air_us() > 0);
+    assert_eq!(stats.downlinks_in(RxWindow::Rx1), 1);
+    assert_eq!(stats.ack_rate(), Some(1.0));
+    device.reset_statistics();
+    assert_eq!(device.get_statistics(), &mac::Statistics::default());
+}
+
+#[tokio::test]
+async fn test_statistics_downlink_without_ack() {
+    let (radio, timer, mut async_device) = setup_with_session();
+
+    // Run the device
+    let task = tokio::spawn(async move {
+        let response = async_device.send(&[1, 2, 3], 3, true).await;
+        (async_device, response)
+    });
+    // Trigger beginning of RX1
+    timer.fire_most_recent().await;
+    // Send a downlink which does not acknowledge the uplink
+    radio.handle_rxtx(handle_data_uplink_without_ack).await;
+
+    let (device, response) = task.await.unwrap();
+    assert!(matches!(response, Ok(SendResponse::DownlinkReceived(0))));
+    let stats = device.get_statistics();
+    assert_eq!(stats.downlinks_in(RxWindow::Rx1), 1);
+    assert_eq!(stats.ack_rate(), Some(0.0));
+}
+
 #[tokio::test]
 async fn test_link_adr_ans() {
     let (radio, timer, mut async_device) = setup_with_session();
@@ -234,7 +526,7 @@ async fn test_link_adr_ans() {
     timer.fire_most_recent().await;
     // Send a downlink with confirmation
     radio.handle_rxtx(handle_data_uplink_with_link_adr_req::<0, 0>).await;
-    tokio::time::sleep(tokio::time::Duration::from_millis(15)).await;
+    timer.wait_armed_count(2).await;
     assert!(*send_await_complete.lock().await);
     // at this point, the device thread should be sending the second frame
     // Trigger beginning of RX1
@@ -249,6 +541,125 @@ async fn test_link_adr_ans() {
     }
 }
 
+/// Restores a session whose next uplink answers two LinkADRReqs.
+fn set_session_with_link_adr_ans(device: &mut Device) {
+    let mut uplink = mac::uplink::Uplink::default();
+    uplink.ack_link_adr(0b111);
+    uplink.ack_link_adr(0b111);
+    device.set_session(Session {
+        nwkskey: crate::NwkSKey::from(get_key()),
+        appskey: crate::AppSKey::from(get_key()),
+        devaddr: get_dev_addr(),
+        fcnt_up: 0,
+        fcnt_down: 0,
+        confirmed: false,
+        uplink,
+    });
+}
+
+#[tokio::test]
+async fn test_link_adr_ans_kept_without_channel() {
+    let (radio, timer, mut async_device) = setup();
+    set_session_with_link_adr_ans(&mut async_device);
+    // all 125 kHz channels off
+    async_device.mac.region.set_channel_mask(7, region::ChannelMask::new(&[0, 0]).unwrap());
+    let response = async_device.send(&[1, 2, 3], 3, false).await;
+    assert!(matches!(response, Err(Error::Mac(mac::Error::NoChannelAvailable))));
+
+    // the answers go out with the next uplink, on a 500 kHz channel
+    async_device.set_datarate(DR::_4);
+    let async_device = tokio::spawn(async move { async_device.send(&[1, 2, 3], 3, false).await });
+    timer.fire_most_recent().await;
+    radio.handle_rxtx(handle_data_uplink_with_link_adr_ans).await;
+    assert!(matches!(async_device.await.unwrap(), Ok(SendResponse::DownlinkReceived(1))));
+}
+
+#[tokio::test]
+async fn test_tx_power() {
+    let (radio, timer, mut async_device) = setup_with_session();
+    let async_device = tokio::spawn(async move {
+        async_device.send(&[1, 2, 3], 3, false).await.unwrap();
+        async_device.send(&[1, 2, 3], 3, false).await
+    });
+    // MaxEIRP of US915 is 30 dBm, which the radio limits to 26 dBm
+    timer.fire_most_recent().await;
+    radio.handle_rxtx(handle_data_uplink_with_tx_power_req::<5>).await;
+    timer.wait_armed_count(2).await;
+    // TXPower 5 is 10 dB below MaxEIRP
+    timer.fire_most_recent().await;
+    radio.handle_rxtx(handle_data_uplink_with_tx_power_ans::<true, 20>).await;
+    assert!(matches!(async_device.await.unwrap(), Ok(SendResponse::DownlinkReceived(1))));
+}
+
+#[tokio::test]
+async fn test_tx_power_rejected() {
+    let (radio, timer, mut async_device) =
+        setup_with_session_in(region::Configuration::new(region::Region::KR920));
+    let async_device = tokio::spawn(async move {
+        async_device.send(&[1, 2, 3], 3, false).await.unwrap();
+        async_device.send(&[1, 2, 3], 3, false).await
+    });
+    // KR920 only defines TXPower 0 to 7: the LinkADRReq is not applied
+    timer.fire_most_recent().await;
+    radio.handle_rxtx(handle_data_uplink_with_tx_power_req::<9>).await;
+    timer.wait_armed_count(2).await;
+    timer.fire_most_recent().await;
+    radio.handle_rxtx(handle_data_uplink_with_tx_power_ans::<false, 14>).await;
+    assert!(matches!(async_device.await.unwrap(), Ok(SendResponse::DownlinkReceived(1))));
+}
+
+#[tokio::test]
+async fn test_rx_param_setup() {
+    let (radio, timer, mut async_device) = setup_with_session();
+    let async_device = tokio::spawn(async move {
+        async_device.send(&[1, 2, 3], 3, false).await.unwrap();
+        let response = async_device.send(&[1, 2, 3], 3, false).await;
+        (async_device, response)
+    });
+    // Trigger beginning of RX1
+    timer.fire_most_recent().await;
+    radio.handle_rxtx(handle_data_uplink_with_rx_param_setup_req).await;
+    timer.wait_armed_count(2).await;
+    // Trigger beginning of RX1 of the second uplink, which carries the RXParamSetupAns
+    timer.fire_most_recent().await;
+    radio.handle_rxtx(handle_data_uplink_with_rx_param_setup_ans).await;
+    let (device, response) = async_device.await.unwrap();
+    assert!(matches!(response, Ok(SendResponse::DownlinkReceived(1))));
+
+    let rx2 = device.get_region().get_rx_config(DR::_0, &Frame::Data, &Window::_2);
+    assert_eq!(rx2.frequency, 923_900_000);
+    assert_eq!(rx2.bb.sf, region::SpreadingFactor::_10);
+}
+
+#[tokio::test]
+async fn test_rx_param_setup_ans_until_downlink() {
+    let (radio, timer, mut async_device) = setup_with_session();
+    let async_device = tokio::spawn(async move {
+        async_device.send(&[1, 2, 3], 3, false).await.unwrap();
+        let response = async_device.send(&[1, 2, 3], 3, false).await;
+        assert!(matches!(response, Ok(SendResponse::RxComplete)));
+        async_device.send(&[1, 2, 3], 3, false).await.unwrap();
+        async_device.send(&[1, 2, 3], 3, false).await
+    });
+    timer.fire_most_recent().await;
+    radio.handle_rxtx(handle_data_uplink_with_rx_param_setup_req).await;
+    timer.wait_armed_count(2).await;
+    // No downlink follows the second uplink
+    timer.fire_most_recent().await;
+    radio.handle_timeout().await;
+    timer.fire_most_recent().await;
+    radio.handle_timeout().await;
+    timer.wait_armed_count(4).await;
+    // The third uplink carries the RXParamSetupAns again
+    timer.fire_most_recent().await;
+    radio.handle_rxtx(handle_data_uplink_with_rx_param_setup_ans).await;
+    timer.wait_armed_count(5).await;
+    // Once a downlink is received, the answer is no longer sent
+    timer.fire_most_recent().await;
+    radio.handle_rxtx(handle_data_uplink_without_mac_cmds::<2>).await;
+    assert!(matches!(async_device.await.unwrap(), Ok(SendResponse::DownlinkReceived(2))));
+}
+
 #[tokio::test]
 #[cfg(feature = "class-c")]
 async fn test_class_c_data_before_rx1() {
@@ -273,8 +684,10 @@ async fn test_class_c_data_before_rx1() {
             panic!()
         }
     }
-    let _ = device.take_downlink().unwrap();
-    let _ = device.take_downlink().unwrap();
+    // Downlinks are taken in the order they were received: RXC first, then RX2
+    assert_eq!(device.take_downlink().unwrap().fport, 3);
+    assert_eq!(device.take_downlink().unwrap().fport, 4);
+    assert!(device.take_downlink().is_none());
 }
 
 #[tokio::test]
@@ -306,8 +719,15 @@ async fn test_class_c_data_before_rx2() {
             panic!()
         }
     }
-    let _ = device.take_downlink().unwrap();
-    let _ = device.take_downlink().unwrap();
+    // Downlinks are taken in the order they were received: RXC first, then RX2
+    let downlink = device.take_downlink().unwrap();
+    assert_eq!((downlink.fport, downlink.window, downlink.fcnt_down), (3, RxWindow::Rxc, 1));
+    assert!(!downlink.ack);
+    let downlink = device.take_downlink().unwrap();
+    assert_eq!((downlink.fport, downlink.window, downlink.fcnt_down), (4, RxWindow::Rx2, 2));
+    assert!(downlink.confirmed);
+    assert!(downlink.ack);
+    assert!(device.take_downlink().is_none());
 }
 
 #[tokio::test]
@@ -330,3 +750,203 @@ async fn test_class_c_async_down() {
     }
     let _ = device.take_downlink().unwrap();
 }
+
+#[tokio::test]
+async fn test_kr920_listen_before_talk() {
+    let (radio, timer, mut async_device) =
+        setup_with_session_in(region::Configuration::new(region::Region::KR920));
+    async_device.get_mut_radio().set_channel_busy(true);
+    let response = async_device.send(&[1, 2, 3], 3, false).await;
+    assert!(matches!(response, Err(Error::ChannelBusy)));
+    let lbt = async_device.get_radio().last_clear_channel().unwrap();
+    assert_eq!(lbt.threshold_dbm, -80);
+    assert_eq!(lbt.sensing_time_us, 5000);
+    // every attempt moved to another channel
+    let frequencies = async_device.get_radio().clear_channel_frequencies();
+    assert_eq!(frequencies.len(), 3);
+    assert!(frequencies.windows(2).all(|pair| pair[0] != pair[1]));
+    assert_eq!(async_device.get_statistics().uplinks(), 0);
+
+    // the channel found busy is left for another one
+    async_device.get_mut_radio().set_channel_busy_for(1);
+    let async_device = tokio::spawn(async move {
+        let response = async_device.send(&[1, 2, 3], 3, false).await;
+        (async_device, response)
+    });
+    timer.fire_most_recent().await;
+    radio.handle_timeout().await;
+    timer.fire_most_recent().await;
+    radio.handle_timeout().await;
+
+    let (device, response) = async_device.await.unwrap();
+    assert!(matches!(response, Ok(SendResponse::RxComplete)));
+    let frequency = device.get_statistics().channels()[0].frequency;
+    assert!([922_100_000, 922_300_000, 922_500_000].contains(&frequency));
+    let frequencies = &device.get_radio().clear_channel_frequencies()[3..];
+    assert_eq!(frequencies.len(), 2);
+    assert_ne!(frequencies[0], frequency);
+    assert_eq!(frequencies[1], frequency);
+}
+
+#[tokio::test]
+async fn test_link_adr_ans_kept_when_channel_busy() {
+    let (radio, timer, mut async_device) = setup();
+    set_session_with_link_adr_ans(&mut async_device);
+    async_device.enable_listen_before_talk(-80, 5000);
+    async_device.get_mut_radio().set_channel_busy(true);
+    let response = async_device.send(&[1, 2, 3], 3, false).await;
+    assert!(matches!(response, Err(Error::ChannelBusy)));
+
+    // the answers go out with the next uplink
+    async_device.get_mut_radio().set_channel_busy(false);
+    let async_device = tokio::spawn(async move { async_device.send(&[1, 2, 3], 3, false).await });
+    timer.fire_most_recent().await;
+    radio.handle_rxtx(handle_data_uplink_with_link_adr_ans).await;
+    assert!(matches!(async_device.await.unwrap(), Ok(SendResponse::DownlinkReceived(1))));
+}
+
+#[tokio::test]
+async fn test_enable_listen_before_talk() {
+    let (radio, timer, mut async_device) = setup_with_session();
+    async_device.enable_listen_before_talk(-80, 5000);
+    async_device.get_mut_radio().set_channel_busy(true);
+    let response = async_device.send(&[1, 2, 3], 3, false).await;
+    assert!(matches!(response, Err(Error::ChannelBusy)));
+    let lbt = async_device.get_radio().last_clear_channel().unwrap();
+    assert_eq!(lbt.threshold_dbm, -80);
+    assert_eq!(lbt.sensing_time_us, 5000);
+
+    // US915 does not mandate listen-before-talk
+    async_device.disable_listen_before_talk();
+    let async_device = tokio::spawn(async move {
+        let response = async_device.send(&[1, 2, 3], 3, false).await;
+        (async_device, response)
+    });
+    timer.fire_most_recent().await;
+    radio.handle_timeout().await;
+    timer.fire_most_recent().await;
+    radio.handle_timeout().await;
+
+    let (device, response) = async_device.await.unwrap();
+    assert!(matches!(response, Ok(SendResponse::RxComplete)));
+    assert_eq!(device.get_radio().clear_channel_frequencies().len(), 3);
+}
+
+/// A single channel plan, such as used with a single channel gateway.
+struct SingleChannel;
+
+impl SingleChannel {
+    const FREQUENCY: u32 = 868_100_000;
+
+    fn rf() -> crate::radio::RfConfig {
+        crate::radio::RfConfig {
+            frequency: Self::FREQUENCY,
+            bb: region::BaseBandModulationParams::new(
+                region::SpreadingFactor::_9,
+                region::Bandwidth::_125KHz,
+                region::CodingRate::_4_5,
+            ),
+        }
+    }
+}
+
+impl region::ChannelPlan for SingleChannel {
+    fn get_max_payload_length(&self, _: DR, _: bool, _: bool) -> u8 {
+        123
+    }
+
+    fn create_tx_config<RNG: rand_core::RngCore>(
+        &mut self,
+        _: &mut RNG,
+        _: DR,
+        _: u8,
+        _: &Frame,
+    ) -> Result<TxConfig, region::NoChannelAvailable> {
+        Ok(TxConfig { pw: 14, rf: Self::rf() })
+    }
+
+    fn get_rx_config(&self, _: DR, _: &Frame, _: &Window) -> crate::radio::RfConfig {
+        Self::rf()
+    }
+
+    fn process_join_accept(&mut self, _: Option<region::CfList<'_>>) {}
+
+    fn set_channel_mask(&mut self, _: u8, _: region::ChannelMask<2>) {}
+
+    fn get_channel_mask(&self) -> [u8; 9] {
+        [1, 0, 0, 0, 0, 0, 0, 0, 0]
+    }
+}
+
+fn handle_single_channel_join_request(
+    uplink: Option<Uplink>,
+    config: crate::radio::RfConfig,
+    rx_buffer: &mut [u8],
+) -> usize {
+    assert_eq!(config, SingleChannel::rf());
+    handle_join_request::<3>(uplink, config, rx_buffer)
+}
+
+#[tokio::test]
+async fn test_join_custom_channel_plan() {
+    let (radio_channel, radio) = TestRadio::new();
+    let (timer_channel, timer) = TestTimer::new();
+    let mut async_device: crate::async_device::Device<
+        TestRadio,
+        DefaultFactory,
+        TestTimer,
+        rand_core::OsRng,
+        512,
+        4,
+        (),
+        SingleChannel,
+    > = crate::async_device::Device::new_with_channel_plan(
+        SingleChannel,
+        radio,
+        timer,
+        rand_core::OsRng,
+        None,
+    );
+    let async_device = tokio::spawn(async move {
+        let response = async_device.join(&get_otaa_credentials()).await;
+        (async_device, response)
+    });
+    timer_channel.fire_most_recent().await;
+    radio_channel.handle_rxtx(handle_single_channel_join_request).await;
+
+    let (device, response) = async_device.await.unwrap();
+    assert!(matches!(response, Ok(JoinResponse::JoinSuccess)));
+    assert_eq!(device.get_statistics().channels()[0].frequency, SingleChannel::FREQUENCY);
+}
+
+#[tokio::test]
+async fn test_join_single_region() {
+    let (radio_channel, radio) = TestRadio::new();
+    let (timer_channel, timer) = TestTimer::new();
+    let mut async_device: crate::async_device::Device<
+        TestRadio,
+        DefaultFactory,
+        TestTimer,
+        rand_core::OsRng,
+        512,
+        4,
+        (),
+        region::US915,
+    > = crate::async_device::Device::new_with_channel_plan(
+        region::US915::default(),
+        radio,
+        timer,
+        rand_core::OsRng,
+        None,
+    );
+    let async_device = tokio::spawn(async move {
+        let response = async_device.join(&get_otaa_credentials()).await;
+        (async_device, response)
+    });
+    timer_channel.fire_most_recent().await;
+    radio_channel.handle_rxtx(handle_join_request::<3>).await;
+
+    let (device, response) = async_device.await.unwrap();
+    assert!(matches!(response, Ok(JoinResponse::JoinSuccess)));
+    assert!(device.get_session().is_some());
+}
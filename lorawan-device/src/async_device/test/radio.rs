@@ -1,5 +1,7 @@
 use super::*;
-use crate::async_device::radio::{PhyRxTx, RxConfig, RxStatus};
+use crate::async_device::radio::{
+    ChannelAssessment, ClearChannelConfig, PhyRxTx, RxConfig, RxStatus,
+};
 use std::sync::Arc;
 use tokio::{
     sync::{mpsc, Mutex},
@@ -11,9 +13,49 @@ impl TestRadio {
         let last_uplink = Arc::new(Mutex::new(None));
         (
             RadioChannel { tx, last_uplink: last_uplink.clone() },
-            Self { rx, last_uplink, current_config: None },
+            Self {
+                rx,
+                last_uplink,
+                current_config: None,
+                busy_assessments: 0,
+                last_clear_channel: None,
+                clear_channel_frequencies: Vec::new(),
+                low_power: false,
+            },
         )
     }
+
+    pub fn set_channel_busy(&mut self, busy: bool) {
+        self.busy_assessments = if busy {
+            usize::MAX
+        } else {
+            0
+        };
+    }
+
+    /// Only the next `count` clear-channel assessments find the channel busy.
+    pub fn set_channel_busy_for(&mut self, count: usize) {
+        self.busy_assessments = count;
+    }
+
+    pub fn last_clear_channel(&self) -> Option<ClearChannelConfig> {
+        self.last_clear_channel
+    }
+
+    /// Frequencies of every clear-channel assessment, in order.
+    pub fn clear_channel_frequencies(&self) -> &[u32] {
+        &self.clear_channel_frequencies
+    }
+
+    /// Configuration of the last RX set up.
+    pub fn current_config(&self) -> Option<RxConfig> {
+        self.current_config
+    }
+
+    /// Whether the radio was put to low power since it last transmitted or received.
+    pub fn is_low_power(&self) -> bool {
+        self.low_power
+    }
 }
 
 #[derive(Debug)]
@@ -26,6 +68,10 @@ pub struct TestRadio {
     current_config: Option<RxConfig>,
     last_uplink: Arc<Mutex<Option<Uplink>>>,
     rx: mpsc::Receiver<Msg>,
+    busy_assessments: usize,
+    last_clear_channel: Option<ClearChannelConfig>,
+    clear_channel_frequencies: Vec<u32>,
+    low_power: bool,
 }
 
 impl PhyRxTx for TestRadio {
@@ -36,18 +82,38 @@ impl PhyRxTx for TestRadio {
     const ANTENNA_GAIN: i8 = 0;
 
     async fn tx(&mut self, config: TxConfig, buffer: &[u8]) -> Result<u32, Self::PhyError> {
-        let length = buffer.len();
         // stash the uplink, to be consumed by channel or by rx handler
+        self.low_power = false;
         let mut last_uplink = self.last_uplink.lock().await;
         *last_uplink = Some(Uplink::new(buffer, config).map_err(|_| "Parse error")?);
-        Ok(length as u32)
+        Ok(0)
+    }
+
+    async fn clear_channel(
+        &mut self,
+        config: ClearChannelConfig,
+    ) -> Result<ChannelAssessment, Self::PhyError> {
+        self.last_clear_channel = Some(config);
+        self.clear_channel_frequencies.push(config.rf.frequency);
+        if self.busy_assessments > 0 {
+            self.busy_assessments -= 1;
+            Ok(ChannelAssessment::Busy)
+        } else {
+            Ok(ChannelAssessment::Clear)
+        }
     }
 
     async fn setup_rx(&mut self, config: RxConfig) -> Result<(), Self::PhyError> {
+        self.low_power = false;
         self.current_config = Some(config);
         Ok(())
     }
 
+    async fn low_power(&mut self) -> Result<(), Self::PhyError> {
+        self.low_power = true;
+        Ok(())
+    }
+
     async fn rx_continuous(
         &mut self,
         rx_buf: &mut [u8],
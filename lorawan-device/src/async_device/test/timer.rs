@@ -1,14 +1,15 @@
 use crate::async_device::radio::Timer;
 use std::{collections::HashMap, sync::Arc};
-use tokio::sync::{mpsc, Mutex};
+use tokio::sync::{mpsc, Mutex, Notify};
 
 impl TestTimer {
     pub fn new() -> (TimerChannel, Self) {
         let tx = Arc::new(Mutex::new(HashMap::new()));
         let armed_count = Arc::new(Mutex::new(0));
+        let armed = Arc::new(Notify::new());
         (
-            TimerChannel { tx: tx.clone(), armed_count: armed_count.clone() },
-            Self { tx, armed_count },
+            TimerChannel { tx: tx.clone(), armed_count: armed_count.clone(), armed: armed.clone() },
+            Self { tx, armed_count, armed },
         )
     }
 }
@@ -16,6 +17,7 @@ impl TestTimer {
 pub struct TestTimer {
     armed_count: Arc<Mutex<usize>>,
     tx: Arc<Mutex<HashMap<usize, mpsc::Sender<()>>>>,
+    armed: Arc<Notify>,
 }
 
 impl TestTimer {
@@ -26,6 +28,7 @@ impl TestTimer {
             let mut tx_map = self.tx.lock().await;
             tx_map.insert(*self.armed_count.lock().await, tx);
         }
+        self.armed.notify_waiters();
         rx.recv().await;
     }
 }
@@ -46,6 +49,7 @@ impl Timer for TestTimer {
 pub struct TimerChannel {
     armed_count: Arc<Mutex<usize>>,
     tx: Arc<Mutex<HashMap<usize, mpsc::Sender<()>>>>,
+    armed: Arc<Notify>,
 }
 
 impl TimerChannel {
@@ -67,6 +71,26 @@ impl TimerChannel {
         }
     }
 
+    /// Another timer fired through this channel, such as for a deadline.
+    pub fn timer(&self) -> TestTimer {
+        TestTimer {
+            tx: self.tx.clone(),
+            armed_count: self.armed_count.clone(),
+            armed: self.armed.clone(),
+        }
+    }
+
+    /// Waits until the timer was armed `count` times in total, eg: by the next operation.
+    pub async fn wait_armed_count(&self, count: usize) {
+        loop {
+            let armed = self.armed.notified();
+            if *self.armed_count.lock().await >= count {
+                return;
+            }
+            armed.await;
+        }
+    }
+
     pub async fn get_armed_count(&self) -> usize {
         *self.armed_count.lock().await
     }
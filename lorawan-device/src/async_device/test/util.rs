@@ -3,30 +3,36 @@ use super::{get_dev_addr, get_key, radio::*, region, timer::*, Device};
 use crate::mac::Session;
 use crate::{AppSKey, NwkSKey};
 
-fn setup_internal(session_data: Option<Session>) -> (RadioChannel, TimerChannel, Device) {
+fn setup_internal(
+    region: region::Configuration,
+    session_data: Option<Session>,
+) -> (RadioChannel, TimerChannel, Device) {
     let (radio_channel, mock_radio) = TestRadio::new();
     let (timer_channel, mock_timer) = TestTimer::new();
-    let region = region::US915::default();
-    let async_device = Device::new_with_session(
-        region.into(),
-        mock_radio,
-        mock_timer,
-        rand::rngs::OsRng,
-        session_data,
-    );
+    let async_device =
+        Device::new_with_session(region, mock_radio, mock_timer, rand::rngs::OsRng, session_data);
     (radio_channel, timer_channel, async_device)
 }
 
 pub fn setup_with_session() -> (RadioChannel, TimerChannel, Device) {
-    setup_internal(Some(Session {
-        nwkskey: NwkSKey::from(get_key()),
-        appskey: AppSKey::from(get_key()),
-        devaddr: get_dev_addr(),
-        fcnt_up: 0,
-        fcnt_down: 0,
-        confirmed: false,
-        uplink: Default::default(),
-    }))
+    setup_with_session_in(region::US915::default().into())
+}
+
+pub fn setup_with_session_in(
+    region: region::Configuration,
+) -> (RadioChannel, TimerChannel, Device) {
+    setup_internal(
+        region,
+        Some(Session {
+            nwkskey: NwkSKey::from(get_key()),
+            appskey: AppSKey::from(get_key()),
+            devaddr: get_dev_addr(),
+            fcnt_up: 0,
+            fcnt_down: 0,
+            confirmed: false,
+            uplink: Default::default(),
+        }),
+    )
 }
 
 #[cfg(feature = "class-c")]
@@ -55,5 +61,5 @@ pub async fn setup_with_session_class_c() -> (RadioChannel, TimerChannel, Device
 }
 
 pub fn setup() -> (RadioChannel, TimerChannel, Device) {
-    setup_internal(None)
+    setup_internal(region::US915::default().into(), None)
 }
@@ -0,0 +1,571 @@
+//! LoRaWAN device which blocks on a synchronous radio and an
+//! [`embedded-hal`](https://docs.rs/embedded-hal/1) delay, for firmware running a simple main
+//! loop without an executor.
+//!
+//! It shares the MAC layer, the errors and the responses of [`async_device`](crate::async_device).
+//! The RX2 window is timed from [`radio::PhyRxTx::timestamp_ms`] if the radio keeps time.
+//! Otherwise, the delays and the time spent in RX1 are added up: the duration of the window, or
+//! the wait for the preamble and the time on air of a frame received in it, which leaves out the
+//! processing time.
+use super::mac::Mac;
+
+use super::mac::{self, Frame, Reception, Window};
+pub use super::{
+    async_device::{Error, JoinResponse, SendResponse, Timings},
+    mac::{JoinRetryConfig, NetworkCredentials, SendData, Session},
+    region::{self, Region},
+    Downlink, JoinMode, LorawanDevice, OverflowPolicy, RxWindow,
+};
+use core::marker::PhantomData;
+use embedded_hal::delay::DelayNs;
+use lorawan::{self, keys::CryptoFactory};
+use rand_core::RngCore;
+
+pub use crate::region::DR;
+use crate::{
+    async_device::CLEAR_CHANNEL_ATTEMPTS,
+    device::impl_lorawan_device,
+    downlink::DownlinkQueue,
+    events::{EventSink, MacEvent},
+    radio::RadioBuffer,
+    region::ChannelPlan,
+    rng,
+};
+
+pub mod radio;
+
+#[cfg(test)]
+mod test;
+
+use self::radio::{ChannelAssessment, RxConfig, RxMode, RxStatus, TxConfig};
+
+/// Type representing a LoRaWAN capable device driven by blocking calls.
+///
+/// A device is bound to the same types as [`async_device::Device`](crate::async_device::Device),
+/// except for:
+/// - R: A blocking radio implementation
+/// - T: A blocking delay implementation
+pub struct Device<
+    R,
+    C,
+    T,
+    G,
+    const N: usize = 256,
+    const D: usize = 1,
+    E = (),
+    P = region::Configuration,
+> where
+    R: radio::PhyRxTx + Timings,
+    T: DelayNs,
+    C: CryptoFactory + Default,
+    G: RngCore,
+    E: EventSink,
+    P: ChannelPlan,
+{
+    crypto: PhantomData<C>,
+    radio: R,
+    /// Access to provided (pseudo)-random number generator.
+    pub rng: G,
+    delay: T,
+    mac: Mac<P>,
+    radio_buffer: RadioBuffer<N>,
+    downlink: DownlinkQueue<D>,
+    events: E,
+    #[cfg(feature = "class-c")]
+    class_c: bool,
+}
+
+impl<R, C, T, const N: usize> Device<R, C, T, rng::Prng, N>
+where
+    R: radio::PhyRxTx + Timings,
+    C: CryptoFactory + Default,
+    T: DelayNs,
+{
+    /// Create a new [`Device`] by providing your own random seed. Using this method, [`Device`] will internally
+    /// use an algorithmic PRNG.
+    ///
+    /// # ⚠️Warning⚠️
+    ///
+    /// This function must **always** be called with a new randomly generated seed! **Never** call this function more
+    /// than once using the same seed. Generate the seed using a true random number generator. Using the same seed will
+    /// leave you vulnerable to replay attacks.
+    pub fn new_with_seed(region: region::Configuration, radio: R, delay: T, seed: u64) -> Self {
+        Device::new(region, radio, delay, rng::Prng::new(seed))
+    }
+}
+
+impl<R, C, T, G, const N: usize, const D: usize> Device<R, C, T, G, N, D>
+where
+    R: radio::PhyRxTx + Timings,
+    C: CryptoFactory + Default,
+    T: DelayNs,
+    G: RngCore,
+{
+    /// Create a new instance of [`Device`] with a RNG external to the LoRa chip. You must provide your own RNG
+    /// implementing [`RngCore`].
+    pub fn new(region: region::Configuration, radio: R, delay: T, rng: G) -> Self {
+        Device::new_with_session(region, radio, delay, rng, None)
+    }
+
+    /// Create a new [`Device`] and provide an optional [`Session`].
+    pub fn new_with_session(
+        region: region::Configuration,
+        radio: R,
+        delay: T,
+        rng: G,
+        session: Option<Session>,
+    ) -> Self {
+        Device::new_with_channel_plan(region, radio, delay, rng, session)
+    }
+}
+
+impl<R, C, T, G, const N: usize, const D: usize, P> Device<R, C, T, G, N, D, (), P>
+where
+    R: radio::PhyRxTx + Timings,
+    C: CryptoFactory + Default,
+    T: DelayNs,
+    G: RngCore,
+    P: ChannelPlan,
+{
+    /// Create a new [`Device`] using a custom [`ChannelPlan`] instead of one
+    /// of the built-in regions, and provide an optional [`Session`].
+    pub fn new_with_channel_plan(
+        plan: P,
+        radio: R,
+        delay: T,
+        rng: G,
+        session: Option<Session>,
+    ) -> Self {
+        let mut mac = Mac::new(plan, R::MAX_RADIO_POWER, R::ANTENNA_GAIN);
+        if let Some(session) = session {
+            mac.set_session(session);
+        }
+        Self {
+            crypto: PhantomData,
+            radio,
+            rng,
+            delay,
+            mac,
+            radio_buffer: RadioBuffer::new(),
+            downlink: DownlinkQueue::new(),
+            events: (),
+            #[cfg(feature = "class-c")]
+            class_c: false,
+        }
+    }
+}
+
+impl<R, C, T, G, const N: usize, const D: usize, E, P> Device<R, C, T, G, N, D, E, P>
+where
+    R: radio::PhyRxTx + Timings,
+    C: CryptoFactory + Default,
+    T: DelayNs,
+    G: RngCore,
+    E: EventSink,
+    P: ChannelPlan,
+{
+    /// Replace the [`EventSink`] which receives the [`MacEvent`]s of this device.
+    pub fn with_event_sink<S: EventSink>(self, events: S) -> Device<R, C, T, G, N, D, S, P> {
+        Device {
+            crypto: PhantomData,
+            radio: self.radio,
+            rng: self.rng,
+            delay: self.delay,
+            mac: self.mac,
+            radio_buffer: self.radio_buffer,
+            downlink: self.downlink,
+            events,
+            #[cfg(feature = "class-c")]
+            class_c: self.class_c,
+        }
+    }
+
+    /// Largest application payload which can be passed to `send` with the current data rate, dwell
+    /// time and pending MAC commands.
+    pub fn max_payload_len(&self) -> usize {
+        self.mac.max_payload_len::<N>()
+    }
+
+    /// Statistics accumulated since the device was created or since the last
+    /// [`reset_statistics`](Self::reset_statistics).
+    pub fn get_statistics(&self) -> &mac::Statistics {
+        self.mac.get_statistics()
+    }
+
+    pub fn reset_statistics(&mut self) {
+        self.mac.reset_statistics()
+    }
+
+    pub fn get_event_sink(&self) -> &E {
+        &self.events
+    }
+
+    pub fn get_mut_event_sink(&mut self) -> &mut E {
+        &mut self.events
+    }
+
+    /// Enables Class C behavior: the radio listens for downlinks on the RXC frequency after the
+    /// RX windows of each uplink, which are received with [`rxc_listen`](Self::rxc_listen). Unlike
+    /// with `async_device::Device`, nothing is received between the transmission and RX1 or
+    /// between RX1 and RX2.
+    #[cfg(feature = "class-c")]
+    pub fn enable_class_c(&mut self) {
+        self.class_c = true;
+    }
+
+    /// Disables Class C behavior. Note that an uplink must be set for the radio to disable
+    /// Class C listen.
+    #[cfg(feature = "class-c")]
+    pub fn disable_class_c(&mut self) {
+        self.class_c = false;
+    }
+
+    /// Senses the channel before every transmission, as
+    /// [`async_device::Device::enable_listen_before_talk`](
+    /// crate::async_device::Device::enable_listen_before_talk).
+    pub fn enable_listen_before_talk(&mut self, threshold_dbm: i16, sensing_time_us: u32) {
+        self.mac.set_listen_before_talk(Some((threshold_dbm, sensing_time_us)));
+    }
+
+    /// Only senses the channel before transmitting if the region mandates listen-before-talk.
+    pub fn disable_listen_before_talk(&mut self) {
+        self.mac.set_listen_before_talk(None);
+    }
+
+    pub fn get_session(&self) -> Option<&Session> {
+        self.mac.get_session()
+    }
+
+    /// Restores a session, eg: one persisted before a reset. The device is then joined.
+    pub fn set_session(&mut self, session: Session) {
+        self.mac.set_session(session)
+    }
+
+    pub fn get_session_keys(&self) -> Option<mac::SessionKeys> {
+        self.mac.get_session_keys()
+    }
+
+    pub fn get_fcnt_up(&self) -> Option<mac::FcntUp> {
+        self.mac.get_fcnt_up()
+    }
+
+    /// Whether the device is joined. It is never busy with an uplink when this can be called.
+    pub fn ready_to_send_data(&self) -> bool {
+        self.mac.is_joined()
+    }
+
+    pub fn get_region(&self) -> &P {
+        &self.mac.region
+    }
+
+    pub fn get_radio(&self) -> &R {
+        &self.radio
+    }
+
+    pub fn get_mut_radio(&mut self) -> &mut R {
+        &mut self.radio
+    }
+
+    /// Retrieve the current data rate being used by this device.
+    pub fn get_datarate(&self) -> DR {
+        self.mac.configuration.data_rate
+    }
+
+    /// Set the data rate being used by this device. This overrides the region default.
+    pub fn set_datarate(&mut self, datarate: DR) {
+        self.mac.configuration.data_rate = datarate;
+    }
+
+    /// Join the LoRaWAN network. Returns once the LoRaWAN network has been joined successfully,
+    /// the RX windows went by without a JoinAccept, or an error has occurred.
+    ///
+    /// Repeatedly calling join using OTAA will result in a new LoRaWAN session to be created.
+    pub fn join(&mut self, join_mode: &JoinMode) -> Result<JoinResponse, Error<R::PhyError>> {
+        self.mac.stop_join_retries();
+        match join_mode {
+            JoinMode::OTAA { deveui, appeui, appkey } => {
+                self.join_otaa(NetworkCredentials::new(*appeui, *deveui, *appkey))
+            }
+            JoinMode::ABP { nwkskey, appskey, devaddr } => {
+                self.mac.join_abp(*nwkskey, *appskey, *devaddr);
+                Ok(JoinResponse::JoinSuccess)
+            }
+        }
+    }
+
+    /// Join the LoRaWAN network, retrying OTAA joins which are not answered according to
+    /// `config`, as [`async_device::Device::join_with_retries`](
+    /// crate::async_device::Device::join_with_retries). The backoff between JoinRequests is
+    /// spent in the delay.
+    pub fn join_with_retries(
+        &mut self,
+        join_mode: &JoinMode,
+        config: JoinRetryConfig,
+    ) -> Result<JoinResponse, Error<R::PhyError>> {
+        let JoinMode::OTAA { deveui, appeui, appkey } = join_mode else {
+            return self.join(join_mode);
+        };
+        self.mac.start_join_retries(config, NetworkCredentials::new(*appeui, *deveui, *appkey));
+        while let Some(credentials) = self.mac.next_join_attempt(&mut self.rng) {
+            match self.join_otaa(credentials) {
+                Ok(JoinResponse::NoJoinAccept) => match self.mac.join_backoff_ms() {
+                    Some(delay) => self.delay.delay_ms(delay),
+                    None => break,
+                },
+                result => {
+                    self.mac.stop_join_retries();
+                    return result;
+                }
+            }
+        }
+        Ok(JoinResponse::NoJoinAccept)
+    }
+
+    fn join_otaa(
+        &mut self,
+        credentials: NetworkCredentials,
+    ) -> Result<JoinResponse, Error<R::PhyError>> {
+        let (tx_config, dev_nonce) =
+            self.mac.join_otaa::<C, G, N>(&mut self.rng, credentials, &mut self.radio_buffer)?;
+
+        let (tx_config, tx_done_ms) = self.transmit(tx_config, &Frame::Join)?;
+        self.mac.emit(
+            &mut self.events,
+            MacEvent::JoinRequestSent {
+                dev_nonce,
+                tx_config,
+                time_on_air_us: mac::time_on_air_us(
+                    &tx_config,
+                    self.radio_buffer.as_ref_for_read().len(),
+                ),
+            },
+        );
+
+        Ok(self.rx_downlink(&Frame::Join, tx_done_ms)?.try_into()?)
+    }
+
+    /// Send data on a given port with the expected confirmation. Returns once the RX windows
+    /// went by or a downlink was received, which is then available by calling
+    /// [`take_downlink`](Self::take_downlink).
+    pub fn send(
+        &mut self,
+        data: &[u8],
+        fport: u8,
+        confirmed: bool,
+    ) -> Result<SendResponse, Error<R::PhyError>> {
+        let (tx_config, fcnt_up) = self.mac.send::<C, G, N>(
+            &mut self.rng,
+            &mut self.radio_buffer,
+            &SendData { data, fport, confirmed },
+        )?;
+        let (tx_config, tx_done_ms) = self.transmit(tx_config, &Frame::Data)?;
+        self.mac.emit(
+            &mut self.events,
+            MacEvent::UplinkSent {
+                fcnt_up,
+                tx_config,
+                time_on_air_us: mac::time_on_air_us(
+                    &tx_config,
+                    self.radio_buffer.as_ref_for_read().len(),
+                ),
+            },
+        );
+
+        Ok(self.rx_downlink(&Frame::Data, tx_done_ms)?.try_into()?)
+    }
+
+    /// Take the downlink data from the device. This is typically called after a
+    /// `SendResponse::DownlinkReceived` is returned from `send`. This call consumes the downlink
+    /// data. If no downlink data is available, `None` is returned. Downlinks are returned in the
+    /// order they were received.
+    pub fn take_downlink(&mut self) -> Option<Downlink> {
+        self.downlink.pop()
+    }
+
+    /// Chooses which downlink to discard when one is received while the queue of `D` downlinks is
+    /// full. Defaults to [`OverflowPolicy::DropNewest`]. Discarded downlinks are reported as
+    /// [`MacEvent::DownlinkDropped`].
+    pub fn set_downlink_overflow_policy(&mut self, policy: OverflowPolicy) {
+        self.downlink.set_policy(policy)
+    }
+
+    /// Transmits the radio buffer, moving the frame to another channel when listen-before-talk
+    /// finds the channel busy, as `async_device::Device` does.
+    fn transmit(
+        &mut self,
+        mut tx_config: TxConfig,
+        frame: &Frame,
+    ) -> Result<(TxConfig, u32), Error<R::PhyError>> {
+        let mut attempts = 1;
+        while let Some(config) = self.mac.get_clear_channel_config(tx_config.rf) {
+            match self.radio.clear_channel(config).map_err(Error::Radio)? {
+                ChannelAssessment::Clear => {
+                    self.mac.region.set_last_channel_busy(false);
+                    break;
+                }
+                ChannelAssessment::Busy => {
+                    self.mac.region.set_last_channel_busy(true);
+                    if attempts == CLEAR_CHANNEL_ATTEMPTS {
+                        return Err(Error::ChannelBusy);
+                    }
+                    attempts += 1;
+                    tx_config = self.mac.create_tx_config(&mut self.rng, frame)?;
+                }
+                ChannelAssessment::Unsupported => return Err(Error::ClearChannelUnsupported),
+            }
+        }
+        self.mac.uplink_sent();
+        let tx_done_ms =
+            self.radio.tx(tx_config, self.radio_buffer.as_ref_for_read()).map_err(Error::Radio)?;
+        Ok((tx_config, tx_done_ms))
+    }
+
+    /// Puts the radio to sleep, or back to Class C listening.
+    fn window_complete(&mut self) -> Result<(), Error<R::PhyError>> {
+        #[cfg(feature = "class-c")]
+        if self.class_c {
+            let rx_config = self.mac.get_rxc_config();
+            self.radio.setup_rx(rx_config).map_err(Error::Radio)?;
+            self.mac.emit(
+                &mut self.events,
+                MacEvent::RxWindowOpened { window: RxWindow::Rxc, rf: rx_config.rf },
+            );
+            return Ok(());
+        }
+
+        self.radio.low_power().map_err(Error::Radio)
+    }
+
+    /// Receives within the RX1 and RX2 windows, `tx_done_ms` after the end of the transmission.
+    fn rx_downlink(
+        &mut self,
+        frame: &Frame,
+        tx_done_ms: u32,
+    ) -> Result<mac::Response, Error<R::PhyError>> {
+        self.radio_buffer.clear();
+        self.radio.low_power().map_err(Error::Radio)?;
+        let tx_done_at = self.radio.timestamp_ms().map(|now| now.wrapping_sub(tx_done_ms));
+        let mut elapsed_ms = tx_done_ms;
+
+        for (window, rx_window) in [(Window::_1, RxWindow::Rx1), (Window::_2, RxWindow::Rx2)] {
+            let (start_ms, rx_config) = self.get_rx_window(frame, &window);
+            // measured if the radio keeps time, added up otherwise
+            if let (Some(tx_done_at), Some(now)) = (tx_done_at, self.radio.timestamp_ms()) {
+                elapsed_ms = now.wrapping_sub(tx_done_at);
+            }
+            let delay_ms = start_ms.saturating_sub(elapsed_ms);
+            debug!("Starting {} in {} ms.", rx_window, delay_ms);
+            self.delay.delay_ms(delay_ms);
+            elapsed_ms += delay_ms;
+
+            debug!("Configuring {} window with config {}.", rx_window, rx_config);
+            self.radio.setup_rx(rx_config).map_err(Error::Radio)?;
+            self.mac.emit(
+                &mut self.events,
+                MacEvent::RxWindowOpened { window: rx_window, rf: rx_config.rf },
+            );
+            // a frame is expected at the nominal start of the window
+            let preamble_wait_ms = self.mac.get_rx_delay(frame, &window).saturating_sub(start_ms);
+            let (response, listened_ms) =
+                self.rx_listen(rx_window, &rx_config, preamble_wait_ms)?;
+            elapsed_ms += listened_ms;
+            if let Some(response) = response {
+                debug!("{} received {}", rx_window, response);
+                self.window_complete()?;
+                return Ok(response);
+            }
+        }
+        debug!("RX2 did not receive anything.");
+        self.window_complete()?;
+        Ok(self.mac.rx2_complete())
+    }
+
+    /// When to start the radio for an RX window, in milliseconds after the end of the
+    /// transmission, and its configuration.
+    fn get_rx_window(&self, frame: &Frame, window: &Window) -> (u32, RxConfig) {
+        self.mac.get_rx_window(frame, window, |sf| mac::RxTiming {
+            wakeup_time_ms: self.radio.get_rx_window_lead_time_ms(),
+            max_rx_error_ms: self.radio.get_rx_window_buffer(),
+            clock_error_ppm: self.radio.clock_error_ppm(),
+            min_rx_symbols: self.radio.get_min_rx_symbols(sf),
+        })
+    }
+
+    /// Receives within an RX window and returns the response, if any, and for how long the
+    /// radio was receiving, given that a frame would be expected `preamble_wait_ms` after the
+    /// window opened.
+    fn rx_listen(
+        &mut self,
+        window: RxWindow,
+        rx_config: &RxConfig,
+        preamble_wait_ms: u32,
+    ) -> Result<(Option<mac::Response>, u32), Error<R::PhyError>> {
+        let bb = &rx_config.rf.bb;
+        let (response, listened_ms) =
+            match self.radio.rx_single(self.radio_buffer.as_mut()).map_err(Error::Radio)? {
+                RxStatus::Rx(s, quality) => {
+                    self.radio_buffer.set_pos(s);
+                    let response = match self.mac.handle_rx::<C, N, D>(
+                        &mut self.radio_buffer,
+                        &mut self.downlink,
+                        Reception { window, quality },
+                        &mut self.events,
+                    ) {
+                        mac::Response::NoUpdate => None,
+                        r => Some(r),
+                    };
+                    let time_on_air_ms = bb.time_on_air_us(Some(8), true, s as u8).div_ceil(1000);
+                    (response, preamble_wait_ms + time_on_air_ms)
+                }
+                RxStatus::RxTimeout => {
+                    let symbols = match rx_config.mode {
+                        RxMode::Single { symbols } => symbols,
+                        RxMode::Continuous => 0,
+                    };
+                    (None, bb.symbols_to_ms(symbols.into()))
+                }
+            };
+        self.mac.emit(&mut self.events, MacEvent::RxWindowClosed { window });
+        self.radio_buffer.clear();
+        Ok((response, listened_ms))
+    }
+
+    /// Blocks until a Class C downlink is received, once the RX windows of an uplink set up the
+    /// radio for Class C. The downlink is then available by calling
+    /// [`take_downlink`](Self::take_downlink).
+    #[cfg(feature = "class-c")]
+    pub fn rxc_listen(&mut self) -> Result<mac::Response, Error<R::PhyError>> {
+        loop {
+            let (sz, quality) =
+                self.radio.rx_continuous(self.radio_buffer.as_mut()).map_err(Error::Radio)?;
+            self.radio_buffer.set_pos(sz);
+            let response = self.mac.handle_rxc::<C, N, D>(
+                &mut self.radio_buffer,
+                &mut self.downlink,
+                quality,
+                &mut self.events,
+            )?;
+            self.radio_buffer.clear();
+            if !matches!(response, mac::Response::NoUpdate) {
+                return Ok(response);
+            }
+        }
+    }
+}
+
+impl<R, C, T, G, const N: usize, const D: usize, E, P> LorawanDevice
+    for Device<R, C, T, G, N, D, E, P>
+where
+    R: radio::PhyRxTx + Timings,
+    C: CryptoFactory + Default,
+    T: DelayNs,
+    G: RngCore,
+    E: EventSink,
+    P: ChannelPlan,
+{
+    type Radio = R;
+    type ChannelPlan = P;
+    type EventSink = E;
+
+    impl_lorawan_device!();
+}
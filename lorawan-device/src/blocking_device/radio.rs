@@ -0,0 +1,63 @@
+pub use crate::async_device::radio::RxStatus;
+pub use crate::radio::{
+    ChannelAssessment, ClearChannelConfig, RfConfig, RxConfig, RxMode, RxQuality, TxConfig,
+};
+
+/// A blocking radio implementation that can transmit and receive data.
+pub trait PhyRxTx {
+    type PhyError;
+
+    /// Board-specific antenna gain and power loss in dBi.
+    const ANTENNA_GAIN: i8 = 0;
+
+    /// Maximum power (dBm) that the radio is able to output. When preparing instructions for radio,
+    /// the value of maximum power will be used as an upper bound.
+    const MAX_RADIO_POWER: u8;
+
+    /// Transmit data buffer with the given transceiver configuration. Blocks until the data have
+    /// been transmitted and returns how many milliseconds ago the transmission ended, from which
+    /// the RX windows are timed.
+    fn tx(&mut self, config: TxConfig, buf: &[u8]) -> Result<u32, Self::PhyError>;
+
+    /// Configures the radio to receive data, without waiting for the data itself.
+    fn setup_rx(&mut self, config: RxConfig) -> Result<(), Self::PhyError>;
+
+    /// Receive data into the provided buffer with the configuration set up for an RX window.
+    /// Blocks until a frame has been received or until no preamble was detected within the
+    /// symbol timeout of the window.
+    fn rx_single(&mut self, buf: &mut [u8]) -> Result<RxStatus, Self::PhyError>;
+
+    /// Receive data into the provided buffer with the configuration set up for Class C. Blocks
+    /// until a frame has been received.
+    fn rx_continuous(&mut self, buf: &mut [u8]) -> Result<(usize, RxQuality), Self::PhyError>;
+
+    /// Puts the radio into a low-power mode
+    fn low_power(&mut self) -> Result<(), Self::PhyError> {
+        Ok(())
+    }
+
+    /// Milliseconds elapsed on a free-running clock which keeps time independently of the delay
+    /// (eg: a timer of the radio or of the MCU), wrapping around. When provided, RX2 is timed
+    /// from it rather than by adding up the delays and the time spent in RX1. Defaults to `None`.
+    fn timestamp_ms(&mut self) -> Option<u32> {
+        None
+    }
+
+    /// Worst drift of the clock timing the RX windows (the delay, or
+    /// [`timestamp_ms`](Self::timestamp_ms) if provided), in parts per million. RX windows are
+    /// widened accordingly, as with [`async_device::radio::Timer::clock_error_ppm`](
+    /// crate::async_device::radio::Timer::clock_error_ppm). Defaults to 0.
+    fn clock_error_ppm(&self) -> u32 {
+        0
+    }
+
+    /// Senses the channel for `config.sensing_time_us` and reports whether it is clear for
+    /// transmitting, as [`async_device::radio::PhyRxTx::clear_channel`](
+    /// crate::async_device::radio::PhyRxTx::clear_channel).
+    fn clear_channel(
+        &mut self,
+        _config: ClearChannelConfig,
+    ) -> Result<ChannelAssessment, Self::PhyError> {
+        Ok(ChannelAssessment::Unsupported)
+    }
+}
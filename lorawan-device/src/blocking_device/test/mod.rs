@@ -0,0 +1,127 @@
+use super::*;
+use crate::{region, test_util::*};
+use lorawan::default_crypto::DefaultFactory;
+
+mod radio;
+use radio::{TestDelay, TestRadio};
+
+type TestDevice = Device<TestRadio, DefaultFactory, TestDelay, rand_core::OsRng, 256, 2>;
+
+fn setup() -> (TestDelay, TestDevice) {
+    let delay = TestDelay::default();
+    let region = region::US915::default().into();
+    (delay.clone(), Device::new(region, TestRadio::default(), delay, rand_core::OsRng))
+}
+
+fn setup_with_session() -> (TestDelay, TestDevice) {
+    let (delay, mut device) = setup();
+    device.join(&get_abp_credentials()).unwrap();
+    (delay, device)
+}
+
+#[test]
+fn test_join_rx1() {
+    let (delay, mut device) = setup();
+    device.get_mut_radio().answer_with(Some(handle_join_request::<1>));
+    let response = device.join(&get_otaa_credentials()).unwrap();
+    assert!(matches!(response, JoinResponse::JoinSuccess));
+    assert!(device.get_session_keys().is_some());
+    // RX1 opens before the JoinAccept delay to absorb the timing error
+    assert_eq!(delay.take_delays_ms(), [4984]);
+    assert!(device.get_radio().is_low_power());
+}
+
+#[test]
+fn test_join_rx2() {
+    let (delay, mut device) = setup();
+    device.get_mut_radio().answer_with(None);
+    device.get_mut_radio().answer_with(Some(handle_join_request::<2>));
+    let response = device.join(&get_otaa_credentials()).unwrap();
+    assert!(matches!(response, JoinResponse::JoinSuccess));
+    // RX2 is timed from the end of RX1
+    assert_eq!(delay.take_delays_ms(), [4984, 982]);
+    assert_eq!(device.get_radio().current_config().unwrap().rf.frequency, 923_300_000);
+}
+
+#[test]
+fn test_join_rx2_timed_by_radio() {
+    let (delay, mut device) = setup();
+    // the clock starts close to wrapping around, and RX1 takes 50 ms more than its timeout
+    delay.clock().set(u32::MAX - 5000);
+    device.get_mut_radio().keep_time(delay.clock(), 50);
+    device.get_mut_radio().answer_with(None);
+    device.get_mut_radio().answer_with(Some(handle_join_request::<2>));
+    let response = device.join(&get_otaa_credentials()).unwrap();
+    assert!(matches!(response, JoinResponse::JoinSuccess));
+    // RX2 opens at 5994 ms, measured from the end of the transmission rather than from the 28 ms
+    // timeout of RX1 (see test_join_rx2)
+    assert_eq!(delay.take_delays_ms(), [4984, 960]);
+}
+
+#[test]
+fn test_join_clock_error() {
+    let (delay, mut device) = setup();
+    device.get_mut_radio().set_clock_error_ppm(1000);
+    device.get_mut_radio().answer_with(Some(handle_join_request::<1>));
+    device.join(&get_otaa_credentials()).unwrap();
+    // a drift of up to 5 ms after 5 s opens RX1 earlier (see test_join_rx1) and for longer
+    assert_eq!(delay.take_delays_ms(), [4979]);
+    let config = device.get_radio().current_config().unwrap();
+    assert_eq!(config.mode, RxMode::Single { symbols: 19 });
+}
+
+#[test]
+fn test_no_join_accept() {
+    let (delay, mut device) = setup();
+    let response = device.join(&get_otaa_credentials()).unwrap();
+    assert!(matches!(response, JoinResponse::NoJoinAccept));
+    assert!(!device.ready_to_send_data());
+    assert_eq!(delay.take_delays_ms().len(), 2);
+}
+
+#[test]
+fn test_unconfirmed_uplink_no_downlink() {
+    let (_, mut device) = setup_with_session();
+    let response = device.send(&[1, 2, 3], 3, false).unwrap();
+    assert!(matches!(response, SendResponse::RxComplete));
+    assert_eq!(device.get_fcnt_up(), Some(1));
+    assert!(device.get_radio().is_low_power());
+}
+
+#[test]
+fn test_confirmed_uplink_no_ack() {
+    let (_, mut device) = setup_with_session();
+    let response = device.send(&[1, 2, 3], 3, true).unwrap();
+    assert!(matches!(response, SendResponse::NoAck));
+}
+
+#[test]
+fn test_confirmed_uplink_with_ack_rx2() {
+    let (_, mut device) = setup_with_session();
+    device.get_mut_radio().answer_with(None);
+    device.get_mut_radio().answer_with(Some(handle_data_uplink_with_link_adr_req::<0, 0>));
+    let response = device.send(&[1, 2, 3], 3, true).unwrap();
+    assert!(matches!(response, SendResponse::DownlinkReceived(0)));
+}
+
+#[cfg(feature = "class-c")]
+#[test]
+fn test_class_c() {
+    let (_, mut device) = setup_with_session();
+    device.enable_class_c();
+    let response = device.send(&[1, 2, 3], 3, false).unwrap();
+    assert!(matches!(response, SendResponse::RxComplete));
+    // RXC is set up after RX2
+    let config = device.get_radio().current_config().unwrap();
+    assert_eq!((config.rf.frequency, config.mode), (923_300_000, RxMode::Continuous));
+
+    device.get_mut_radio().answer_with(Some(class_c_downlink::<1>));
+    device.get_mut_radio().answer_with(Some(class_c_downlink::<2>));
+    for fcnt_down in 1..=2 {
+        let response = device.rxc_listen().unwrap();
+        assert!(matches!(response, mac::Response::DownlinkReceived(fcnt) if fcnt == fcnt_down));
+    }
+    let downlinks: Vec<_> = core::iter::from_fn(|| device.take_downlink()).collect();
+    assert_eq!(downlinks.len(), 2);
+    assert!(downlinks.iter().all(|downlink| downlink.window == RxWindow::Rxc));
+}
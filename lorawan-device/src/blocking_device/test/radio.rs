@@ -0,0 +1,138 @@
+use super::*;
+use crate::blocking_device::{
+    radio::{PhyRxTx, RxConfig, RxQuality, RxStatus, TxConfig},
+    Timings,
+};
+use embedded_hal::delay::DelayNs;
+use std::{
+    cell::{Cell, RefCell},
+    collections::VecDeque,
+    rc::Rc,
+};
+
+/// A radio answering each RX window in turn with the handlers pushed with
+/// [`TestRadio::answer_with`].
+#[derive(Default)]
+pub struct TestRadio {
+    last_uplink: Option<Uplink>,
+    rx_windows: VecDeque<Option<RxTxHandler>>,
+    current_config: Option<RxConfig>,
+    low_power: bool,
+    /// Clock shared with a [`TestDelay`], and how far each RX window moves it on.
+    clock: Option<(Rc<Cell<u32>>, u32)>,
+    clock_error_ppm: u32,
+}
+
+impl TestRadio {
+    /// Answers the next RX window with `handler`, or lets it time out.
+    pub fn answer_with(&mut self, handler: Option<RxTxHandler>) {
+        self.rx_windows.push_back(handler);
+    }
+
+    /// Provides the time of `clock`, which each RX window moves on by `rx_ms` on top of the
+    /// delays.
+    pub fn keep_time(&mut self, clock: Rc<Cell<u32>>, rx_ms: u32) {
+        self.clock = Some((clock, rx_ms));
+    }
+
+    pub fn set_clock_error_ppm(&mut self, clock_error_ppm: u32) {
+        self.clock_error_ppm = clock_error_ppm;
+    }
+
+    /// Configuration of the last RX set up.
+    pub fn current_config(&self) -> Option<RxConfig> {
+        self.current_config
+    }
+
+    pub fn is_low_power(&self) -> bool {
+        self.low_power
+    }
+
+    fn receive(&mut self, buf: &mut [u8]) -> Option<usize> {
+        let handler = self.rx_windows.pop_front().flatten()?;
+        let rf = self.current_config.expect("RX not set up").rf;
+        Some(handler(self.last_uplink.take(), rf, buf))
+    }
+}
+
+impl PhyRxTx for TestRadio {
+    type PhyError = &'static str;
+
+    const MAX_RADIO_POWER: u8 = 26;
+
+    fn tx(&mut self, config: TxConfig, buf: &[u8]) -> Result<u32, Self::PhyError> {
+        self.low_power = false;
+        self.last_uplink = Some(Uplink::new(buf, config).map_err(|_| "error creating uplink")?);
+        Ok(0)
+    }
+
+    fn setup_rx(&mut self, config: RxConfig) -> Result<(), Self::PhyError> {
+        self.low_power = false;
+        self.current_config = Some(config);
+        Ok(())
+    }
+
+    fn rx_single(&mut self, buf: &mut [u8]) -> Result<RxStatus, Self::PhyError> {
+        if let Some((clock, rx_ms)) = &self.clock {
+            clock.set(clock.get().wrapping_add(*rx_ms));
+        }
+        Ok(match self.receive(buf) {
+            Some(len) => RxStatus::Rx(len, RxQuality::new(-80, 0)),
+            None => RxStatus::RxTimeout,
+        })
+    }
+
+    fn rx_continuous(&mut self, buf: &mut [u8]) -> Result<(usize, RxQuality), Self::PhyError> {
+        // nothing would ever be received
+        let len = self.receive(buf).ok_or("no RXC downlink")?;
+        Ok((len, RxQuality::new(-80, 0)))
+    }
+
+    fn low_power(&mut self) -> Result<(), Self::PhyError> {
+        self.low_power = true;
+        Ok(())
+    }
+
+    fn timestamp_ms(&mut self) -> Option<u32> {
+        self.clock.as_ref().map(|(clock, _)| clock.get())
+    }
+
+    fn clock_error_ppm(&self) -> u32 {
+        self.clock_error_ppm
+    }
+}
+
+impl Timings for TestRadio {
+    fn get_rx_window_lead_time_ms(&self) -> u32 {
+        10
+    }
+}
+
+/// Records the delays instead of sleeping, moving a clock on. Clones share the record and the
+/// clock.
+#[derive(Default, Clone)]
+pub struct TestDelay {
+    delays_ms: Rc<RefCell<Vec<u32>>>,
+    clock: Rc<Cell<u32>>,
+}
+
+impl TestDelay {
+    pub fn take_delays_ms(&self) -> Vec<u32> {
+        self.delays_ms.take()
+    }
+
+    pub fn clock(&self) -> Rc<Cell<u32>> {
+        self.clock.clone()
+    }
+}
+
+impl DelayNs for TestDelay {
+    fn delay_ns(&mut self, ns: u32) {
+        self.delay_ms(ns / 1_000_000);
+    }
+
+    fn delay_ms(&mut self, ms: u32) {
+        self.delays_ms.borrow_mut().push(ms);
+        self.clock.set(self.clock.get().wrapping_add(ms));
+    }
+}
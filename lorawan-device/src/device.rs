@@ -0,0 +1,135 @@
+//! The API shared by the device front-ends.
+use crate::mac::{FcntUp, Session, SessionKeys, Statistics};
+use crate::region::{ChannelPlan, DR};
+use crate::{Downlink, OverflowPolicy};
+
+/// Operations of a LoRaWAN device which do not involve the radio, implemented by
+/// [`async_device::Device`](crate::async_device::Device),
+/// [`nb_device::Device`](crate::nb_device::Device) and `blocking_device::Device` (with the
+/// `blocking` feature) over the same MAC layer.
+///
+/// Application glue written against this trait works with any front-end; only joining and
+/// sending, which are asynchronous, event-driven or blocking depending on the front-end, differ.
+pub trait LorawanDevice {
+    /// The radio driven by the device.
+    type Radio;
+    /// The channel plan of the device.
+    type ChannelPlan: ChannelPlan;
+    /// The [`EventSink`](crate::events::EventSink) of the device.
+    type EventSink;
+
+    /// Current session, once joined.
+    fn get_session(&self) -> Option<&Session>;
+
+    /// Restores a session, eg: one persisted before a reset. The device is then joined.
+    fn set_session(&mut self, session: Session);
+
+    fn get_session_keys(&self) -> Option<SessionKeys>;
+
+    /// Frame counter of the next uplink, once joined.
+    fn get_fcnt_up(&self) -> Option<FcntUp>;
+
+    /// Whether the device is joined and not busy with an uplink, so that data may be sent.
+    fn ready_to_send_data(&self) -> bool;
+
+    /// Retrieve the current data rate being used by this device.
+    fn get_datarate(&self) -> DR;
+
+    /// Set the data rate being used by this device. This overrides the region default.
+    fn set_datarate(&mut self, datarate: DR);
+
+    fn get_region(&self) -> &Self::ChannelPlan;
+
+    fn get_radio(&self) -> &Self::Radio;
+
+    fn get_mut_radio(&mut self) -> &mut Self::Radio;
+
+    fn get_event_sink(&self) -> &Self::EventSink;
+
+    fn get_mut_event_sink(&mut self) -> &mut Self::EventSink;
+
+    /// Largest application payload which can currently be sent.
+    fn max_payload_len(&self) -> usize;
+
+    fn get_statistics(&self) -> &Statistics;
+
+    fn reset_statistics(&mut self);
+
+    /// Takes the oldest downlink from the queue.
+    fn take_downlink(&mut self) -> Option<Downlink>;
+
+    fn set_downlink_overflow_policy(&mut self, policy: OverflowPolicy);
+
+    #[cfg(feature = "class-c")]
+    fn enable_class_c(&mut self);
+
+    #[cfg(feature = "class-c")]
+    fn disable_class_c(&mut self);
+}
+
+/// Implements [`LorawanDevice`] for a front-end by forwarding to its inherent methods of the same
+/// names.
+macro_rules! impl_lorawan_device {
+    () => {
+        fn get_session(&self) -> Option<&$crate::mac::Session> {
+            Self::get_session(self)
+        }
+        fn set_session(&mut self, session: $crate::mac::Session) {
+            Self::set_session(self, session)
+        }
+        fn get_session_keys(&self) -> Option<$crate::mac::SessionKeys> {
+            Self::get_session_keys(self)
+        }
+        fn get_fcnt_up(&self) -> Option<$crate::mac::FcntUp> {
+            Self::get_fcnt_up(self)
+        }
+        fn ready_to_send_data(&self) -> bool {
+            Self::ready_to_send_data(self)
+        }
+        fn get_datarate(&self) -> $crate::region::DR {
+            Self::get_datarate(self)
+        }
+        fn set_datarate(&mut self, datarate: $crate::region::DR) {
+            Self::set_datarate(self, datarate)
+        }
+        fn get_region(&self) -> &Self::ChannelPlan {
+            Self::get_region(self)
+        }
+        fn get_radio(&self) -> &Self::Radio {
+            Self::get_radio(self)
+        }
+        fn get_mut_radio(&mut self) -> &mut Self::Radio {
+            Self::get_mut_radio(self)
+        }
+        fn get_event_sink(&self) -> &Self::EventSink {
+            Self::get_event_sink(self)
+        }
+        fn get_mut_event_sink(&mut self) -> &mut Self::EventSink {
+            Self::get_mut_event_sink(self)
+        }
+        fn max_payload_len(&self) -> usize {
+            Self::max_payload_len(self)
+        }
+        fn get_statistics(&self) -> &$crate::mac::Statistics {
+            Self::get_statistics(self)
+        }
+        fn reset_statistics(&mut self) {
+            Self::reset_statistics(self)
+        }
+        fn take_downlink(&mut self) -> Option<$crate::Downlink> {
+            Self::take_downlink(self)
+        }
+        fn set_downlink_overflow_policy(&mut self, policy: $crate::OverflowPolicy) {
+            Self::set_downlink_overflow_policy(self, policy)
+        }
+        #[cfg(feature = "class-c")]
+        fn enable_class_c(&mut self) {
+            Self::enable_class_c(self)
+        }
+        #[cfg(feature = "class-c")]
+        fn disable_class_c(&mut self) {
+            Self::disable_class_c(self)
+        }
+    };
+}
+pub(crate) use impl_lorawan_device;
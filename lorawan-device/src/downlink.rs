@@ -0,0 +1,106 @@
+//! Buffering of received downlinks until the application takes them.
+use crate::Downlink;
+use heapless::Deque;
+
+/// What to do with a downlink received while the downlink queue is full.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt-03", derive(defmt::Format))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum OverflowPolicy {
+    /// Discard the oldest queued downlink to make room for the new one.
+    DropOldest,
+    /// Keep the queue as is and discard the new downlink.
+    #[default]
+    DropNewest,
+}
+
+/// FIFO queue of up to `D` downlinks.
+pub(crate) struct DownlinkQueue<const D: usize> {
+    queue: Deque<Downlink, D>,
+    policy: OverflowPolicy,
+}
+
+impl<const D: usize> DownlinkQueue<D> {
+    pub(crate) const fn new() -> Self {
+        Self { queue: Deque::new(), policy: OverflowPolicy::DropNewest }
+    }
+
+    pub(crate) fn set_policy(&mut self, policy: OverflowPolicy) {
+        self.policy = policy;
+    }
+
+    /// Enqueues the downlink, returning the one discarded according to the policy if the queue
+    /// was full.
+    pub(crate) fn push(&mut self, downlink: Downlink) -> Option<Downlink> {
+        match self.queue.push_back(downlink) {
+            Ok(()) => None,
+            Err(downlink) => match self.policy {
+                OverflowPolicy::DropNewest => Some(downlink),
+                OverflowPolicy::DropOldest => {
+                    let oldest = self.queue.pop_front();
+                    // Cannot fail since an element was just removed. With D = 0 there is nothing
+                    // to remove and the new downlink is the one dropped.
+                    match self.queue.push_back(downlink) {
+                        Ok(()) => oldest,
+                        Err(downlink) => Some(downlink),
+                    }
+                }
+            },
+        }
+    }
+
+    pub(crate) fn pop(&mut self) -> Option<Downlink> {
+        self.queue.pop_front()
+    }
+
+    #[cfg(test)]
+    pub(crate) fn len(&self) -> usize {
+        self.queue.len()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{radio::RxQuality, RxWindow};
+    use heapless::Vec;
+
+    fn downlink(fport: u8) -> Downlink {
+        Downlink {
+            data: Vec::new(),
+            fport,
+            window: RxWindow::Rxc,
+            quality: RxQuality::new(0, 0),
+            fcnt_down: 0,
+            ack: false,
+            fpending: false,
+            confirmed: false,
+        }
+    }
+
+    #[test]
+    fn fifo_order() {
+        let mut queue = DownlinkQueue::<3>::new();
+        for fport in 1..=3 {
+            assert!(queue.push(downlink(fport)).is_none());
+        }
+        assert_eq!(queue.len(), 3);
+        for fport in 1..=3 {
+            assert_eq!(queue.pop().unwrap().fport, fport);
+        }
+        assert!(queue.pop().is_none());
+    }
+
+    #[test]
+    fn overflow_policies() {
+        let mut queue = DownlinkQueue::<2>::new();
+        queue.push(downlink(1));
+        queue.push(downlink(2));
+        assert_eq!(queue.push(downlink(3)).unwrap().fport, 3);
+
+        queue.set_policy(OverflowPolicy::DropOldest);
+        assert_eq!(queue.push(downlink(4)).unwrap().fport, 1);
+        assert_eq!(queue.pop().unwrap().fport, 2);
+        assert_eq!(queue.pop().unwrap().fport, 4);
+    }
+}
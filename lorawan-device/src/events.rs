@@ -0,0 +1,72 @@
+//! Structured MAC events for telemetry and fleet monitoring.
+//!
+//! Both `async_device::Device` and `nb_device::Device` report what the MAC is doing to an
+//! [`EventSink`]. By default the sink is `()`, which discards every event; provide your own sink
+//! with `with_event_sink` on either device.
+//!
+//! # Example: counting uplinks
+//!
+//! ```
+//! use lorawan_device::events::{EventSink, MacEvent};
+//!
+//! #[derive(Default)]
+//! struct UplinkCounter(u32);
+//!
+//! impl EventSink for UplinkCounter {
+//!     fn event(&mut self, event: MacEvent) {
+//!         if let MacEvent::UplinkSent { .. } = event {
+//!             self.0 += 1;
+//!         }
+//!     }
+//! }
+//! ```
+use crate::mac::{FcntDown, FcntUp};
+use crate::radio::{RfConfig, RxQuality, TxConfig};
+use crate::RxWindow;
+
+/// An event emitted by the MAC layer.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "defmt-03", derive(defmt::Format))]
+pub enum MacEvent {
+    /// A JoinRequest has been handed to the radio. The channel and data rate in use are described
+    /// by `tx_config`.
+    JoinRequestSent { dev_nonce: u16, tx_config: TxConfig, time_on_air_us: u32 },
+    /// A data uplink has been handed to the radio. The channel and data rate in use are described
+    /// by `tx_config`.
+    UplinkSent { fcnt_up: FcntUp, tx_config: TxConfig, time_on_air_us: u32 },
+    /// The radio has been configured to receive in the given window.
+    RxWindowOpened { window: RxWindow, rf: RfConfig },
+    /// The given receive window has been closed.
+    RxWindowClosed { window: RxWindow },
+    /// A downlink addressed to this device was successfully authenticated and decrypted.
+    DownlinkReceived { window: RxWindow, fcnt_down: FcntDown, quality: RxQuality },
+    /// A downlink MAC command, identified by its CID, was applied.
+    MacCommandApplied { cid: u8 },
+    /// A downlink MAC command, identified by its CID, was not supported or could not be applied.
+    MacCommandRejected { cid: u8 },
+    /// A downlink was discarded because the downlink queue was full. Which one is discarded depends
+    /// on the [`OverflowPolicy`](crate::OverflowPolicy).
+    DownlinkDropped { fport: u8 },
+    /// The set of enabled channels changed. Bit `n` of the mask (LSB first) corresponds to
+    /// channel `n`.
+    ChannelMaskChanged { channel_mask: [u8; 9] },
+}
+
+/// Receives [`MacEvent`]s from a device.
+///
+/// The sink is called synchronously from within the MAC, so implementations should return
+/// quickly (eg: by copying the event into a queue).
+pub trait EventSink {
+    fn event(&mut self, event: MacEvent);
+}
+
+/// Discards all events.
+impl EventSink for () {
+    fn event(&mut self, _event: MacEvent) {}
+}
+
+impl<T: EventSink + ?Sized> EventSink for &mut T {
+    fn event(&mut self, event: MacEvent) {
+        (**self).event(event)
+    }
+}
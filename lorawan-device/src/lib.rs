@@ -23,8 +23,18 @@ pub use region::Region;
 #[cfg(test)]
 mod test_util;
 
+mod device;
+pub use device::LorawanDevice;
+
 pub mod async_device;
 
+#[cfg(feature = "blocking")]
+pub mod blocking_device;
+
+mod downlink;
+pub use downlink::OverflowPolicy;
+pub mod events;
+
 pub mod nb_device;
 use nb_device::state::State;
 
@@ -44,16 +54,51 @@ pub use rand_core::RngCore;
 mod rng;
 pub use rng::Prng;
 
-/// Provides the application payload and FPort of a downlink message.
+/// Receive window of a Class A or Class C device.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt-03", derive(defmt::Format))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum RxWindow {
+    /// First Class A receive window.
+    Rx1,
+    /// Second Class A receive window.
+    Rx2,
+    /// Class C continuous receive window.
+    Rxc,
+}
+
+/// Provides the application payload and FPort of a downlink message, along with how it was
+/// received.
 pub struct Downlink {
     pub data: Vec<u8, 256>,
     pub fport: u8,
+    /// Receive window in which the downlink arrived.
+    pub window: RxWindow,
+    /// RSSI and SNR of the downlink.
+    pub quality: radio::RxQuality,
+    pub fcnt_down: mac::FcntDown,
+    /// The network acknowledged the last confirmed uplink.
+    pub ack: bool,
+    /// The network has more data pending and expects an uplink soon.
+    pub fpending: bool,
+    /// The downlink is a confirmed data down and will be acknowledged in the next uplink.
+    pub confirmed: bool,
 }
 
 #[cfg(feature = "defmt-03")]
 impl defmt::Format for Downlink {
     fn format(&self, f: defmt::Formatter<'_>) {
-        defmt::write!(f, "Downlink {{ fport: {}, data: ", self.fport,);
+        defmt::write!(
+            f,
+            "Downlink {{ fport: {}, window: {}, quality: {}, fcnt_down: {}, ack: {}, fpending: {}, confirmed: {}, data: ",
+            self.fport,
+            self.window,
+            self.quality,
+            self.fcnt_down,
+            self.ack,
+            self.fpending,
+            self.confirmed,
+        );
 
         for byte in self.data.iter() {
             defmt::write!(f, "{:02x}", byte);
@@ -63,6 +108,11 @@ impl defmt::Format for Downlink {
 }
 
 /// Allows to fine-tune the beginning and end of the receive windows for a specific board.
+///
+/// Used by [`nb_device`], whose windows are opened and closed at fixed offsets from their nominal
+/// start. The symbol timeouts computed from the timing error by `async_device` and
+/// `blocking_device` (see [`async_device::Timings`]) do not apply, since the radio of `nb_device`
+/// is only given the RF configuration of a window.
 pub trait Timings {
     /// The offset in milliseconds from the beginning of the receive windows. For example, settings this to 100
     /// tell the LoRaWAN stack to begin configuring the receive window 100 ms before the window needs to start.
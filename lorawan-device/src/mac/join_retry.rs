@@ -0,0 +1,160 @@
+//! Scheduling of JoinRequest retransmissions.
+//!
+//! LoRaWAN 1.0.3 Section 7 limits the aggregated duty cycle of JoinRequest retransmissions to 1%
+//! during the first hour after the first attempt, 0.1% during the following 10 hours and 0.01%
+//! afterwards.
+use super::NetworkCredentials;
+use crate::region::DR;
+use heapless::Vec;
+use rand_core::RngCore;
+
+const HOUR_MS: u64 = 60 * 60 * 1000;
+
+/// Strategy for retrying OTAA joins, used by
+/// [`async_device::Device::join_with_retries`](crate::async_device::Device::join_with_retries) and
+/// [`nb_device::Device::join_with_retries`](crate::nb_device::Device::join_with_retries).
+#[derive(Debug, Clone)]
+pub struct JoinRetryConfig {
+    /// Maximum number of JoinRequests sent before giving up.
+    pub max_attempts: u16,
+    /// Data rates to use for successive attempts, in order, wrapping around. When empty, every
+    /// attempt uses the data rate configured on the device.
+    pub datarates: Vec<DR, 8>,
+    /// Upper bound of the random delay added on top of the duty cycle backoff, which avoids
+    /// devices rebooting together from retrying in lockstep.
+    pub max_jitter_ms: u32,
+}
+
+impl Default for JoinRetryConfig {
+    fn default() -> Self {
+        Self { max_attempts: 10, datarates: Vec::new(), max_jitter_ms: 5000 }
+    }
+}
+
+pub(crate) struct JoinScheduler {
+    config: JoinRetryConfig,
+    credentials: NetworkCredentials,
+    attempts: u16,
+    /// Time elapsed since the first attempt, as far as the scheduler can tell.
+    elapsed_ms: u64,
+    time_on_air_us: u32,
+    jitter_ms: u32,
+}
+
+impl JoinScheduler {
+    pub(crate) fn new(config: JoinRetryConfig, credentials: NetworkCredentials) -> Self {
+        Self { config, credentials, attempts: 0, elapsed_ms: 0, time_on_air_us: 0, jitter_ms: 0 }
+    }
+
+    /// Starts a new attempt, returning the credentials and data rate to use or `None` once the
+    /// budget is exhausted.
+    pub(crate) fn next_attempt<RNG: RngCore>(
+        &mut self,
+        rng: &mut RNG,
+    ) -> Option<(NetworkCredentials, Option<DR>)> {
+        if self.attempts >= self.config.max_attempts {
+            return None;
+        }
+        let datarates = &self.config.datarates;
+        let dr = if datarates.is_empty() {
+            None
+        } else {
+            Some(datarates[self.attempts as usize % datarates.len()])
+        };
+        self.attempts += 1;
+        self.jitter_ms = match self.config.max_jitter_ms {
+            0 => 0,
+            max => (rng.next_u32() as u64 % (max as u64 + 1)) as u32,
+        };
+        Some((self.credentials.clone(), dr))
+    }
+
+    pub(crate) fn record_time_on_air(&mut self, time_on_air_us: u32) {
+        self.time_on_air_us = time_on_air_us;
+    }
+
+    /// Delay before the next attempt, given that `rx_ms` were spent in the receive windows after
+    /// the previous JoinRequest. Returns `None` once the budget is exhausted.
+    pub(crate) fn backoff_ms(&mut self, rx_ms: u32) -> Option<u32> {
+        if self.attempts >= self.config.max_attempts {
+            return None;
+        }
+        let max_duty_cycle_inverse: u64 = match self.elapsed_ms {
+            t if t < HOUR_MS => 100,
+            t if t < 11 * HOUR_MS => 1_000,
+            _ => 10_000,
+        };
+        let time_on_air_us = self.time_on_air_us as u64;
+        let cycle_us = time_on_air_us * max_duty_cycle_inverse;
+        let spent_us = time_on_air_us + rx_ms as u64 * 1000;
+        let off_ms = cycle_us.saturating_sub(spent_us).div_ceil(1000);
+        let delay_ms = (off_ms + self.jitter_ms as u64).min(u32::MAX as u64) as u32;
+        self.elapsed_ms += spent_us.div_ceil(1000) + delay_ms as u64;
+        Some(delay_ms)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{AppEui, AppKey, DevEui};
+
+    fn scheduler(config: JoinRetryConfig) -> JoinScheduler {
+        let credentials = NetworkCredentials::new(
+            AppEui::from([0; 8]),
+            DevEui::from([0; 8]),
+            AppKey::from([0; 16]),
+        );
+        JoinScheduler::new(config, credentials)
+    }
+
+    #[test]
+    fn duty_cycle_backoff() {
+        let config =
+            JoinRetryConfig { max_attempts: u16::MAX, max_jitter_ms: 0, ..Default::default() };
+        let mut scheduler = scheduler(config);
+        let mut rng = crate::Prng::new(0);
+        // 1 s time on air: 100 s cycles during the first hour
+        scheduler.next_attempt(&mut rng).unwrap();
+        scheduler.record_time_on_air(1_000_000);
+        assert_eq!(scheduler.backoff_ms(6000), Some(93_000));
+        while scheduler.elapsed_ms < HOUR_MS {
+            scheduler.next_attempt(&mut rng).unwrap();
+            scheduler.backoff_ms(6000).unwrap();
+        }
+        // 0.1% until hour 11
+        assert_eq!(scheduler.backoff_ms(6000), Some(993_000));
+        scheduler.elapsed_ms = 11 * HOUR_MS;
+        // 0.01% afterwards
+        assert_eq!(scheduler.backoff_ms(6000), Some(9_993_000));
+    }
+
+    #[test]
+    fn budget_and_datarate_rotation() {
+        let mut datarates = Vec::new();
+        datarates.extend_from_slice(&[DR::_5, DR::_3]).unwrap();
+        let config = JoinRetryConfig { max_attempts: 3, datarates, max_jitter_ms: 100 };
+        let mut scheduler = scheduler(config);
+        let mut rng = crate::Prng::new(0);
+        let mut drs = Vec::<_, 3>::new();
+        while let Some((_, dr)) = scheduler.next_attempt(&mut rng) {
+            assert!(scheduler.jitter_ms <= 100);
+            drs.push(dr.unwrap()).unwrap();
+            if let Some(delay) = scheduler.backoff_ms(6000) {
+                assert!(delay <= 100);
+            }
+        }
+        assert_eq!(drs, [DR::_5, DR::_3, DR::_5]);
+        assert_eq!(scheduler.backoff_ms(6000), None);
+    }
+
+    #[test]
+    fn max_jitter() {
+        let config = JoinRetryConfig { max_jitter_ms: u32::MAX, ..Default::default() };
+        let mut scheduler = scheduler(config);
+        let mut rng = crate::Prng::new(0);
+        while scheduler.next_attempt(&mut rng).is_some() {
+            scheduler.backoff_ms(6000);
+        }
+    }
+}
@@ -3,12 +3,17 @@
 //! decrypting from send and receive buffers.
 
 use crate::{
-    radio::{self, RadioBuffer, RfConfig, RxConfig, RxMode},
-    region, AppSKey, Downlink, NwkSKey,
+    downlink::DownlinkQueue,
+    events::{EventSink, MacEvent},
+    radio::{self, RadioBuffer, RfConfig, RxConfig, RxMode, RxQuality},
+    region::{self, ChannelPlan, SpreadingFactor},
+    AppSKey, NwkSKey, RxWindow,
 };
-use heapless::Vec;
 use lorawan::{self, keys::CryptoFactory};
-use lorawan::{maccommands::DownlinkMacCommand, parser::DevAddr};
+use lorawan::{
+    maccommands::{DownlinkMacCommand, SerializableMacCommand},
+    parser::DevAddr,
+};
 
 pub type FcntDown = u32;
 pub type FcntUp = u32;
@@ -25,23 +30,64 @@ use crate::nb_device;
 
 pub(crate) mod uplink;
 
-#[derive(Copy, Clone, Debug)]
-pub(crate) enum Frame {
+mod join_retry;
+pub use join_retry::JoinRetryConfig;
+use join_retry::JoinScheduler;
+
+mod rx_timing;
+pub(crate) use rx_timing::RxTiming;
+
+mod statistics;
+use statistics::Recorder;
+pub use statistics::{ChannelAirtime, Statistics, MAX_TRACKED_CHANNELS};
+
+/// Kind of uplink frame a transmission or receive window belongs to.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt-03", derive(defmt::Format))]
+pub enum Frame {
     Join,
     Data,
 }
 
-#[derive(Copy, Clone, Debug)]
-pub(crate) enum Window {
+/// Class A receive window following an uplink.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt-03", derive(defmt::Format))]
+pub enum Window {
     _1,
     _2,
 }
 
+impl From<&Window> for RxWindow {
+    fn from(window: &Window) -> RxWindow {
+        match window {
+            Window::_1 => RxWindow::Rx1,
+            Window::_2 => RxWindow::Rx2,
+        }
+    }
+}
+
+/// Where and how a frame was received.
+#[derive(Copy, Clone, Debug)]
+pub(crate) struct Reception {
+    pub(crate) window: RxWindow,
+    pub(crate) quality: RxQuality,
+}
+
+/// Time on air of a frame of `len` bytes, using the 8 symbol preamble and explicit header
+/// mandated for LoRaWAN uplinks.
+pub(crate) fn time_on_air_us(tx_config: &radio::TxConfig, len: usize) -> u32 {
+    tx_config.rf.bb.time_on_air_us(Some(8), true, len as u8)
+}
+
 #[derive(Debug, PartialEq, Clone, Copy)]
 #[cfg_attr(feature = "defmt-03", derive(defmt::Format))]
 /// LoRaWAN Session and Network Configurations
 pub struct Configuration {
     pub(crate) data_rate: region::DR,
+    /// Whether the 400 ms uplink dwell time limit applies, which restricts the payload size.
+    pub(crate) uplink_dwell_time: bool,
+    /// TXPower index set by the network, 0 being the MaxEIRP of the region.
+    pub(crate) tx_power: u8,
     rx1_delay: u32,
     join_accept_delay1: u32,
     join_accept_delay2: u32,
@@ -50,36 +96,84 @@ pub struct Configuration {
 impl Configuration {
     fn handle_downlink_macs(
         &mut self,
-        region: &mut region::Configuration,
+        region: &mut impl ChannelPlan,
         uplink: &mut uplink::Uplink,
         cmds: lorawan::maccommands::MacCommandIterator<'_, DownlinkMacCommand<'_>>,
+        events: &mut impl EventSink,
     ) {
-        use uplink::MacAnsTrait;
         for cmd in cmds {
+            let cid = cmd.cid();
             match cmd {
                 DownlinkMacCommand::LinkADRReq(payload) => {
-                    // we ignore DR and TxPwr
+                    // we ignore DR; TXPower 15 keeps the current power
+                    let tx_power = match payload.tx_power() {
+                        0x0F => Some(self.tx_power),
+                        tx_power if tx_power <= region.get_max_tx_power() => Some(tx_power),
+                        _ => None,
+                    };
+                    let Some(tx_power) = tx_power else {
+                        uplink.ack_link_adr(0b011);
+                        events.event(MacEvent::MacCommandRejected { cid });
+                        continue;
+                    };
+                    self.tx_power = tx_power;
+                    let previous_mask = region.get_channel_mask();
                     region.set_channel_mask(
                         payload.redundancy().channel_mask_control(),
                         payload.channel_mask(),
                     );
-                    uplink.adr_ans.add();
+                    uplink.ack_link_adr(0b111);
+                    events.event(MacEvent::MacCommandApplied { cid });
+                    let channel_mask = region.get_channel_mask();
+                    if channel_mask != previous_mask {
+                        events.event(MacEvent::ChannelMaskChanged { channel_mask });
+                    }
                 }
                 DownlinkMacCommand::RXTimingSetupReq(payload) => {
                     self.rx1_delay = del_to_delay_ms(payload.delay());
                     uplink.ack_rx_delay();
+                    events.event(MacEvent::MacCommandApplied { cid });
+                }
+                DownlinkMacCommand::RXParamSetupReq(payload) => {
+                    let status = region
+                        .set_rx_params(payload.dl_settings(), Some(payload.frequency().value()));
+                    uplink.ack_rx_param_setup(status);
+                    events.event(if status == 0b111 {
+                        MacEvent::MacCommandApplied { cid }
+                    } else {
+                        MacEvent::MacCommandRejected { cid }
+                    });
                 }
-                _ => (),
+                DownlinkMacCommand::DlChannelReq(payload) => {
+                    match region.set_downlink_frequency(
+                        payload.channel_index(),
+                        payload.frequency().value(),
+                    ) {
+                        Some(status) => {
+                            uplink.ack_dl_channel(status);
+                            events.event(if status == 0b11 {
+                                MacEvent::MacCommandApplied { cid }
+                            } else {
+                                MacEvent::MacCommandRejected { cid }
+                            });
+                        }
+                        None => events.event(MacEvent::MacCommandRejected { cid }),
+                    }
+                }
+                _ => events.event(MacEvent::MacCommandRejected { cid }),
             }
         }
     }
 }
 
-pub(crate) struct Mac {
+pub(crate) struct Mac<P: ChannelPlan = region::Configuration> {
     pub configuration: Configuration,
-    pub region: region::Configuration,
+    pub region: P,
     board_eirp: BoardEirp,
     state: State,
+    statistics: Statistics,
+    join_scheduler: Option<JoinScheduler>,
+    listen_before_talk: Option<(i16, u32)>,
 }
 
 struct BoardEirp {
@@ -99,6 +193,21 @@ enum State {
 pub enum Error {
     NotJoined,
     InvalidResponse(Response),
+    /// The application payload exceeds the `max` bytes allowed by the current data rate and
+    /// dwell time, once pending MAC commands are accounted for.
+    PayloadTooLarge {
+        max: usize,
+    },
+    /// The frame could not be assembled (eg: data on FPort 0).
+    InvalidPayload(lorawan::creator::Error),
+    /// The channel plan allows no channel for the uplink.
+    NoChannelAvailable,
+}
+
+impl From<region::NoChannelAvailable> for Error {
+    fn from(_: region::NoChannelAvailable) -> Self {
+        Error::NoChannelAvailable
+    }
 }
 
 pub struct SendData<'a> {
@@ -109,15 +218,21 @@ pub struct SendData<'a> {
 
 pub(crate) type Result<T = ()> = core::result::Result<T, Error>;
 
-impl Mac {
-    pub(crate) fn new(region: region::Configuration, max_power: u8, antenna_gain: i8) -> Self {
+impl<P: ChannelPlan> Mac<P> {
+    pub(crate) fn new(region: P, max_power: u8, antenna_gain: i8) -> Self {
         let data_rate = region.get_default_datarate();
+        let uplink_dwell_time = region.get_default_uplink_dwell_time();
         Self {
             board_eirp: BoardEirp { max_power, antenna_gain },
             region,
             state: State::Unjoined,
+            statistics: Statistics::default(),
+            join_scheduler: None,
+            listen_before_talk: None,
             configuration: Configuration {
                 data_rate,
+                uplink_dwell_time,
+                tx_power: 0,
                 rx1_delay: region::constants::RECEIVE_DELAY1,
                 join_accept_delay1: region::constants::JOIN_ACCEPT_DELAY1,
                 join_accept_delay2: region::constants::JOIN_ACCEPT_DELAY2,
@@ -126,20 +241,19 @@ impl Mac {
     }
 
     /// Prepare the radio buffer with transmitting a join request frame and provides the radio
-    /// configuration for the transmission.
+    /// configuration for the transmission. Returns an error if the channel plan allows no
+    /// channel.
     pub(crate) fn join_otaa<C: CryptoFactory + Default, RNG: RngCore, const N: usize>(
         &mut self,
         rng: &mut RNG,
         credentials: NetworkCredentials,
         buf: &mut RadioBuffer<N>,
-    ) -> (radio::TxConfig, u16) {
+    ) -> Result<(radio::TxConfig, u16)> {
+        let tx_config = self.create_tx_config(rng, &Frame::Join)?;
         let mut otaa = otaa::Otaa::new(credentials);
         let dev_nonce = otaa.prepare_buffer::<C, RNG, N>(rng, buf);
         self.state = State::Otaa(otaa);
-        let mut tx_config =
-            self.region.create_tx_config(rng, self.configuration.data_rate, &Frame::Join);
-        tx_config.adjust_power(self.board_eirp.max_power, self.board_eirp.antenna_gain);
-        (tx_config, dev_nonce)
+        Ok((tx_config, dev_nonce))
     }
 
     /// Join via ABP. This does not transmit a join request frame, but instead sets the session.
@@ -158,24 +272,98 @@ impl Mac {
     }
 
     /// Prepare the radio buffer for transmitting a data frame and provide the radio configuration
-    /// for the transmission. Returns an error if the device is not joined.
+    /// for the transmission. Returns an error if the device is not joined or if the channel plan
+    /// allows no channel.
     pub(crate) fn send<C: CryptoFactory + Default, RNG: RngCore, const N: usize>(
         &mut self,
         rng: &mut RNG,
         buf: &mut RadioBuffer<N>,
         send_data: &SendData<'_>,
     ) -> Result<(radio::TxConfig, FcntUp)> {
+        if !matches!(self.state, State::Joined(_)) {
+            return Err(Error::NotJoined);
+        }
+        // the channel is selected first, so that no frame is prepared when none is allowed
+        let tx_config = self.create_tx_config(rng, &Frame::Data)?;
+        let max_mac_payload_len = self.max_mac_payload_len::<N>();
         let fcnt = match &mut self.state {
-            State::Joined(ref mut session) => Ok(session.prepare_buffer::<C, N>(send_data, buf)),
+            State::Joined(ref mut session) => {
+                session.prepare_buffer::<C, N>(send_data, buf, max_mac_payload_len)
+            }
             State::Otaa(_) => Err(Error::NotJoined),
             State::Unjoined => Err(Error::NotJoined),
         }?;
-        let mut tx_config =
-            self.region.create_tx_config(rng, self.configuration.data_rate, &Frame::Data);
-        tx_config.adjust_power(self.board_eirp.max_power, self.board_eirp.antenna_gain);
         Ok((tx_config, fcnt))
     }
 
+    /// Called when the frame in the radio buffer may be on air: the pending MAC answers and ACK
+    /// it carries are no longer sent in the following uplinks. Until then, a frame which is not
+    /// transmitted (eg: listen-before-talk found every channel busy) leaves them pending.
+    pub(crate) fn uplink_sent(&mut self) {
+        if let State::Joined(session) = &mut self.state {
+            session.uplink_sent();
+        }
+    }
+
+    /// Selects a channel for the frame in the radio buffer and provides the radio configuration
+    /// for transmitting it. Also used to move the frame to another channel when the first one is
+    /// busy.
+    pub(crate) fn create_tx_config<RNG: RngCore>(
+        &mut self,
+        rng: &mut RNG,
+        frame: &Frame,
+    ) -> Result<radio::TxConfig> {
+        let mut tx_config = self.region.create_tx_config(
+            rng,
+            self.configuration.data_rate,
+            self.configuration.tx_power,
+            frame,
+        )?;
+        tx_config.adjust_power(self.board_eirp.max_power, self.board_eirp.antenna_gain);
+        Ok(tx_config)
+    }
+
+    /// Requires listen-before-talk with the given energy detection threshold (dBm) and sensing
+    /// time (µs), in addition to the regions mandating it. `None` leaves it to the region.
+    pub(crate) fn set_listen_before_talk(&mut self, listen_before_talk: Option<(i16, u32)>) {
+        self.listen_before_talk = listen_before_talk;
+    }
+
+    /// Clear-channel assessment to perform before transmitting on `rf`, if any. Parameters
+    /// required by the user take precedence over the ones of the region.
+    pub(crate) fn get_clear_channel_config(
+        &self,
+        rf: RfConfig,
+    ) -> Option<radio::ClearChannelConfig> {
+        match self.listen_before_talk {
+            Some((threshold_dbm, sensing_time_us)) => {
+                Some(radio::ClearChannelConfig { rf, threshold_dbm, sensing_time_us })
+            }
+            None => self.region.get_clear_channel_config(rf),
+        }
+    }
+
+    /// Largest MACPayload allowed by the current data rate and dwell time which also fits in a
+    /// radio buffer of `N` bytes (MHDR and MIC excluded).
+    fn max_mac_payload_len<const N: usize>(&self) -> usize {
+        let max = self.region.get_max_payload_length(
+            self.configuration.data_rate,
+            false,
+            self.configuration.uplink_dwell_time,
+        ) as usize;
+        core::cmp::min(max, N.saturating_sub(5))
+    }
+
+    /// Largest application payload which may currently be sent, accounting for the MAC commands
+    /// pending in FOpts.
+    pub(crate) fn max_payload_len<const N: usize>(&self) -> usize {
+        let max_mac_payload_len = self.max_mac_payload_len::<N>();
+        match &self.state {
+            State::Joined(session) => session.max_payload_len(max_mac_payload_len),
+            State::Otaa(_) | State::Unjoined => max_mac_payload_len.saturating_sub(8),
+        }
+    }
+
     pub(crate) fn get_rx_delay(&self, frame: &Frame, window: &Window) -> u32 {
         match frame {
             Frame::Join => match window {
@@ -210,21 +398,36 @@ impl Mac {
     pub(crate) fn handle_rx<C: CryptoFactory + Default, const N: usize, const D: usize>(
         &mut self,
         buf: &mut RadioBuffer<N>,
-        dl: &mut Vec<Downlink, D>,
+        dl: &mut DownlinkQueue<D>,
+        reception: Reception,
+        events: &mut impl EventSink,
     ) -> Response {
         match &mut self.state {
-            State::Joined(ref mut session) => session.handle_rx::<C, N, D>(
-                &mut self.region,
-                &mut self.configuration,
-                buf,
-                dl,
-                false,
-            ),
+            State::Joined(ref mut session) => {
+                let confirmed = session.confirmed;
+                let (response, ack) = session.handle_rx::<C, N, D>(
+                    &mut self.region,
+                    &mut self.configuration,
+                    buf,
+                    dl,
+                    reception,
+                    &mut Recorder { statistics: &mut self.statistics, events },
+                );
+                if confirmed && matches!(response, Response::DownlinkReceived(_)) {
+                    self.statistics.record_confirmed_uplink();
+                    if ack {
+                        self.statistics.record_ack();
+                        self.region.set_last_channel_busy(false);
+                    }
+                }
+                response
+            }
             State::Otaa(ref mut otaa) => {
                 if let Some(session) =
                     otaa.handle_rx::<C, N>(&mut self.region, &mut self.configuration, buf)
                 {
                     self.state = State::Joined(session);
+                    self.join_scheduler = None;
                     Response::JoinSuccess
                 } else {
                     Response::NoUpdate
@@ -240,16 +443,21 @@ impl Mac {
     pub(crate) fn handle_rxc<C: CryptoFactory + Default, const N: usize, const D: usize>(
         &mut self,
         buf: &mut RadioBuffer<N>,
-        dl: &mut Vec<Downlink, D>,
+        dl: &mut DownlinkQueue<D>,
+        quality: RxQuality,
+        events: &mut impl EventSink,
     ) -> Result<Response> {
         match &mut self.state {
-            State::Joined(ref mut session) => Ok(session.handle_rx::<C, N, D>(
-                &mut self.region,
-                &mut self.configuration,
-                buf,
-                dl,
-                true,
-            )),
+            State::Joined(ref mut session) => Ok(session
+                .handle_rx::<C, N, D>(
+                    &mut self.region,
+                    &mut self.configuration,
+                    buf,
+                    dl,
+                    Reception { window: RxWindow::Rxc, quality },
+                    &mut Recorder { statistics: &mut self.statistics, events },
+                )
+                .0),
             State::Otaa(_) => Err(Error::NotJoined),
             State::Unjoined => Err(Error::NotJoined),
         }
@@ -257,7 +465,13 @@ impl Mac {
 
     pub(crate) fn rx2_complete(&mut self) -> Response {
         match &mut self.state {
-            State::Joined(session) => session.rx2_complete(),
+            State::Joined(session) => {
+                if session.confirmed {
+                    self.statistics.record_confirmed_uplink();
+                    self.region.set_last_channel_busy(true);
+                }
+                session.rx2_complete()
+            }
             State::Otaa(otaa) => otaa.rx2_complete(),
             State::Unjoined => Response::NoUpdate,
         }
@@ -279,6 +493,70 @@ impl Mac {
         }
     }
 
+    /// Accounts for the event in the statistics before passing it on to the sink.
+    pub(crate) fn emit(&mut self, events: &mut impl EventSink, event: MacEvent) {
+        if let (MacEvent::JoinRequestSent { time_on_air_us, .. }, Some(scheduler)) =
+            (&event, &mut self.join_scheduler)
+        {
+            scheduler.record_time_on_air(*time_on_air_us);
+        }
+        Recorder { statistics: &mut self.statistics, events }.event(event);
+    }
+
+    /// Starts retrying OTAA joins according to `config` until one succeeds or the attempts run
+    /// out.
+    pub(crate) fn start_join_retries(
+        &mut self,
+        config: JoinRetryConfig,
+        credentials: NetworkCredentials,
+    ) {
+        self.join_scheduler = Some(JoinScheduler::new(config, credentials));
+    }
+
+    pub(crate) fn stop_join_retries(&mut self) {
+        self.join_scheduler = None;
+    }
+
+    /// Provides the credentials for the next join attempt, switching to the next data rate of the
+    /// rotation, or `None` if there is no attempt left.
+    pub(crate) fn next_join_attempt<RNG: RngCore>(
+        &mut self,
+        rng: &mut RNG,
+    ) -> Option<NetworkCredentials> {
+        let scheduler = self.join_scheduler.as_mut()?;
+        match scheduler.next_attempt(rng) {
+            Some((credentials, dr)) => {
+                if let Some(dr) = dr {
+                    self.configuration.data_rate = dr;
+                }
+                Some(credentials)
+            }
+            None => {
+                self.join_scheduler = None;
+                None
+            }
+        }
+    }
+
+    /// After a join attempt went unanswered, provides how long to wait (from the end of RX2)
+    /// before the next attempt, or `None` if there is no attempt left.
+    pub(crate) fn join_backoff_ms(&mut self) -> Option<u32> {
+        let rx_ms = self.configuration.join_accept_delay2;
+        let backoff = self.join_scheduler.as_mut()?.backoff_ms(rx_ms);
+        if backoff.is_none() {
+            self.join_scheduler = None;
+        }
+        backoff
+    }
+
+    pub(crate) fn get_statistics(&self) -> &Statistics {
+        &self.statistics
+    }
+
+    pub(crate) fn reset_statistics(&mut self) {
+        self.statistics = Statistics::default();
+    }
+
     pub(crate) fn is_joined(&self) -> bool {
         matches!(&self.state, State::Joined(_))
     }
@@ -291,11 +569,21 @@ impl Mac {
         }
     }
 
-    pub(crate) fn get_rx_config(&self, buffer_ms: u32, frame: &Frame, window: &Window) -> RxConfig {
-        RxConfig {
-            rf: self.region.get_rx_config(self.configuration.data_rate, frame, window),
-            mode: RxMode::Single { ms: buffer_ms },
-        }
+    /// Provides when to start the radio for an RX window, in milliseconds after the end of the
+    /// transmission, and the radio configuration which times out unless a preamble is detected
+    /// within the timing error. `timing` gives the characteristics of the radio and timer for the
+    /// spreading factor of the window.
+    pub(crate) fn get_rx_window(
+        &self,
+        frame: &Frame,
+        window: &Window,
+        timing: impl FnOnce(SpreadingFactor) -> RxTiming,
+    ) -> (u32, RxConfig) {
+        let rf = self.region.get_rx_config(self.configuration.data_rate, frame, window);
+        let delay_ms = self.get_rx_delay(frame, window);
+        let params = timing(rf.bb.sf).window(&rf.bb, delay_ms);
+        let start_ms = delay_ms.saturating_add_signed(params.offset_ms);
+        (start_ms, RxConfig { rf, mode: RxMode::Single { symbols: params.timeout_symbols } })
     }
 
     #[cfg(feature = "class-c")]
@@ -1,6 +1,6 @@
 use super::{del_to_delay_ms, session::Session, Response};
 use crate::radio::RadioBuffer;
-use crate::region::Configuration;
+use crate::region::ChannelPlan;
 use crate::{AppEui, AppKey, DevEui};
 use lorawan::keys::CryptoFactory;
 use lorawan::{
@@ -49,7 +49,7 @@ impl Otaa {
 
     pub(crate) fn handle_rx<C: CryptoFactory + Default, const N: usize>(
         &mut self,
-        region: &mut Configuration,
+        region: &mut impl ChannelPlan,
         configuration: &mut super::Configuration,
         rx: &mut RadioBuffer<N>,
     ) -> Option<Session> {
@@ -57,8 +57,10 @@ impl Otaa {
             lorawan_parse(rx.as_mut_for_read(), C::default())
         {
             let decrypt = encrypted.decrypt(&self.network_credentials.appkey);
-            region.process_join_accept(&decrypt);
+            region.process_join_accept(decrypt.c_f_list());
+            region.set_rx_params(decrypt.dl_settings(), None);
             configuration.rx1_delay = del_to_delay_ms(decrypt.rx_delay());
+            configuration.tx_power = 0;
             if decrypt.validate_mic(&self.network_credentials.appkey) {
                 return Some(Session::derive_new(
                     &decrypt,
@@ -0,0 +1,86 @@
+//! Timing of the RX1 and RX2 windows.
+//!
+//! As in Semtech's LoRaMac-node, the receiver is started early enough and kept listening long
+//! enough for `min_rx_symbols` of the 8 preamble symbols to be detected anywhere within the
+//! timing error, which grows with the delay since the end of the transmission.
+use crate::radio::BaseBandModulationParams;
+
+/// Characteristics of the radio and of the timer which bound the timing error of RX windows.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct RxTiming {
+    /// Time needed to get the radio receiving, anticipated on top of the timing error.
+    pub(crate) wakeup_time_ms: u32,
+    /// Timing error regardless of the delay (eg: scheduling latency).
+    pub(crate) max_rx_error_ms: u32,
+    /// Drift of the timer, proportional to the delay.
+    pub(crate) clock_error_ppm: u32,
+    /// Number of preamble symbols the radio needs to detect a frame.
+    pub(crate) min_rx_symbols: u16,
+}
+
+/// When to start the radio, relative to the nominal start of the window, and for how many symbols
+/// to wait for a preamble.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct RxWindowParams {
+    pub(crate) offset_ms: i32,
+    pub(crate) timeout_symbols: u16,
+}
+
+impl RxTiming {
+    /// Parameters of a window opening `delay_ms` after the end of the transmission.
+    pub(crate) fn window(&self, bb: &BaseBandModulationParams, delay_ms: u32) -> RxWindowParams {
+        let t_sym_us = (1u64 << bb.sf.factor()) * 1_000_000 / bb.bw.hz() as u64;
+        let rx_error_us = self.max_rx_error_ms as u64 * 1000
+            + delay_ms as u64 * self.clock_error_ppm as u64 / 1000;
+        let min_rx_symbols = self.min_rx_symbols as u64;
+        let timeout_symbols = ((2 * min_rx_symbols).saturating_sub(8) * t_sym_us + 2 * rx_error_us)
+            .div_ceil(t_sym_us)
+            .max(min_rx_symbols)
+            .min(u16::MAX as u64);
+        // center the window on the middle of the preamble
+        let offset_us = 4 * t_sym_us as i64
+            - (timeout_symbols * t_sym_us / 2) as i64
+            - self.wakeup_time_ms as i64 * 1000;
+        let offset_ms = if offset_us < 0 {
+            offset_us - 500
+        } else {
+            offset_us + 500
+        } / 1000;
+        RxWindowParams { offset_ms: offset_ms as i32, timeout_symbols: timeout_symbols as u16 }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::region::{Bandwidth, CodingRate, SpreadingFactor};
+
+    const TIMING: RxTiming =
+        RxTiming { wakeup_time_ms: 1, max_rx_error_ms: 10, clock_error_ppm: 0, min_rx_symbols: 6 };
+
+    fn bb(sf: SpreadingFactor, bw: Bandwidth) -> BaseBandModulationParams {
+        BaseBandModulationParams::new(sf, bw, CodingRate::_4_5)
+    }
+
+    #[test]
+    fn test_sf12() {
+        // symbols of 32.768 ms cover the timing error with the minimum number of symbols
+        let params = TIMING.window(&bb(SpreadingFactor::_12, Bandwidth::_125KHz), 1000);
+        assert_eq!(params, RxWindowParams { offset_ms: 32, timeout_symbols: 6 });
+    }
+
+    #[test]
+    fn test_sf7() {
+        // symbols of 1.024 ms: the window is widened to cover 10 ms on either side
+        let params = TIMING.window(&bb(SpreadingFactor::_7, Bandwidth::_125KHz), 1000);
+        assert_eq!(params, RxWindowParams { offset_ms: -9, timeout_symbols: 24 });
+    }
+
+    #[test]
+    fn test_clock_error() {
+        // 5 s at 2000 ppm drift by 10 ms
+        let timing = RxTiming { clock_error_ppm: 2000, ..TIMING };
+        let params = timing.window(&bb(SpreadingFactor::_7, Bandwidth::_125KHz), 5000);
+        assert_eq!(params, RxWindowParams { offset_ms: -19, timeout_symbols: 44 });
+    }
+}
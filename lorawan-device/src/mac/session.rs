@@ -1,4 +1,8 @@
-use crate::{region, AppSKey, Downlink, NwkSKey};
+use crate::{
+    downlink::DownlinkQueue,
+    events::{EventSink, MacEvent},
+    region, AppSKey, Downlink, NwkSKey, RxWindow,
+};
 use heapless::Vec;
 use lorawan::keys::CryptoFactory;
 use lorawan::maccommands::{DownlinkMacCommand, MacCommandIterator};
@@ -13,7 +17,7 @@ use crate::radio::RadioBuffer;
 
 use super::{
     otaa::{DevNonce, NetworkCredentials},
-    uplink, FcntUp, Response, SendData,
+    uplink, Error, FcntUp, Reception, Response, SendData,
 };
 
 #[derive(Clone, Debug)]
@@ -95,20 +99,25 @@ impl Session {
 }
 
 impl Session {
+    /// Handles a received frame, also providing whether it acknowledged the last uplink (ACK bit
+    /// set in FCtrl).
     pub(crate) fn handle_rx<C: CryptoFactory + Default, const N: usize, const D: usize>(
         &mut self,
-        region: &mut region::Configuration,
+        region: &mut impl region::ChannelPlan,
         configuration: &mut super::Configuration,
         rx: &mut RadioBuffer<N>,
-        dl: &mut Vec<Downlink, D>,
-        ignore_mac: bool,
-    ) -> Response {
+        dl: &mut DownlinkQueue<D>,
+        reception: Reception,
+        events: &mut impl EventSink,
+    ) -> (Response, bool) {
+        let Reception { window, quality } = reception;
         if let Ok(PhyPayload::Data(DataPayload::Encrypted(encrypted_data))) =
             lorawan_parse(rx.as_mut_for_read(), C::default())
         {
             if self.devaddr() == &encrypted_data.fhdr().dev_addr() {
                 let fcnt = encrypted_data.fhdr().fcnt() as u32;
                 let confirmed = encrypted_data.is_confirmed();
+                let fctrl = encrypted_data.fhdr().fctrl();
                 if encrypted_data.validate_mic(self.nwkskey().inner(), fcnt)
                     && (fcnt > self.fcnt_down || fcnt == 0)
                 {
@@ -122,7 +131,9 @@ impl Session {
                         )
                         .unwrap();
 
-                    if !ignore_mac {
+                    // MAC commands are only processed in Class A windows
+                    if window != RxWindow::Rxc {
+                        self.uplink.clear_downlink_sticky_answers();
                         // MAC commands may be in the FHDR or the FRMPayload
                         configuration.handle_downlink_macs(
                             region,
@@ -130,12 +141,14 @@ impl Session {
                             MacCommandIterator::<DownlinkMacCommand<'_>>::new(
                                 decrypted.fhdr().data(),
                             ),
+                            events,
                         );
                         if let FRMPayload::MACCommands(mac_cmds) = decrypted.frm_payload() {
                             configuration.handle_downlink_macs(
                                 region,
                                 &mut self.uplink,
                                 MacCommandIterator::<DownlinkMacCommand<'_>>::new(mac_cmds.data()),
+                                events,
                             );
                         }
                     }
@@ -144,27 +157,44 @@ impl Session {
                         self.uplink.set_downlink_confirmation();
                     }
 
-                    return if self.fcnt_up == 0xFFFF_FFFF {
+                    let response = if self.fcnt_up == 0xFFFF_FFFF {
                         // if the FCnt is used up, the session has expired
                         Response::SessionExpired
                     } else {
                         // we can always increment fcnt_up when we receive a downlink
                         self.fcnt_up += 1;
+                        events.event(MacEvent::DownlinkReceived {
+                            window,
+                            fcnt_down: fcnt,
+                            quality,
+                        });
                         if let (Some(fport), FRMPayload::Data(data)) =
                             (decrypted.f_port(), decrypted.frm_payload())
                         {
                             // heapless Vec from slice fails only if slice is too large.
                             // A data FRM payload will never exceed 256 bytes.
                             let data = Vec::from_slice(data).unwrap();
-                            // TODO: propagate error type when heapless vec is full?
-                            let _ = dl.push(Downlink { data, fport });
+                            let downlink = Downlink {
+                                data,
+                                fport,
+                                window,
+                                quality,
+                                fcnt_down: fcnt,
+                                ack: fctrl.ack(),
+                                fpending: fctrl.f_pending(),
+                                confirmed,
+                            };
+                            if let Some(dropped) = dl.push(downlink) {
+                                events.event(MacEvent::DownlinkDropped { fport: dropped.fport });
+                            }
                         }
                         Response::DownlinkReceived(fcnt)
                     };
+                    return (response, fctrl.ack());
                 }
             }
         }
-        Response::NoUpdate
+        (Response::NoUpdate, false)
     }
 
     pub(crate) fn rx2_complete(&mut self) -> Response {
@@ -182,11 +212,30 @@ impl Session {
         }
     }
 
+    /// Largest application payload which fits alongside the pending FOpts MAC commands, given the
+    /// largest MACPayload allowed by the current data rate.
+    pub(crate) fn max_payload_len(&self, max_mac_payload_len: usize) -> usize {
+        // FHDR without FOpts (7 bytes) and FPort
+        max_mac_payload_len.saturating_sub(8 + self.uplink.fopts_len())
+    }
+
+    /// Clears the ACK and MAC answers carried by the frame prepared last, which is being
+    /// transmitted.
+    pub(crate) fn uplink_sent(&mut self) {
+        self.uplink.clear_downlink_confirmation();
+        self.uplink.clear_sent_cmds();
+    }
+
     pub(crate) fn prepare_buffer<C: CryptoFactory + Default, const N: usize>(
         &mut self,
         data: &SendData<'_>,
         tx_buffer: &mut RadioBuffer<N>,
-    ) -> FcntUp {
+        max_mac_payload_len: usize,
+    ) -> super::Result<FcntUp> {
+        let max = self.max_payload_len(max_mac_payload_len);
+        if data.data.len() > max {
+            return Err(Error::PayloadTooLarge { max });
+        }
         tx_buffer.clear();
         let fcnt = self.fcnt_up;
         let mut buf = [0u8; 256];
@@ -195,7 +244,6 @@ impl Session {
         let mut fctrl = FCtrl(0x0, true);
         if self.uplink.confirms_downlink() {
             fctrl.set_ack();
-            self.uplink.clear_downlink_confirmation();
         }
 
         self.confirmed = data.confirmed;
@@ -207,6 +255,7 @@ impl Session {
             .set_fcnt(fcnt);
 
         let mut cmds = Vec::new();
+        // taken from the pending ones by `uplink_sent`, once the frame may be on air
         self.uplink.get_cmds(&mut cmds);
         let mut dyn_cmds: Vec<&dyn SerializableMacCommand, 8> = Vec::new();
 
@@ -217,19 +266,12 @@ impl Session {
         }
 
         let crypto_factory = C::default();
-        match phy.build(
-            data.data,
-            dyn_cmds.as_slice(),
-            &self.nwkskey,
-            &self.appskey,
-            &crypto_factory,
-        ) {
-            Ok(packet) => {
-                tx_buffer.clear();
-                tx_buffer.extend_from_slice(packet).unwrap();
-            }
-            Err(e) => panic!("Error assembling packet! {:?} ", e),
-        }
-        fcnt
+        let packet = phy
+            .build(data.data, dyn_cmds.as_slice(), &self.nwkskey, &self.appskey, &crypto_factory)
+            .map_err(Error::InvalidPayload)?;
+        tx_buffer.clear();
+        // The packet fits since the MACPayload is bounded by the buffer size.
+        tx_buffer.extend_from_slice(packet).unwrap();
+        Ok(fcnt)
     }
 }
@@ -0,0 +1,253 @@
+//! Link statistics accumulated by the MAC.
+use crate::events::{EventSink, MacEvent};
+use crate::RxWindow;
+use heapless::Vec;
+
+/// Maximum number of distinct frequencies for which airtime is tracked individually. Airtime on
+/// further frequencies is still accounted for in [`Statistics::time_on_air_us`].
+pub const MAX_TRACKED_CHANNELS: usize = 16;
+
+/// Accumulated time on air for a single uplink frequency.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt-03", derive(defmt::Format))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ChannelAirtime {
+    /// Uplink frequency in Hz.
+    pub frequency: u32,
+    /// Total time on air on this frequency, in microseconds.
+    pub time_on_air_us: u64,
+}
+
+/// Statistics about uplinks, downlinks and link quality since the device was created or since
+/// the last call to `reset_statistics`.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt-03", derive(defmt::Format))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Statistics {
+    time_on_air_us: u64,
+    channels: Vec<ChannelAirtime, MAX_TRACKED_CHANNELS>,
+    join_attempts: u32,
+    uplinks: u32,
+    confirmed_uplinks: u32,
+    acks: u32,
+    downlinks: u32,
+    rx1_downlinks: u32,
+    rx2_downlinks: u32,
+    rxc_downlinks: u32,
+    dropped_downlinks: u32,
+    last_rssi: Option<i16>,
+    last_snr: Option<i8>,
+    rssi_sum: i64,
+    snr_sum: i64,
+}
+
+impl Statistics {
+    /// Total time on air of all transmissions (join requests and data uplinks), in microseconds.
+    pub fn time_on_air_us(&self) -> u64 {
+        self.time_on_air_us
+    }
+
+    /// Time on air per uplink frequency, in order of first use.
+    pub fn channels(&self) -> &[ChannelAirtime] {
+        &self.channels
+    }
+
+    /// Number of join requests transmitted.
+    pub fn join_attempts(&self) -> u32 {
+        self.join_attempts
+    }
+
+    /// Number of data uplinks transmitted, confirmed or not.
+    pub fn uplinks(&self) -> u32 {
+        self.uplinks
+    }
+
+    /// Number of confirmed data uplinks for which the receive windows have completed.
+    pub fn confirmed_uplinks(&self) -> u32 {
+        self.confirmed_uplinks
+    }
+
+    /// Number of confirmed data uplinks which were acknowledged in RX1 or RX2.
+    pub fn acks(&self) -> u32 {
+        self.acks
+    }
+
+    /// Ratio of acknowledged confirmed uplinks, or `None` if no confirmed uplink was sent.
+    pub fn ack_rate(&self) -> Option<f32> {
+        if self.confirmed_uplinks == 0 {
+            None
+        } else {
+            Some(self.acks as f32 / self.confirmed_uplinks as f32)
+        }
+    }
+
+    /// Number of data downlinks received in any window.
+    pub fn downlinks(&self) -> u32 {
+        self.downlinks
+    }
+
+    /// Number of data downlinks received in the given window.
+    pub fn downlinks_in(&self, window: RxWindow) -> u32 {
+        match window {
+            RxWindow::Rx1 => self.rx1_downlinks,
+            RxWindow::Rx2 => self.rx2_downlinks,
+            RxWindow::Rxc => self.rxc_downlinks,
+        }
+    }
+
+    /// Number of downlinks discarded because the downlink queue was full.
+    pub fn dropped_downlinks(&self) -> u32 {
+        self.dropped_downlinks
+    }
+
+    /// RSSI of the most recent downlink, in dBm.
+    pub fn last_rssi(&self) -> Option<i16> {
+        self.last_rssi
+    }
+
+    /// SNR of the most recent downlink, in dB.
+    pub fn last_snr(&self) -> Option<i8> {
+        self.last_snr
+    }
+
+    /// Average RSSI over all downlinks, in dBm.
+    pub fn average_rssi(&self) -> Option<i16> {
+        self.average(self.rssi_sum).map(|avg| avg as i16)
+    }
+
+    /// Average SNR over all downlinks, in dB.
+    pub fn average_snr(&self) -> Option<i8> {
+        self.average(self.snr_sum).map(|avg| avg as i8)
+    }
+
+    fn average(&self, sum: i64) -> Option<i64> {
+        if self.downlinks == 0 {
+            None
+        } else {
+            Some(sum / self.downlinks as i64)
+        }
+    }
+
+    pub(crate) fn record(&mut self, event: &MacEvent) {
+        match *event {
+            MacEvent::JoinRequestSent { tx_config, time_on_air_us, .. } => {
+                self.join_attempts += 1;
+                self.record_airtime(tx_config.rf.frequency, time_on_air_us);
+            }
+            MacEvent::UplinkSent { tx_config, time_on_air_us, .. } => {
+                self.uplinks += 1;
+                self.record_airtime(tx_config.rf.frequency, time_on_air_us);
+            }
+            MacEvent::DownlinkReceived { window, quality, .. } => {
+                self.downlinks += 1;
+                match window {
+                    RxWindow::Rx1 => self.rx1_downlinks += 1,
+                    RxWindow::Rx2 => self.rx2_downlinks += 1,
+                    RxWindow::Rxc => self.rxc_downlinks += 1,
+                }
+                self.last_rssi = Some(quality.rssi());
+                self.last_snr = Some(quality.snr());
+                self.rssi_sum += quality.rssi() as i64;
+                self.snr_sum += quality.snr() as i64;
+            }
+            MacEvent::DownlinkDropped { .. } => self.dropped_downlinks += 1,
+            _ => (),
+        }
+    }
+
+    pub(crate) fn record_confirmed_uplink(&mut self) {
+        self.confirmed_uplinks += 1;
+    }
+
+    pub(crate) fn record_ack(&mut self) {
+        self.acks += 1;
+    }
+
+    fn record_airtime(&mut self, frequency: u32, time_on_air_us: u32) {
+        self.time_on_air_us += time_on_air_us as u64;
+        if let Some(channel) = self.channels.iter_mut().find(|c| c.frequency == frequency) {
+            channel.time_on_air_us += time_on_air_us as u64;
+        } else {
+            let _ = self
+                .channels
+                .push(ChannelAirtime { frequency, time_on_air_us: time_on_air_us as u64 });
+        }
+    }
+}
+
+/// Accounts for events in the statistics before passing them on to the user's sink.
+pub(crate) struct Recorder<'a, E> {
+    pub(crate) statistics: &'a mut Statistics,
+    pub(crate) events: &'a mut E,
+}
+
+impl<E: EventSink> EventSink for Recorder<'_, E> {
+    fn event(&mut self, event: MacEvent) {
+        self.statistics.record(&event);
+        self.events.event(event);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::radio::{RfConfig, RxQuality, TxConfig};
+    use lora_modulation::{Bandwidth, BaseBandModulationParams, CodingRate, SpreadingFactor};
+
+    fn tx_config(frequency: u32) -> TxConfig {
+        TxConfig {
+            pw: 14,
+            rf: RfConfig {
+                frequency,
+                bb: BaseBandModulationParams::new(
+                    SpreadingFactor::_7,
+                    Bandwidth::_125KHz,
+                    CodingRate::_4_5,
+                ),
+            },
+        }
+    }
+
+    #[test]
+    fn airtime_per_channel() {
+        let mut stats = Statistics::default();
+        let (a, b) = (tx_config(868_100_000), tx_config(868_300_000));
+        stats.record(&MacEvent::JoinRequestSent { dev_nonce: 0, tx_config: a, time_on_air_us: 10 });
+        stats.record(&MacEvent::UplinkSent { fcnt_up: 0, tx_config: b, time_on_air_us: 20 });
+        stats.record(&MacEvent::UplinkSent { fcnt_up: 1, tx_config: a, time_on_air_us: 30 });
+        assert_eq!(stats.time_on_air_us(), 60);
+        assert_eq!(stats.join_attempts(), 1);
+        assert_eq!(stats.uplinks(), 2);
+        assert_eq!(
+            stats.channels(),
+            &[
+                ChannelAirtime { frequency: 868_100_000, time_on_air_us: 40 },
+                ChannelAirtime { frequency: 868_300_000, time_on_air_us: 20 },
+            ]
+        );
+    }
+
+    #[test]
+    fn downlink_quality() {
+        let mut stats = Statistics::default();
+        assert_eq!(stats.average_rssi(), None);
+        assert_eq!(stats.ack_rate(), None);
+        for (window, rssi, snr) in [(RxWindow::Rx1, -50, 10), (RxWindow::Rx2, -100, -4)] {
+            let quality = RxQuality::new(rssi, snr);
+            stats.record(&MacEvent::DownlinkReceived { window, fcnt_down: 0, quality });
+        }
+        assert_eq!(stats.downlinks(), 2);
+        assert_eq!(stats.downlinks_in(RxWindow::Rx1), 1);
+        assert_eq!(stats.downlinks_in(RxWindow::Rx2), 1);
+        assert_eq!(stats.downlinks_in(RxWindow::Rxc), 0);
+        assert_eq!(stats.last_rssi(), Some(-100));
+        assert_eq!(stats.last_snr(), Some(-4));
+        assert_eq!(stats.average_rssi(), Some(-75));
+        assert_eq!(stats.average_snr(), Some(3));
+
+        stats.record_confirmed_uplink();
+        stats.record_confirmed_uplink();
+        stats.record_ack();
+        assert_eq!(stats.ack_rate(), Some(0.5));
+    }
+}
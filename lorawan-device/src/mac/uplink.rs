@@ -3,20 +3,25 @@ This a temporary design where flags will be left about desired MAC uplinks by th
 During Uplink assembly, this struct will be inquired to drive construction
  */
 use heapless::Vec;
-use lorawan::maccommands::{LinkADRAnsPayload, RXTimingSetupAnsPayload, UplinkMacCommand};
+use lorawan::maccommands::{
+    DlChannelAnsPayload, LinkADRAnsPayload, RXParamSetupAnsPayload, RXTimingSetupAnsPayload,
+    UplinkMacCommand,
+};
 
 #[derive(Default, Debug, Clone)]
 #[cfg_attr(feature = "defmt-03", derive(defmt::Format))]
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Uplink {
-    pub adr_ans: AdrAns,
+    link_adr_ans: Vec<u8, 4>,
     pub rx_delay_ans: RxDelayAns,
+    rx_param_setup_ans: Option<u8>,
+    dl_channel_ans: Vec<u8, 2>,
     confirmed: bool,
 }
 
-// multiple AdrAns may happen per downlink
-// so we aggregate how many AdrAns are required
-type AdrAns = u8;
+// Payloads of the answers which carry a status, indexed by the status
+const STATUS_PAYLOADS: [[u8; 1]; 8] = [[0], [1], [2], [3], [4], [5], [6], [7]];
+
 // only one RxDelayReq will happen
 // so we only need to implement this as a bool
 type RxDelayAns = bool;
@@ -25,25 +30,9 @@ type RxDelayAns = bool;
 pub(crate) trait MacAnsTrait {
     fn add(&mut self);
     fn clear(&mut self);
-    // we use a uint instead of bool because some ADR responses
-    // require a counter for state.
-    // eg: ADR Req may be batched in a single downlink and require
-    // multiple ADR Ans in the next uplink
     fn get(&self) -> u8;
 }
 
-impl MacAnsTrait for AdrAns {
-    fn add(&mut self) {
-        *self += 1;
-    }
-    fn clear(&mut self) {
-        *self = 0;
-    }
-    fn get(&self) -> u8 {
-        *self
-    }
-}
-
 impl MacAnsTrait for RxDelayAns {
     fn add(&mut self) {
         *self = true;
@@ -68,21 +57,76 @@ impl Uplink {
         self.confirmed
     }
 
+    pub fn ack_link_adr(&mut self, status: u8) {
+        // multiple LinkADRReqs may happen per downlink; more than this are answered with the
+        // first ones
+        let _ = self.link_adr_ans.push(status & 0b111);
+    }
+
     pub fn ack_rx_delay(&mut self) {
         self.rx_delay_ans.add();
     }
 
-    pub fn get_cmds(&mut self, macs: &mut Vec<UplinkMacCommand<'_>, 8>) {
-        for _ in 0..self.adr_ans.get() {
-            macs.push(UplinkMacCommand::LinkADRAns(LinkADRAnsPayload::new(&[0x07]).unwrap()))
-                .unwrap();
+    pub fn ack_rx_param_setup(&mut self, status: u8) {
+        self.rx_param_setup_ans = Some(status & 0b111);
+    }
+
+    pub fn ack_dl_channel(&mut self, status: u8) {
+        // more DlChannelReqs than this in one downlink are answered with the first ones
+        let _ = self.dl_channel_ans.push(status & 0b11);
+    }
+
+    /// Stops repeating RXParamSetupAns and DlChannelAns, which are sent in every uplink until a
+    /// Class A downlink is received, as required since LoRaWAN 1.0.2.
+    pub fn clear_downlink_sticky_answers(&mut self) {
+        self.rx_param_setup_ans = None;
+        self.dl_channel_ans.clear();
+    }
+
+    /// Length of the MAC commands which will be piggybacked in the FOpts of the next uplink.
+    pub fn fopts_len(&self) -> usize {
+        // LinkADRAns, RXParamSetupAns and DlChannelAns are CID + status, RXTimingSetupAns is
+        // only a CID
+        self.link_adr_ans.len() * 2
+            + self.rx_delay_ans.get() as usize
+            + self.rx_param_setup_ans.map_or(0, |_| 2)
+            + self.dl_channel_ans.len() * 2
+    }
+
+    /// Forgets the answers sent in an uplink, except for the ones repeated until a downlink is
+    /// received.
+    pub fn clear_sent_cmds(&mut self) {
+        self.link_adr_ans.clear();
+        self.rx_delay_ans.clear();
+    }
+
+    /// MAC commands to piggyback in the next uplink. They remain pending until
+    /// [`clear_sent_cmds`](Self::clear_sent_cmds) is called once the uplink is transmitted.
+    pub fn get_cmds(&self, macs: &mut Vec<UplinkMacCommand<'_>, 8>) {
+        for status in &self.link_adr_ans {
+            macs.push(UplinkMacCommand::LinkADRAns(
+                LinkADRAnsPayload::new(&STATUS_PAYLOADS[*status as usize]).unwrap(),
+            ))
+            .unwrap();
         }
-        self.adr_ans.clear();
 
         if self.rx_delay_ans.get() != 0 {
             macs.push(UplinkMacCommand::RXTimingSetupAns(RXTimingSetupAnsPayload::new(&[])))
                 .unwrap();
         }
-        self.rx_delay_ans.clear();
+
+        if let Some(status) = self.rx_param_setup_ans {
+            macs.push(UplinkMacCommand::RXParamSetupAns(
+                RXParamSetupAnsPayload::new(&STATUS_PAYLOADS[status as usize]).unwrap(),
+            ))
+            .unwrap();
+        }
+
+        for status in &self.dl_channel_ans {
+            macs.push(UplinkMacCommand::DlChannelAns(
+                DlChannelAnsPayload::new(&STATUS_PAYLOADS[*status as usize]).unwrap(),
+            ))
+            .unwrap();
+        }
     }
 }
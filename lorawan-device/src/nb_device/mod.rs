@@ -3,7 +3,11 @@
 //! implementation.
 use super::radio::RadioBuffer;
 use super::*;
+use crate::device::impl_lorawan_device;
+use crate::downlink::DownlinkQueue;
+use crate::events::EventSink;
 use crate::nb_device::radio::PhyRxTx;
+use crate::region::ChannelPlan;
 use mac::{Mac, SendData};
 
 pub(crate) mod state;
@@ -14,17 +18,43 @@ mod test;
 
 type TimestampMs = u32;
 
-pub struct Device<R, C, RNG, const N: usize, const D: usize = 1>
+/// A non-blocking LoRaWAN device. `E` is the [`EventSink`] receiving the
+/// [`MacEvent`](crate::events::MacEvent)s of this device, which defaults to `()` (ie: discard).
+/// `P` is the [`ChannelPlan`], which defaults to the built-in regions of
+/// [`region::Configuration`].
+///
+/// Listen-before-talk is not supported: uplinks in the regions requiring it (eg: KR920) fail with
+/// [`Error::ClearChannelUnsupported`].
+pub struct Device<R, C, RNG, const N: usize, const D: usize = 1, E = (), P = region::Configuration>
 where
     R: PhyRxTx + Timings,
     C: CryptoFactory + Default,
     RNG: RngCore,
+    E: EventSink,
+    P: ChannelPlan,
 {
     state: State,
-    shared: Shared<R, RNG, N, D>,
+    shared: Shared<R, RNG, N, D, E, P>,
     crypto: PhantomData<C>,
 }
 
+impl<R, C, const N: usize, const D: usize> Device<R, C, Prng, N, D>
+where
+    R: PhyRxTx + Timings,
+    C: CryptoFactory + Default,
+{
+    /// Create a new [`Device`] which uses a builtin PRNG seeded with `seed`.
+    ///
+    /// # ⚠️Warning⚠️
+    ///
+    /// This function must **always** be called with a new randomly generated seed! **Never** call this function more
+    /// than once using the same seed. Generate the seed using a true random number generator. Using the same seed will
+    /// leave you vulnerable to replay attacks.
+    pub fn new_with_seed(region: region::Configuration, radio: R, seed: u64) -> Self {
+        Device::new(region, radio, Prng::new(seed))
+    }
+}
+
 impl<R, C, RNG, const N: usize, const D: usize> Device<R, C, RNG, N, D>
 where
     R: PhyRxTx + Timings,
@@ -32,6 +62,34 @@ where
     RNG: RngCore,
 {
     pub fn new(region: region::Configuration, radio: R, rng: RNG) -> Device<R, C, RNG, N, D> {
+        Device::new_with_channel_plan(region, radio, rng)
+    }
+
+    /// Create a new [`Device`] and provide an optional [`Session`](mac::Session).
+    pub fn new_with_session(
+        region: region::Configuration,
+        radio: R,
+        rng: RNG,
+        session: Option<mac::Session>,
+    ) -> Device<R, C, RNG, N, D> {
+        let mut device = Device::new(region, radio, rng);
+        if let Some(session) = session {
+            device.set_session(session);
+        }
+        device
+    }
+}
+
+impl<R, C, RNG, const N: usize, const D: usize, P> Device<R, C, RNG, N, D, (), P>
+where
+    R: PhyRxTx + Timings,
+    C: CryptoFactory + Default,
+    RNG: RngCore,
+    P: ChannelPlan,
+{
+    /// Create a new [`Device`] using a custom [`ChannelPlan`] instead of one of the built-in
+    /// regions.
+    pub fn new_with_channel_plan(plan: P, radio: R, rng: RNG) -> Self {
         Device {
             crypto: PhantomData,
             state: State::default(),
@@ -39,13 +97,59 @@ where
                 radio,
                 rng,
                 tx_buffer: RadioBuffer::new(),
-                mac: Mac::new(region, R::MAX_RADIO_POWER, R::ANTENNA_GAIN),
-                downlink: Vec::new(),
+                mac: Mac::new(plan, R::MAX_RADIO_POWER, R::ANTENNA_GAIN),
+                downlink: DownlinkQueue::new(),
+                events: (),
+                class_c: false,
             },
         }
     }
+}
+
+impl<R, C, RNG, const N: usize, const D: usize, E, P> Device<R, C, RNG, N, D, E, P>
+where
+    R: PhyRxTx + Timings,
+    C: CryptoFactory + Default,
+    RNG: RngCore,
+    E: EventSink,
+    P: ChannelPlan,
+{
+    /// Replace the [`EventSink`] which receives the MAC events of this device.
+    pub fn with_event_sink<S: EventSink>(self, events: S) -> Device<R, C, RNG, N, D, S, P> {
+        let Shared { radio, rng, tx_buffer, mac, downlink, class_c, .. } = self.shared;
+        Device {
+            crypto: PhantomData,
+            state: self.state,
+            shared: Shared { radio, rng, tx_buffer, mac, downlink, events, class_c },
+        }
+    }
+
+    /// Largest application payload which can be passed to `send` with the current data rate, dwell
+    /// time and pending MAC commands.
+    pub fn max_payload_len(&self) -> usize {
+        self.shared.mac.max_payload_len::<N>()
+    }
+
+    /// Statistics accumulated since the device was created or since the last
+    /// [`reset_statistics`](Self::reset_statistics).
+    pub fn get_statistics(&self) -> &mac::Statistics {
+        self.shared.mac.get_statistics()
+    }
+
+    pub fn reset_statistics(&mut self) {
+        self.shared.mac.reset_statistics()
+    }
+
+    pub fn get_event_sink(&self) -> &E {
+        &self.shared.events
+    }
+
+    pub fn get_mut_event_sink(&mut self) -> &mut E {
+        &mut self.shared.events
+    }
 
     pub fn join(&mut self, join_mode: JoinMode) -> Result<Response, Error<R>> {
+        self.shared.mac.stop_join_retries();
         match join_mode {
             JoinMode::OTAA { deveui, appeui, appkey } => {
                 self.handle_event(Event::Join(NetworkCredentials::new(appeui, deveui, appkey)))
@@ -57,11 +161,59 @@ where
         }
     }
 
-    pub fn get_radio(&mut self) -> &mut R {
+    /// Join the network, retrying OTAA joins which are not answered according to `config`.
+    ///
+    /// Instead of `Response::NoJoinAccept`, an unanswered JoinRequest results in a
+    /// `Response::TimeoutRequest` for the time of the next attempt, which respects the duty cycle
+    /// limits of the LoRaWAN specification. `Response::NoJoinAccept` is only returned once
+    /// `config.max_attempts` JoinRequests went unanswered.
+    pub fn join_with_retries(
+        &mut self,
+        join_mode: JoinMode,
+        config: mac::JoinRetryConfig,
+    ) -> Result<Response, Error<R>> {
+        let JoinMode::OTAA { deveui, appeui, appkey } = join_mode else {
+            return self.join(join_mode);
+        };
+        let mac = &mut self.shared.mac;
+        mac.start_join_retries(config, NetworkCredentials::new(appeui, deveui, appkey));
+        match mac.next_join_attempt(&mut self.shared.rng) {
+            Some(creds) => self.handle_event(Event::Join(creds)),
+            None => Ok(Response::NoJoinAccept),
+        }
+    }
+
+    /// Enables Class C behavior: once joined, the radio listens to RXC whenever it is not
+    /// transmitting nor receiving in RX1 or RX2, and frames received there are handled as radio
+    /// events. Class C downlinks are not possible until a confirmed uplink is sent to the LNS,
+    /// and listening starts with the next uplink.
+    ///
+    /// Class C downlinks may be reported between the RX windows of an uplink, before its own
+    /// response. It is recommended to call `take_downlink` until it returns `None`.
+    #[cfg(feature = "class-c")]
+    pub fn enable_class_c(&mut self) {
+        self.shared.class_c = true;
+    }
+
+    /// Disables Class C behavior. The radio stops listening to RXC after the next uplink.
+    #[cfg(feature = "class-c")]
+    pub fn disable_class_c(&mut self) {
+        self.shared.class_c = false;
+    }
+
+    pub fn get_region(&self) -> &P {
+        &self.shared.mac.region
+    }
+
+    pub fn get_radio(&self) -> &R {
+        &self.shared.radio
+    }
+
+    pub fn get_mut_radio(&mut self) -> &mut R {
         &mut self.shared.radio
     }
 
-    pub fn get_datarate(&mut self) -> region::DR {
+    pub fn get_datarate(&self) -> region::DR {
         self.shared.mac.configuration.data_rate
     }
 
@@ -69,6 +221,27 @@ where
         self.shared.mac.configuration.data_rate = datarate
     }
 
+    /// When the device next needs the CPU, ie: the timestamp of the last
+    /// [`Response::TimeoutRequest`] still pending, in the time base of the timestamps of
+    /// [`TxDone`](radio::Response::TxDone). The start or end of an RX window, or the next
+    /// JoinRequest of [`join_with_retries`](Self::join_with_retries).
+    ///
+    /// `None` while only a radio interrupt (eg: the end of a transmission, or a Class C downlink)
+    /// or the application can make progress, so that the MCU may sleep until an interrupt.
+    pub fn next_deadline(&self) -> Option<u32> {
+        self.state.next_deadline()
+    }
+
+    /// Milliseconds until [`next_deadline`](Self::next_deadline), which may be 0 if it went by,
+    /// given the current timestamp `now`. The timestamps may wrap around: a deadline less than
+    /// half their range ahead of `now` is in the future, any other went by.
+    pub fn time_until_deadline(&self, now: u32) -> Option<u32> {
+        self.next_deadline().map(|deadline| match deadline.wrapping_sub(now) {
+            remaining if remaining <= u32::MAX / 2 => remaining,
+            _ => 0,
+        })
+    }
+
     pub fn ready_to_send_data(&self) -> bool {
         matches!(&self.state, State::Idle(_)) && self.shared.mac.is_joined()
     }
@@ -93,30 +266,56 @@ where
         self.shared.mac.get_session_keys()
     }
 
+    /// Takes the oldest downlink from the queue.
     pub fn take_downlink(&mut self) -> Option<Downlink> {
         self.shared.downlink.pop()
     }
 
+    /// Chooses which downlink to discard when one is received while the queue of `D` downlinks is
+    /// full. Defaults to [`OverflowPolicy::DropNewest`].
+    pub fn set_downlink_overflow_policy(&mut self, policy: OverflowPolicy) {
+        self.shared.downlink.set_policy(policy)
+    }
+
     pub fn handle_event(&mut self, event: Event<'_, R>) -> Result<Response, Error<R>> {
-        let (new_state, result) = self.state.handle_event::<R, C, RNG, N, D>(
-            &mut self.shared.mac,
-            &mut self.shared.radio,
-            &mut self.shared.rng,
-            &mut self.shared.tx_buffer,
-            &mut self.shared.downlink,
-            event,
-        );
+        let (new_state, result) =
+            self.state.handle_event::<R, C, RNG, N, D, E, P>(&mut self.shared, event);
         self.state = new_state;
         result
     }
 }
 
-pub(crate) struct Shared<R: PhyRxTx + Timings, RNG: RngCore, const N: usize, const D: usize> {
+impl<R, C, RNG, const N: usize, const D: usize, E, P> LorawanDevice
+    for Device<R, C, RNG, N, D, E, P>
+where
+    R: PhyRxTx + Timings,
+    C: CryptoFactory + Default,
+    RNG: RngCore,
+    E: EventSink,
+    P: ChannelPlan,
+{
+    type Radio = R;
+    type ChannelPlan = P;
+    type EventSink = E;
+
+    impl_lorawan_device!();
+}
+
+pub(crate) struct Shared<
+    R: PhyRxTx + Timings,
+    RNG: RngCore,
+    const N: usize,
+    const D: usize,
+    E: EventSink,
+    P: ChannelPlan,
+> {
     pub(crate) radio: R,
     pub(crate) rng: RNG,
     pub(crate) tx_buffer: RadioBuffer<N>,
-    pub(crate) mac: Mac,
-    pub(crate) downlink: Vec<Downlink, D>,
+    pub(crate) mac: Mac<P>,
+    pub(crate) downlink: DownlinkQueue<D>,
+    pub(crate) events: E,
+    pub(crate) class_c: bool,
 }
 
 #[derive(Debug)]
@@ -139,6 +338,9 @@ pub enum Error<R: PhyRxTx> {
     Radio(R::PhyError),
     State(state::Error),
     Mac(mac::Error),
+    /// Listen-before-talk is required (eg: in KR920), but this device does not support it, so
+    /// nothing was sent.
+    ClearChannelUnsupported,
 }
 
 impl<R: PhyRxTx> From<mac::Error> for Error<R> {
@@ -37,12 +37,22 @@ O
 └──────────╫─╫───┘         ║   ║               ║                    ║
 else(Ready)║ ╚═════════════╝   ║               ║                    ║
            ╚═══════════════════╝               ╚════════════════════╝
+
+When joining with retries, a JoinRequest left unanswered in RxWindow2 leads to "JoinBackoff"
+(TimeoutReq) instead of Idle (NoJoinAccept), unless the retries are exhausted. The Timeout ending
+the backoff sends the next JoinRequest as if Join was handled by Idle.
+
+With Class C enabled on a joined device, the radio listens to RXC in Idle and in
+WaitingForRxWindow, where RadioEvents are frames received on RXC (DataDown) instead of errors.
  */
 use super::super::*;
 use super::{
-    mac::{Frame, Mac, Window},
-    radio, Event, RadioBuffer, Response, Timings,
+    mac::{Frame, Mac, Reception, Window},
+    radio, Event, RadioBuffer, Response, Shared, Timings,
 };
+use crate::downlink::DownlinkQueue;
+use crate::events::{EventSink, MacEvent};
+use crate::region::ChannelPlan;
 
 #[derive(Copy, Clone)]
 pub enum State {
@@ -50,6 +60,7 @@ pub enum State {
     SendingData(SendingData),
     WaitingForRxWindow(WaitingForRxWindow),
     WaitingForRx(WaitingForRx),
+    JoinBackoff(JoinBackoff),
 }
 
 macro_rules! into_state {
@@ -64,7 +75,7 @@ macro_rules! into_state {
     )*};
 }
 
-into_state!(Idle, SendingData, WaitingForRxWindow, WaitingForRx);
+into_state!(Idle, SendingData, WaitingForRxWindow, WaitingForRx, JoinBackoff);
 
 impl Default for State {
     fn default() -> Self {
@@ -72,6 +83,18 @@ impl Default for State {
     }
 }
 
+impl State {
+    /// Timestamp of the `TimeoutFired` event this state waits for, if any.
+    pub(crate) fn next_deadline(&self) -> Option<u32> {
+        match self {
+            State::Idle(_) | State::SendingData(_) => None,
+            State::WaitingForRxWindow(s) => Some(s.window.start()),
+            State::WaitingForRx(s) => Some(s.window_close),
+            State::JoinBackoff(s) => Some(s.next_attempt),
+        }
+    }
+}
+
 impl From<Rx> for Window {
     fn from(val: Rx) -> Window {
         match val {
@@ -81,6 +104,15 @@ impl From<Rx> for Window {
     }
 }
 
+impl From<Rx> for RxWindow {
+    fn from(val: Rx) -> RxWindow {
+        match val {
+            Rx::_1(_) => RxWindow::Rx1,
+            Rx::_2(_) => RxWindow::Rx2,
+        }
+    }
+}
+
 #[derive(Debug)]
 pub enum Error {
     RadioEventWhileIdle,
@@ -92,6 +124,8 @@ pub enum Error {
     SendDataWhileWaitingForRx,
     BufferTooSmall,
     UnexpectedRadioResponse,
+    RadioEventWhileJoinBackoff,
+    SendDataWhileJoinBackoff,
 }
 
 impl<R: radio::PhyRxTx> From<Error> for super::Error<R> {
@@ -107,20 +141,29 @@ impl State {
         RNG: RngCore,
         const N: usize,
         const D: usize,
+        E: EventSink,
+        P: ChannelPlan,
     >(
         self,
-        mac: &mut Mac,
-        radio: &mut R,
-        rng: &mut RNG,
-        buf: &mut RadioBuffer<N>,
-        dl: &mut Vec<Downlink, D>,
+        shared: &mut Shared<R, RNG, N, D, E, P>,
         event: Event<'_, R>,
     ) -> (Self, Result<Response, super::Error<R>>) {
+        let Shared { radio, rng, tx_buffer: buf, mac, downlink: dl, events, class_c } = shared;
+        let class_c = *class_c;
         match self {
-            State::Idle(s) => s.handle_event::<R, C, RNG, N>(mac, radio, rng, buf, event),
-            State::SendingData(s) => s.handle_event::<R, N>(mac, radio, event),
-            State::WaitingForRxWindow(s) => s.handle_event::<R, N>(mac, radio, event),
-            State::WaitingForRx(s) => s.handle_event::<R, C, N, D>(mac, radio, buf, event, dl),
+            State::Idle(s) => {
+                s.handle_event::<R, C, RNG, N, D>(mac, radio, rng, buf, dl, events, class_c, event)
+            }
+            State::SendingData(s) => s.handle_event::<R, N>(mac, radio, events, class_c, event),
+            State::WaitingForRxWindow(s) => {
+                s.handle_event::<R, C, N, D>(mac, radio, buf, dl, events, class_c, event)
+            }
+            State::WaitingForRx(s) => {
+                s.handle_event::<R, C, N, D>(mac, radio, buf, event, dl, events, class_c)
+            }
+            State::JoinBackoff(s) => {
+                s.handle_event::<R, C, RNG, N, D>(mac, radio, rng, buf, dl, events, class_c, event)
+            }
         }
     }
 }
@@ -129,17 +172,22 @@ impl State {
 pub struct Idle;
 
 impl Idle {
+    #[allow(clippy::too_many_arguments)]
     pub(crate) fn handle_event<
         R: radio::PhyRxTx + Timings,
         C: CryptoFactory + Default,
         RNG: RngCore,
         const N: usize,
+        const D: usize,
     >(
         self,
-        mac: &mut Mac,
+        mac: &mut Mac<impl ChannelPlan>,
         radio: &mut R,
         rng: &mut RNG,
         buf: &mut RadioBuffer<N>,
+        dl: &mut DownlinkQueue<D>,
+        events: &mut impl EventSink,
+        class_c: bool,
         event: Event<'_, R>,
     ) -> (State, Result<Response, super::Error<R>>) {
         enum IntermediateResponse<R: radio::PhyRxTx> {
@@ -149,11 +197,23 @@ impl Idle {
 
         let response = match event {
             // tolerate unexpected timeout
-            Event::Join(creds) => {
-                let (tx_config, dev_nonce) = mac.join_otaa::<C, RNG, N>(rng, creds, buf);
-                IntermediateResponse::RadioTx((Frame::Join, tx_config, dev_nonce as u32))
-            }
+            Event::Join(creds) => match mac.join_otaa::<C, RNG, N>(rng, creds, buf) {
+                Err(e) => IntermediateResponse::EarlyReturn(Err(e.into())),
+                Ok((tx_config, dev_nonce)) => {
+                    IntermediateResponse::RadioTx((Frame::Join, tx_config, dev_nonce as u32))
+                }
+            },
             Event::TimeoutFired => IntermediateResponse::EarlyReturn(Ok(Response::NoUpdate)),
+            Event::RadioEvent(radio_event) if rxc_enabled(mac, class_c) => {
+                IntermediateResponse::EarlyReturn(handle_rxc_event::<R, C, N, D>(
+                    mac,
+                    radio,
+                    buf,
+                    dl,
+                    events,
+                    radio_event,
+                ))
+            }
             Event::RadioEvent(_radio_event) => {
                 IntermediateResponse::EarlyReturn(Err(Error::RadioEventWhileIdle.into()))
             }
@@ -170,10 +230,31 @@ impl Idle {
         match response {
             IntermediateResponse::EarlyReturn(response) => (State::Idle(self), response),
             IntermediateResponse::RadioTx((frame, tx_config, fcnt_up)) => {
+                // the radio events provide no clear-channel assessment
+                if mac.get_clear_channel_config(tx_config.rf).is_some() {
+                    return (State::Idle(self), Err(super::Error::ClearChannelUnsupported));
+                }
                 let event: radio::Event<'_, R> =
                     radio::Event::TxRequest(tx_config, buf.as_ref_for_read());
                 match radio.handle_event(event) {
                     Ok(response) => {
+                        // from here on, the frame may be on air
+                        mac.uplink_sent();
+                        let time_on_air_us =
+                            mac::time_on_air_us(&tx_config, buf.as_ref_for_read().len());
+                        mac.emit(
+                            events,
+                            match frame {
+                                Frame::Join => MacEvent::JoinRequestSent {
+                                    dev_nonce: fcnt_up as u16,
+                                    tx_config,
+                                    time_on_air_us,
+                                },
+                                Frame::Data => {
+                                    MacEvent::UplinkSent { fcnt_up, tx_config, time_on_air_us }
+                                }
+                            },
+                        );
                         match response {
                             // intermediate state where we wait for Join to complete sending
                             // allows for asynchronous sending
@@ -183,9 +264,9 @@ impl Idle {
                             ),
                             // directly jump to waiting for RxWindow
                             // allows for synchronous sending
-                            radio::Response::TxDone(ms) => {
-                                data_rxwindow1_timeout::<R, N>(frame, mac, radio, ms)
-                            }
+                            radio::Response::TxDone(ms) => data_rxwindow1_timeout::<R, N>(
+                                frame, mac, radio, events, class_c, ms,
+                            ),
                             _ => (State::Idle(self), Err(Error::UnexpectedRadioResponse.into())),
                         }
                     }
@@ -204,8 +285,10 @@ pub struct SendingData {
 impl SendingData {
     pub(crate) fn handle_event<R: radio::PhyRxTx + Timings, const N: usize>(
         self,
-        mac: &mut Mac,
+        mac: &mut Mac<impl ChannelPlan>,
         radio: &mut R,
+        events: &mut impl EventSink,
+        class_c: bool,
         event: Event<'_, R>,
     ) -> (State, Result<Response, super::Error<R>>) {
         match event {
@@ -216,9 +299,9 @@ impl SendingData {
                     Ok(response) => {
                         match response {
                             // expect a complete transmit
-                            radio::Response::TxDone(ms) => {
-                                data_rxwindow1_timeout::<R, N>(self.frame, mac, radio, ms)
-                            }
+                            radio::Response::TxDone(ms) => data_rxwindow1_timeout::<R, N>(
+                                self.frame, mac, radio, events, class_c, ms,
+                            ),
                             // anything other than TxComplete is unexpected
                             _ => {
                                 panic!("SendingData: Unexpected radio response");
@@ -245,42 +328,71 @@ pub struct WaitingForRxWindow {
 }
 
 impl WaitingForRxWindow {
-    pub(crate) fn handle_event<R: radio::PhyRxTx + Timings, const N: usize>(
+    #[allow(clippy::too_many_arguments)]
+    pub(crate) fn handle_event<
+        R: radio::PhyRxTx + Timings,
+        C: CryptoFactory + Default,
+        const N: usize,
+        const D: usize,
+    >(
         self,
-        mac: &mut Mac,
+        mac: &mut Mac<impl ChannelPlan>,
         radio: &mut R,
+        buf: &mut RadioBuffer<N>,
+        dl: &mut DownlinkQueue<D>,
+        events: &mut impl EventSink,
+        class_c: bool,
         event: Event<'_, R>,
     ) -> (State, Result<Response, super::Error<R>>) {
         match event {
             // we are waiting for a Timeout
             Event::TimeoutFired => {
+                if rxc_enabled(mac, class_c) {
+                    mac.emit(events, MacEvent::RxWindowClosed { window: RxWindow::Rxc });
+                }
+                // windows are timed by `Timings` rather than from the timing error, as the
+                // radio is not given a symbol timeout
                 let (rx_config, window_start) =
                     mac.get_rx_parameters_legacy(&self.frame, &self.window.into());
                 // configure the radio for the RX
                 match radio.handle_event(radio::Event::RxRequest(rx_config)) {
                     Ok(_) => {
+                        mac.emit(
+                            events,
+                            MacEvent::RxWindowOpened { window: self.window.into(), rf: rx_config },
+                        );
+                        // the deadline may be past the wrap-around of the timestamps
                         let window_close: u32 = match self.window {
                             // RxWindow1 one must timeout before RxWindow2
                             Rx::_1(time) => {
                                 let time_between_windows =
                                     mac.get_rx_delay(&self.frame, &Window::_2) - window_start;
                                 if time_between_windows > radio.get_rx_window_duration_ms() {
-                                    time + radio.get_rx_window_duration_ms()
+                                    time.wrapping_add(radio.get_rx_window_duration_ms())
                                 } else {
-                                    time + time_between_windows
+                                    time.wrapping_add(time_between_windows)
                                 }
                             }
                             // RxWindow2 can last however long
-                            Rx::_2(time) => time + radio.get_rx_window_duration_ms(),
+                            Rx::_2(time) => time.wrapping_add(radio.get_rx_window_duration_ms()),
                         };
                         (
-                            State::WaitingForRx(self.into()),
+                            State::WaitingForRx(WaitingForRx {
+                                frame: self.frame,
+                                window: self.window,
+                                window_close,
+                            }),
                             Ok(Response::TimeoutRequest(window_close)),
                         )
                     }
                     Err(e) => (State::WaitingForRxWindow(self), Err(super::Error::Radio(e))),
                 }
             }
+            // Class C downlinks may be received before the RX windows of the uplink
+            Event::RadioEvent(radio_event) if rxc_enabled(mac, class_c) => (
+                State::WaitingForRxWindow(self),
+                handle_rxc_event::<R, C, N, D>(mac, radio, buf, dl, events, radio_event),
+            ),
             Event::RadioEvent(_) => (
                 State::WaitingForRxWindow(self),
                 Err(Error::RadioEventWhileWaitingForRxWindow.into()),
@@ -297,19 +409,15 @@ impl WaitingForRxWindow {
     }
 }
 
-impl From<WaitingForRxWindow> for WaitingForRx {
-    fn from(val: WaitingForRxWindow) -> WaitingForRx {
-        WaitingForRx { frame: val.frame, window: val.window }
-    }
-}
-
 #[derive(Copy, Clone)]
 pub struct WaitingForRx {
     frame: Frame,
     window: Rx,
+    window_close: u32,
 }
 
 impl WaitingForRx {
+    #[allow(clippy::too_many_arguments)]
     pub(crate) fn handle_event<
         R: radio::PhyRxTx + Timings,
         C: CryptoFactory + Default,
@@ -317,11 +425,13 @@ impl WaitingForRx {
         const D: usize,
     >(
         self,
-        mac: &mut Mac,
+        mac: &mut Mac<impl ChannelPlan>,
         radio: &mut R,
         buf: &mut RadioBuffer<N>,
         event: Event<'_, R>,
-        dl: &mut Vec<Downlink, D>,
+        dl: &mut DownlinkQueue<D>,
+        events: &mut impl EventSink,
+        class_c: bool,
     ) -> (State, Result<Response, super::Error<R>>) {
         match event {
             // we are waiting for the async tx to complete
@@ -329,7 +439,7 @@ impl WaitingForRx {
                 // send the transmit request to the radio
                 match radio.handle_event(radio_event) {
                     Ok(response) => match response {
-                        radio::Response::RxDone(_quality) => {
+                        radio::Response::RxDone(quality) => {
                             // copy from radio buffer to mac buffer
                             buf.clear();
                             if let Err(()) =
@@ -340,13 +450,23 @@ impl WaitingForRx {
                                     Err(Error::BufferTooSmall.into()),
                                 );
                             }
-                            match mac.handle_rx::<C, N, D>(buf, dl) {
+                            let window = self.window.into();
+                            match mac.handle_rx::<C, N, D>(
+                                buf,
+                                dl,
+                                Reception { window, quality },
+                                events,
+                            ) {
                                 // NoUpdate can occur when a stray radio packet is received. Maintain state
                                 mac::Response::NoUpdate => {
                                     (State::WaitingForRx(self), Ok(Response::NoUpdate))
                                 }
                                 // Any other type of update indicates we are done receiving. Change to Idle
-                                r => (State::Idle(Idle), Ok(r.into())),
+                                r => {
+                                    mac.emit(events, MacEvent::RxWindowClosed { window });
+                                    let result = start_rxc(mac, radio, events, class_c);
+                                    (State::Idle(Idle), result.map(|()| r.into()))
+                                }
                             }
                         }
                         _ => (State::WaitingForRx(self), Ok(Response::NoUpdate)),
@@ -358,12 +478,16 @@ impl WaitingForRx {
                 if let Err(e) = radio.handle_event(radio::Event::CancelRx) {
                     return (State::WaitingForRx(self), Err(super::Error::Radio(e)));
                 }
+                mac.emit(events, MacEvent::RxWindowClosed { window: self.window.into() });
 
                 match self.window {
                     Rx::_1(t1) => {
                         let time_between_windows = mac.get_rx_delay(&self.frame, &Window::_2)
                             - mac.get_rx_delay(&self.frame, &Window::_1);
-                        let t2 = t1 + time_between_windows;
+                        let t2 = t1.wrapping_add(time_between_windows);
+                        if let Err(e) = start_rxc(mac, radio, events, class_c) {
+                            return (State::WaitingForRx(self), Err(e));
+                        }
                         // TODO: jump to RxWindow2 if t2 == now
                         (
                             State::WaitingForRxWindow(WaitingForRxWindow {
@@ -374,9 +498,22 @@ impl WaitingForRx {
                         )
                     }
                     // Timeout during second RxWindow leads to giving up
-                    Rx::_2(_) => {
+                    Rx::_2(t2) => {
                         let response = mac.rx2_complete();
-                        (State::Idle(Idle), Ok(response.into()))
+                        if let mac::Response::NoJoinAccept = response {
+                            if let Some(delay) = mac.join_backoff_ms() {
+                                // timestamps wrap around, as for `time_until_deadline`
+                                let next_attempt = t2
+                                    .wrapping_add(radio.get_rx_window_duration_ms())
+                                    .wrapping_add(delay);
+                                return (
+                                    State::JoinBackoff(JoinBackoff { next_attempt }),
+                                    Ok(Response::TimeoutRequest(next_attempt)),
+                                );
+                            }
+                        }
+                        let result = start_rxc(mac, radio, events, class_c);
+                        (State::Idle(Idle), result.map(|()| response.into()))
                     }
                 }
             }
@@ -390,22 +527,140 @@ impl WaitingForRx {
     }
 }
 
+#[derive(Copy, Clone)]
+pub struct JoinBackoff {
+    next_attempt: u32,
+}
+
+impl JoinBackoff {
+    #[allow(clippy::too_many_arguments)]
+    pub(crate) fn handle_event<
+        R: radio::PhyRxTx + Timings,
+        C: CryptoFactory + Default,
+        RNG: RngCore,
+        const N: usize,
+        const D: usize,
+    >(
+        self,
+        mac: &mut Mac<impl ChannelPlan>,
+        radio: &mut R,
+        rng: &mut RNG,
+        buf: &mut RadioBuffer<N>,
+        dl: &mut DownlinkQueue<D>,
+        events: &mut impl EventSink,
+        class_c: bool,
+        event: Event<'_, R>,
+    ) -> (State, Result<Response, super::Error<R>>) {
+        match event {
+            Event::TimeoutFired => match mac.next_join_attempt(rng) {
+                Some(creds) => Idle.handle_event::<R, C, RNG, N, D>(
+                    mac,
+                    radio,
+                    rng,
+                    buf,
+                    dl,
+                    events,
+                    class_c,
+                    Event::Join(creds),
+                ),
+                None => (State::Idle(Idle), Ok(Response::NoJoinAccept)),
+            },
+            // a new join request supersedes the retries
+            Event::Join(_) => {
+                mac.stop_join_retries();
+                Idle.handle_event::<R, C, RNG, N, D>(
+                    mac, radio, rng, buf, dl, events, class_c, event,
+                )
+            }
+            Event::RadioEvent(_) => {
+                (State::JoinBackoff(self), Err(Error::RadioEventWhileJoinBackoff.into()))
+            }
+            Event::SendDataRequest(_) => {
+                (State::JoinBackoff(self), Err(Error::SendDataWhileJoinBackoff.into()))
+            }
+        }
+    }
+}
+
 #[derive(Copy, Clone, Debug)]
 enum Rx {
     _1(u32),
     _2(u32),
 }
 
+impl Rx {
+    fn start(&self) -> u32 {
+        match self {
+            Rx::_1(time) | Rx::_2(time) => *time,
+        }
+    }
+}
+
 fn data_rxwindow1_timeout<R: radio::PhyRxTx + Timings, const N: usize>(
     frame: Frame,
-    mac: &mut Mac,
+    mac: &mut Mac<impl ChannelPlan>,
     radio: &mut R,
+    events: &mut impl EventSink,
+    class_c: bool,
     timestamp_ms: u32,
 ) -> (State, Result<Response, super::Error<R>>) {
     let delay = mac.get_rx_delay(&frame, &Window::_1);
-    let t1 = (delay as i32 + timestamp_ms as i32 + radio.get_rx_window_offset_ms()) as u32;
+    let t1 = timestamp_ms.wrapping_add(delay).wrapping_add_signed(radio.get_rx_window_offset_ms());
+    let result = start_rxc(mac, radio, events, class_c);
     (
         State::WaitingForRxWindow(WaitingForRxWindow { frame, window: Rx::_1(t1) }),
-        Ok(Response::TimeoutRequest(t1)),
+        result.map(|()| Response::TimeoutRequest(t1)),
     )
 }
+
+/// Whether the radio listens to RXC when not transmitting nor in RX1 or RX2.
+fn rxc_enabled(mac: &Mac<impl ChannelPlan>, class_c: bool) -> bool {
+    class_c && mac.is_joined()
+}
+
+/// Configures the radio to listen to RXC, if enabled.
+#[cfg_attr(not(feature = "class-c"), allow(unused_variables))]
+fn start_rxc<R: radio::PhyRxTx>(
+    mac: &mut Mac<impl ChannelPlan>,
+    radio: &mut R,
+    events: &mut impl EventSink,
+    class_c: bool,
+) -> Result<(), super::Error<R>> {
+    #[cfg(feature = "class-c")]
+    if rxc_enabled(mac, class_c) {
+        let rf = mac.get_rxc_config().rf;
+        radio.handle_event(radio::Event::RxRequest(rf)).map_err(super::Error::Radio)?;
+        mac.emit(events, MacEvent::RxWindowOpened { window: RxWindow::Rxc, rf });
+    }
+    Ok(())
+}
+
+/// Handles a radio event while listening to RXC, which goes on after a frame is received.
+fn handle_rxc_event<
+    R: radio::PhyRxTx + Timings,
+    C: CryptoFactory + Default,
+    const N: usize,
+    const D: usize,
+>(
+    mac: &mut Mac<impl ChannelPlan>,
+    radio: &mut R,
+    buf: &mut RadioBuffer<N>,
+    dl: &mut DownlinkQueue<D>,
+    events: &mut impl EventSink,
+    radio_event: radio::Event<'_, R>,
+) -> Result<Response, super::Error<R>> {
+    let radio::Response::RxDone(quality) =
+        radio.handle_event(radio_event).map_err(super::Error::Radio)?
+    else {
+        return Ok(Response::NoUpdate);
+    };
+    buf.clear();
+    if let Err(()) = buf.extend_from_slice(radio.get_received_packet().as_ref()) {
+        return Err(Error::BufferTooSmall.into());
+    }
+    let response = mac.handle_rxc::<C, N, D>(buf, dl, quality, events);
+    buf.clear();
+    mac.emit(events, MacEvent::RxWindowClosed { window: RxWindow::Rxc });
+    start_rxc(mac, radio, events, true)?;
+    Ok(response?.into())
+}
@@ -4,6 +4,7 @@ use crate::test_util::*;
 use util::*;
 
 use crate::nb_device::Event;
+use crate::region::{Bandwidth, SpreadingFactor};
 #[test]
 fn test_join_rx1() {
     let mut device = test_device();
@@ -12,7 +13,7 @@ fn test_join_rx1() {
     // send a timeout for beginning of window
     let response = device.handle_event(Event::TimeoutFired).unwrap();
     assert!(matches!(response, Response::TimeoutRequest(5100)));
-    device.get_radio().set_rxtx_handler(handle_join_request::<1>);
+    device.get_mut_radio().set_rxtx_handler(handle_join_request::<1>);
     // send a radio event to let the radio device indicate a packet was received
     let response = device.handle_event(Event::RadioEvent(radio::Event::Phy(()))).unwrap();
     assert!(matches!(response, Response::JoinSuccess));
@@ -22,7 +23,7 @@ fn test_join_rx1() {
 #[test]
 fn test_join_rx2() {
     let mut device = test_device();
-    device.get_radio().set_rxtx_handler(handle_join_request::<2>);
+    device.get_mut_radio().set_rxtx_handler(handle_join_request::<2>);
     let response = device.join(get_otaa_credentials()).unwrap();
     assert!(matches!(response, Response::TimeoutRequest(5000)));
     let response = device.handle_event(Event::TimeoutFired).unwrap();
@@ -39,6 +40,122 @@ fn test_join_rx2() {
     assert!(device.get_session_keys().is_some());
 }
 
+#[test]
+fn test_join_with_retries() {
+    let mut device = test_device_with_seed(0);
+    let mut datarates = heapless::Vec::new();
+    datarates.extend_from_slice(&[region::DR::_3, region::DR::_4]).unwrap();
+    let config = mac::JoinRetryConfig { max_attempts: 2, datarates, max_jitter_ms: 1000 };
+    let response = device.join_with_retries(get_otaa_credentials(), config).unwrap();
+    assert!(matches!(response, Response::TimeoutRequest(5000)));
+    device.handle_event(Event::TimeoutFired).unwrap();
+    device.handle_event(Event::TimeoutFired).unwrap();
+    device.handle_event(Event::TimeoutFired).unwrap();
+    // end of rx2 schedules the next attempt instead of giving up: RX2 closes at 6100, the
+    // JoinRequest of 61.696 ms at DR3 is followed by 108 ms off to keep a 1% duty cycle, and the
+    // seeded RNG adds 702 ms of jitter
+    let response = device.handle_event(Event::TimeoutFired).unwrap();
+    assert!(matches!(response, Response::TimeoutRequest(6910)));
+    assert_eq!(device.next_deadline(), Some(6910));
+    assert!(!device.ready_to_send_data());
+    let bb = device.get_mut_radio().take_uplink().unwrap().get_tx_config().rf.bb;
+    assert_eq!((bb.sf, bb.bw), (SpreadingFactor::_7, Bandwidth::_125KHz));
+    // second attempt, at the next data rate
+    let response = device.handle_event(Event::TimeoutFired).unwrap();
+    assert!(matches!(response, Response::TimeoutRequest(5000)));
+    let bb = device.get_radio().last_uplink().unwrap().get_tx_config().rf.bb;
+    assert_eq!((bb.sf, bb.bw), (SpreadingFactor::_8, Bandwidth::_500KHz));
+    let response = device.handle_event(Event::TimeoutFired).unwrap();
+    assert!(matches!(response, Response::TimeoutRequest(5100)));
+    device.get_mut_radio().set_rxtx_handler(handle_join_request::<1>);
+    let response = device.handle_event(Event::RadioEvent(radio::Event::Phy(()))).unwrap();
+    assert!(matches!(response, Response::JoinSuccess));
+    assert_eq!(device.get_statistics().join_attempts(), 2);
+}
+
+#[test]
+fn test_join_backoff_across_timestamp_wrap() {
+    let mut device = test_device_with_seed(0);
+    let mut datarates = heapless::Vec::new();
+    datarates.push(region::DR::_3).unwrap();
+    let config = mac::JoinRetryConfig { max_attempts: 2, datarates, max_jitter_ms: 0 };
+    device.get_mut_radio().set_tx_timestamp(u32::MAX - 6109);
+    device.join_with_retries(get_otaa_credentials(), config).unwrap();
+    for _ in 0..3 {
+        device.handle_event(Event::TimeoutFired).unwrap();
+    }
+    // RX2 closes at u32::MAX - 9, followed by 108 ms off
+    let response = device.handle_event(Event::TimeoutFired).unwrap();
+    assert!(matches!(response, Response::TimeoutRequest(98)));
+    assert_eq!(device.time_until_deadline(u32::MAX - 9), Some(108));
+}
+
+#[cfg(feature = "region-kr920")]
+#[test]
+fn test_listen_before_talk_unsupported() {
+    let mut device: Device<TestRadio, lorawan::default_crypto::DefaultFactory, _, 255> =
+        Device::new_with_seed(
+            region::Configuration::new(region::Region::KR920),
+            TestRadio::default(),
+            0,
+        );
+    let response = device.join(get_otaa_credentials());
+    assert!(matches!(response, Err(crate::nb_device::Error::ClearChannelUnsupported)));
+    // nothing was sent
+    assert!(device.get_mut_radio().take_uplink().is_none());
+    assert_eq!(device.next_deadline(), None);
+}
+
+#[test]
+fn test_rx_windows_across_timestamp_wrap() {
+    let mut device = test_device();
+    device.join(get_abp_credentials()).unwrap();
+    // timestamps wrapping around within the RX windows, or past i32::MAX
+    for tx_done in [u32::MAX - 1050, i32::MAX as u32 - 500] {
+        device.get_mut_radio().set_tx_timestamp(tx_done);
+        device.send(&[0; 1], 1, false).unwrap();
+        for deadline in [1000, 1100, 2000, 2100] {
+            assert_eq!(device.next_deadline(), Some(tx_done.wrapping_add(deadline)));
+            device.handle_event(Event::TimeoutFired).unwrap();
+        }
+        assert!(device.ready_to_send_data());
+        device.get_mut_radio().take_uplink();
+    }
+}
+
+#[test]
+fn test_restore_session() {
+    let mut device = test_device();
+    restore_session(&mut device);
+    // the restored frame counter is used by the next uplink
+    device.send(&[0; 1], 1, false).unwrap();
+    assert!(!device.ready_to_send_data());
+    for _ in 0..4 {
+        device.handle_event(Event::TimeoutFired).unwrap();
+    }
+    assert!(device.ready_to_send_data());
+    assert_eq!(device.get_fcnt_up(), Some(8));
+}
+
+#[test]
+fn test_next_deadline() {
+    let mut device = test_device();
+    device.join(get_abp_credentials()).unwrap();
+    assert_eq!(device.next_deadline(), None);
+    device.send(&[0; 1], 1, false).unwrap();
+    // start and end of RX1 and RX2, as requested
+    for deadline in [1000, 1100, 2000, 2100] {
+        assert_eq!(device.next_deadline(), Some(deadline));
+        assert_eq!(device.time_until_deadline(900), Some(deadline - 900));
+        // across a wrap-around of the timestamps
+        assert_eq!(device.time_until_deadline(u32::MAX - 99), Some(deadline + 100));
+        assert_eq!(device.time_until_deadline(deadline + 1), Some(0));
+        device.handle_event(Event::TimeoutFired).unwrap();
+    }
+    assert_eq!(device.next_deadline(), None);
+    assert_eq!(device.time_until_deadline(2200), None);
+}
+
 #[test]
 fn test_unconfirmed_uplink_no_downlink() {
     let mut device = test_device();
@@ -80,7 +197,7 @@ fn test_confirmed_uplink_with_ack_rx1() {
     assert!(matches!(response, Response::TimeoutRequest(1000)));
     let response = device.handle_event(Event::TimeoutFired).unwrap(); // begin Rx1
     assert!(matches!(response, Response::TimeoutRequest(1100)));
-    device.get_radio().set_rxtx_handler(handle_data_uplink_with_link_adr_req::<0, 0>);
+    device.get_mut_radio().set_rxtx_handler(handle_data_uplink_with_link_adr_req::<0, 0>);
     // send a radio event to let the radio device indicate a packet was received
     let response = device.handle_event(Event::RadioEvent(radio::Event::Phy(()))).unwrap();
     assert!(matches!(response, Response::DownlinkReceived(0)));
@@ -99,7 +216,7 @@ fn test_confirmed_uplink_with_ack_rx2() {
     assert!(matches!(response, Response::TimeoutRequest(2000)));
     let response = device.handle_event(Event::TimeoutFired).unwrap(); // being Rx2
     assert!(matches!(response, Response::TimeoutRequest(2100)));
-    device.get_radio().set_rxtx_handler(handle_data_uplink_with_link_adr_req::<0, 0>);
+    device.get_mut_radio().set_rxtx_handler(handle_data_uplink_with_link_adr_req::<0, 0>);
     // send a radio event to let the radio device indicate a packet was received
     let response = device.handle_event(Event::RadioEvent(radio::Event::Phy(()))).unwrap();
     assert!(matches!(response, Response::DownlinkReceived(0)));
@@ -114,7 +231,7 @@ fn test_link_adr_ans() {
     assert!(matches!(response, Response::TimeoutRequest(1000)));
     let response = device.handle_event(Event::TimeoutFired).unwrap(); // begin Rx1
     assert!(matches!(response, Response::TimeoutRequest(1100)));
-    device.get_radio().set_rxtx_handler(handle_data_uplink_with_link_adr_req::<0, 0>);
+    device.get_mut_radio().set_rxtx_handler(handle_data_uplink_with_link_adr_req::<0, 0>);
     // send a radio event to let the radio device indicate a packet was received
     let response = device.handle_event(Event::RadioEvent(radio::Event::Phy(()))).unwrap();
     assert!(matches!(response, Response::DownlinkReceived(0)));
@@ -123,8 +240,66 @@ fn test_link_adr_ans() {
     assert!(matches!(response, Response::TimeoutRequest(1000)));
     let response = device.handle_event(Event::TimeoutFired).unwrap(); // begin Rx1
     assert!(matches!(response, Response::TimeoutRequest(1100)));
-    device.get_radio().set_rxtx_handler(handle_data_uplink_with_link_adr_ans);
+    device.get_mut_radio().set_rxtx_handler(handle_data_uplink_with_link_adr_ans);
     // send a radio event to let the radio device indicate a packet was received
     let response = device.handle_event(Event::RadioEvent(radio::Event::Phy(()))).unwrap();
     assert!(matches!(response, Response::DownlinkReceived(1)));
 }
+
+#[test]
+fn test_link_adr_ans_kept_when_tx_fails() {
+    let mut device = test_device();
+    device.join(get_abp_credentials()).unwrap();
+    device.send(&[0; 1], 1, true).unwrap();
+    device.handle_event(Event::TimeoutFired).unwrap(); // begin Rx1
+    device.get_mut_radio().set_rxtx_handler(handle_data_uplink_with_link_adr_req::<0, 0>);
+    let response = device.handle_event(Event::RadioEvent(radio::Event::Phy(()))).unwrap();
+    assert!(matches!(response, Response::DownlinkReceived(0)));
+    // the radio fails to transmit the uplink carrying the LinkAdrAns
+    device.get_mut_radio().fail_next_tx();
+    assert!(matches!(device.send(&[0; 1], 1, true), Err(crate::nb_device::Error::Radio(_))));
+    // so the next one carries it
+    let response = device.send(&[0; 1], 1, true).unwrap();
+    assert!(matches!(response, Response::TimeoutRequest(1000)));
+    device.handle_event(Event::TimeoutFired).unwrap(); // begin Rx1
+    device.get_mut_radio().set_rxtx_handler(handle_data_uplink_with_link_adr_ans);
+    let response = device.handle_event(Event::RadioEvent(radio::Event::Phy(()))).unwrap();
+    assert!(matches!(response, Response::DownlinkReceived(1)));
+}
+
+#[test]
+#[cfg(feature = "class-c")]
+fn test_class_c() {
+    const RXC_FREQUENCY: u32 = 923_300_000;
+    let mut device = test_device();
+    device.join(get_abp_credentials()).unwrap();
+    device.enable_class_c();
+    let response = device.send(&[0; 1], 1, false).unwrap();
+    assert!(matches!(response, Response::TimeoutRequest(1000)));
+    // RXC is open until RX1
+    assert_eq!(device.get_radio().current_config().unwrap().frequency, RXC_FREQUENCY);
+    device.get_mut_radio().set_rxtx_handler(class_c_downlink::<1>);
+    let response = device.handle_event(Event::RadioEvent(radio::Event::Phy(()))).unwrap();
+    assert!(matches!(response, Response::DownlinkReceived(1)));
+    let downlink = device.take_downlink().unwrap();
+    assert_eq!((downlink.window, downlink.fcnt_down), (RxWindow::Rxc, 1));
+    let response = device.handle_event(Event::TimeoutFired).unwrap(); // begin Rx1
+    assert!(matches!(response, Response::TimeoutRequest(1100)));
+    let response = device.handle_event(Event::TimeoutFired).unwrap(); // end Rx1
+    assert!(matches!(response, Response::TimeoutRequest(2000)));
+    // and between RX1 and RX2
+    assert_eq!(device.get_radio().current_config().unwrap().frequency, RXC_FREQUENCY);
+    let response = device.handle_event(Event::TimeoutFired).unwrap(); // begin Rx2
+    assert!(matches!(response, Response::TimeoutRequest(2100)));
+    let response = device.handle_event(Event::TimeoutFired).unwrap(); // end Rx2
+    assert!(matches!(response, Response::RxComplete));
+    assert!(device.ready_to_send_data());
+
+    // and after RX2
+    device.get_mut_radio().set_rxtx_handler(class_c_downlink::<2>);
+    let response = device.handle_event(Event::RadioEvent(radio::Event::Phy(()))).unwrap();
+    assert!(matches!(response, Response::DownlinkReceived(2)));
+    let downlink = device.take_downlink().unwrap();
+    assert_eq!((downlink.window, downlink.fcnt_down), (RxWindow::Rxc, 2));
+    assert!(device.take_downlink().is_none());
+}
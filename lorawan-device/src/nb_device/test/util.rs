@@ -13,6 +13,13 @@ pub fn test_device() -> Device<TestRadio, default_crypto::DefaultFactory, rand_c
     Device::new(Configuration::new(Region::US915), TestRadio::default(), rand::rngs::OsRng)
 }
 
+/// A device whose random choices (eg: join channels and jitter) are reproducible.
+pub fn test_device_with_seed(
+    seed: u64,
+) -> Device<TestRadio, default_crypto::DefaultFactory, crate::Prng, 255> {
+    Device::new(Configuration::new(Region::US915), TestRadio::default(), crate::Prng::new(seed))
+}
+
 #[derive(Debug)]
 pub struct TestRadio {
     current_config: Option<RfConfig>,
@@ -20,12 +27,40 @@ pub struct TestRadio {
     rxtx_handler: Option<RxTxHandler>,
     buffer: [u8; 256],
     buffer_index: usize,
+    tx_timestamp: u32,
+    fail_next_tx: bool,
 }
 
 impl TestRadio {
     pub fn set_rxtx_handler(&mut self, handler: RxTxHandler) {
         self.rxtx_handler = Some(handler);
     }
+
+    /// Discards the last uplink, as if it was never received.
+    pub fn take_uplink(&mut self) -> Option<Uplink> {
+        self.last_uplink.take()
+    }
+
+    /// Rejects the next TX request, as a radio failing to transmit.
+    pub fn fail_next_tx(&mut self) {
+        self.fail_next_tx = true;
+    }
+
+    /// The last uplink, left for the RX handler.
+    pub fn last_uplink(&self) -> Option<&Uplink> {
+        self.last_uplink.as_ref()
+    }
+
+    /// Timestamp of the end of the next transmissions.
+    pub fn set_tx_timestamp(&mut self, timestamp: u32) {
+        self.tx_timestamp = timestamp;
+    }
+
+    /// Channel of the last RX request.
+    #[allow(unused)]
+    pub fn current_config(&self) -> Option<RfConfig> {
+        self.current_config
+    }
 }
 
 impl Default for TestRadio {
@@ -36,6 +71,8 @@ impl Default for TestRadio {
             rxtx_handler: None,
             buffer: [0; 256],
             buffer_index: 0,
+            tx_timestamp: 0,
+            fail_next_tx: false,
         }
     }
 }
@@ -61,6 +98,10 @@ impl PhyRxTx for TestRadio {
         Self: Sized,
     {
         match event {
+            Event::TxRequest(_, _) if self.fail_next_tx => {
+                self.fail_next_tx = false;
+                return Err("tx failed");
+            }
             Event::TxRequest(config, buf) => {
                 // ensure that we have always consumed the previous uplink
                 if self.last_uplink.is_some() {
@@ -68,7 +109,7 @@ impl PhyRxTx for TestRadio {
                 }
                 self.last_uplink =
                     Some(Uplink::new(buf, config).map_err(|_| "error creating uplink")?);
-                return Ok(Response::TxDone(0));
+                return Ok(Response::TxDone(self.tx_timestamp));
             }
             Event::RxRequest(rf_config) => {
                 self.current_config = Some(rf_config);
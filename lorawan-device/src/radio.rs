@@ -11,10 +11,10 @@ pub struct RfConfig {
 #[derive(Debug, Clone, Copy, PartialEq)]
 pub enum RxMode {
     Continuous,
-    /// Single shot receive. Argument `ms` indicates how many milliseconds of extra buffer time should
-    /// be added to the preamble detection timeout.
+    /// Single shot receive, which times out unless a preamble is detected within `symbols`
+    /// symbols.
     Single {
-        ms: u32,
+        symbols: u16,
     },
 }
 
@@ -33,12 +33,37 @@ pub struct TxConfig {
 }
 
 impl TxConfig {
+    /// Turns the EIRP selected by the channel plan into the output power of the radio, which is
+    /// limited to `max_power`.
     pub fn adjust_power(&mut self, max_power: u8, antenna_gain: i8) {
         self.pw -= antenna_gain;
         self.pw = core::cmp::min(self.pw, max_power as i8);
     }
 }
 
+/// Listen-before-talk parameters of a clear-channel assessment, as required by some regions
+/// before every transmission.
+#[cfg_attr(feature = "defmt-03", derive(defmt::Format))]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ClearChannelConfig {
+    /// Channel on which the transmission is about to happen.
+    pub rf: RfConfig,
+    /// The channel is busy if a signal stronger than this (in dBm) is sensed.
+    pub threshold_dbm: i16,
+    /// How long to sense the channel for, in microseconds.
+    pub sensing_time_us: u32,
+}
+
+/// Outcome of a clear-channel assessment.
+#[cfg_attr(feature = "defmt-03", derive(defmt::Format))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChannelAssessment {
+    Clear,
+    Busy,
+    /// The radio is not able to assess the channel.
+    Unsupported,
+}
+
 #[cfg_attr(feature = "defmt-03", derive(defmt::Format))]
 #[derive(Debug, Clone, Copy, PartialEq)]
 pub struct RxQuality {
@@ -0,0 +1,143 @@
+use rand_core::RngCore;
+
+/// How a [`ChannelSelector`] spreads uplinks over the enabled channels.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt-03", derive(defmt::Format))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum ChannelSelection {
+    /// A random channel, other than the previous one whenever there is a choice.
+    #[default]
+    Random,
+    /// Every enabled channel in turn, in increasing order.
+    RoundRobin,
+}
+
+/// No channel is allowed for the uplink: the channel mask disables every channel, or none of them
+/// supports the data rate.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt-03", derive(defmt::Format))]
+pub struct NoChannelAvailable;
+
+/// Selects the channel of each data uplink in a list of enabled channels.
+///
+/// The channel of the previous uplink is not selected again while another one is enabled. If
+/// created with `avoid_busy`, channels marked as busy (eg: listen-before-talk found them busy, or
+/// a confirmed uplink on them was not acknowledged) are only selected when every enabled channel
+/// is busy.
+#[derive(Debug, Default, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ChannelSelector {
+    selection: ChannelSelection,
+    avoid_busy: bool,
+    last: Option<u8>,
+    busy: [u8; 9],
+}
+
+impl ChannelSelector {
+    pub fn new(selection: ChannelSelection, avoid_busy: bool) -> Self {
+        Self { selection, avoid_busy, last: None, busy: [0; 9] }
+    }
+
+    /// Selects one of the `enabled` channels, which are indices in the channel plan sorted in
+    /// increasing order.
+    pub fn select<RNG: RngCore>(
+        &mut self,
+        rng: &mut RNG,
+        enabled: &[u8],
+    ) -> Result<u8, NoChannelAvailable> {
+        let avoid_busy = self.avoid_busy && enabled.iter().any(|channel| !self.is_busy(*channel));
+        let usable = |channel: u8| !(avoid_busy && self.is_busy(channel));
+        let usable_count = enabled.iter().filter(|channel| usable(**channel)).count();
+        if usable_count == 0 {
+            return Err(NoChannelAvailable);
+        }
+        let last = self.last.filter(|_| usable_count > 1);
+        let candidates = || enabled.iter().copied().filter(|c| usable(*c) && Some(*c) != last);
+        let channel = match self.selection {
+            ChannelSelection::Random => {
+                let n = rng.next_u32() as usize % candidates().count();
+                candidates().nth(n)
+            }
+            ChannelSelection::RoundRobin => candidates()
+                .find(|channel| self.last.map_or(true, |last| *channel > last))
+                .or_else(|| candidates().next()),
+        }
+        .ok_or(NoChannelAvailable)?;
+        self.last = Some(channel);
+        Ok(channel)
+    }
+
+    /// Marks `channel` as busy, or as clear again.
+    pub fn set_busy(&mut self, channel: u8, busy: bool) {
+        let Some(bank) = self.busy.get_mut(channel as usize / 8) else {
+            return;
+        };
+        let bit = 1 << (channel % 8);
+        if busy {
+            *bank |= bit;
+        } else {
+            *bank &= !bit;
+        }
+    }
+
+    pub fn is_busy(&self, channel: u8) -> bool {
+        self.busy.get(channel as usize / 8).is_some_and(|bank| bank & (1 << (channel % 8)) != 0)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_no_channel() {
+        let mut selector = ChannelSelector::default();
+        assert_eq!(selector.select(&mut crate::Prng::new(0), &[]), Err(NoChannelAvailable));
+    }
+
+    #[test]
+    fn test_random_without_repeats() {
+        let mut selector = ChannelSelector::default();
+        let mut rng = crate::Prng::new(0);
+        let mut last = None;
+        for _ in 0..100 {
+            let channel = selector.select(&mut rng, &[1, 4, 9]).unwrap();
+            assert!([1, 4, 9].contains(&channel));
+            assert_ne!(Some(channel), last);
+            last = Some(channel);
+        }
+        // a single channel is repeated
+        assert_eq!(selector.select(&mut rng, &[9]), Ok(9));
+        assert_eq!(selector.select(&mut rng, &[9]), Ok(9));
+    }
+
+    #[test]
+    fn test_round_robin() {
+        let mut selector = ChannelSelector::new(ChannelSelection::RoundRobin, false);
+        let mut rng = crate::Prng::new(0);
+        let channels: [u8; 7] =
+            core::array::from_fn(|_| selector.select(&mut rng, &[2, 5, 64]).unwrap());
+        assert_eq!(channels, [2, 5, 64, 2, 5, 64, 2]);
+        // the previous channel was disabled: carry on with the next one
+        assert_eq!(selector.select(&mut rng, &[0, 5]), Ok(5));
+    }
+
+    #[test]
+    fn test_avoid_busy() {
+        let mut selector = ChannelSelector::new(ChannelSelection::RoundRobin, true);
+        let mut rng = crate::Prng::new(0);
+        selector.set_busy(5, true);
+        for _ in 0..4 {
+            assert_ne!(selector.select(&mut rng, &[2, 5, 7]), Ok(5));
+        }
+        // busy channels are still used when nothing else is enabled
+        assert_eq!(selector.select(&mut rng, &[5]), Ok(5));
+        selector.set_busy(5, false);
+        assert!(!selector.is_busy(5));
+
+        // busy channels are not avoided unless requested
+        let mut selector = ChannelSelector::new(ChannelSelection::RoundRobin, false);
+        selector.set_busy(2, true);
+        assert_eq!(selector.select(&mut rng, &[2, 5]), Ok(2));
+    }
+}
@@ -0,0 +1,493 @@
+use super::*;
+
+mod datarates;
+use datarates::*;
+
+// 19.15 dBm, rounded down
+const CN_MAX_EIRP: i8 = 19;
+const DEFAULT_RX2_DATARATE: u8 = 1;
+
+/// Spacing between adjacent uplink (and downlink) channels.
+const CHANNEL_SPACING: u32 = 200_000;
+
+/// One join channel per group of 8 uplink channels.
+const JOIN_CHANNELS_20MHZ: [u8; 8] = [3, 11, 19, 27, 35, 43, 51, 59];
+/// One join channel every 2 MHz.
+const JOIN_CHANNELS_26MHZ: [u8; 5] = [0, 10, 20, 30, 40];
+
+/// Order in which the channel plans are tried when joining without a configured plan.
+const PLANS: [CN470Plan; 4] =
+    [CN470Plan::TypeA20MHz, CN470Plan::TypeB20MHz, CN470Plan::TypeA26MHz, CN470Plan::TypeB26MHz];
+
+/// Channel plans of the `CN470` region, as defined in RP002-1.0.3. The plan in use depends on the
+/// antenna of the gateways of the network.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt-03", derive(defmt::Format))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum CN470Plan {
+    /// 20 MHz antenna, type A: 64 uplink channels at 470.3-476.5 MHz and 503.5-509.7 MHz,
+    /// 32 downlink channels at 483.9-490.1 MHz.
+    TypeA20MHz,
+    /// 20 MHz antenna, type B: 64 uplink channels at 476.9-483.1 MHz and 496.3-502.5 MHz,
+    /// 32 downlink channels at 490.3-496.5 MHz.
+    TypeB20MHz,
+    /// 26 MHz antenna, type A: 48 uplink channels at 470.3-479.7 MHz, 24 downlink channels at
+    /// 490.1-494.7 MHz.
+    TypeA26MHz,
+    /// 26 MHz antenna, type B: 48 uplink channels at 480.3-489.7 MHz, 24 downlink channels at
+    /// 500.1-504.7 MHz.
+    TypeB26MHz,
+}
+
+impl CN470Plan {
+    fn num_uplink_channels(self) -> u8 {
+        match self {
+            Self::TypeA20MHz | Self::TypeB20MHz => 64,
+            Self::TypeA26MHz | Self::TypeB26MHz => 48,
+        }
+    }
+
+    fn uplink_frequency(self, channel: u8) -> u32 {
+        let channel = channel as u32;
+        match self {
+            Self::TypeA20MHz if channel < 32 => 470_300_000 + channel * CHANNEL_SPACING,
+            Self::TypeA20MHz => 503_500_000 + (channel - 32) * CHANNEL_SPACING,
+            Self::TypeB20MHz if channel < 32 => 476_900_000 + channel * CHANNEL_SPACING,
+            Self::TypeB20MHz => 496_300_000 + (channel - 32) * CHANNEL_SPACING,
+            Self::TypeA26MHz => 470_300_000 + channel * CHANNEL_SPACING,
+            Self::TypeB26MHz => 480_300_000 + channel * CHANNEL_SPACING,
+        }
+    }
+
+    /// RX1 frequency of an uplink on `channel`.
+    fn downlink_frequency(self, channel: u8) -> u32 {
+        let channel = channel as u32;
+        match self {
+            Self::TypeA20MHz => 483_900_000 + (channel % 32) * CHANNEL_SPACING,
+            Self::TypeB20MHz => 490_300_000 + (channel % 32) * CHANNEL_SPACING,
+            Self::TypeA26MHz => 490_100_000 + (channel % 24) * CHANNEL_SPACING,
+            Self::TypeB26MHz => 500_100_000 + (channel % 24) * CHANNEL_SPACING,
+        }
+    }
+
+    fn default_rx2(self) -> u32 {
+        match self {
+            Self::TypeA20MHz => 485_300_000,
+            Self::TypeB20MHz => 486_900_000,
+            Self::TypeA26MHz => 492_500_000,
+            Self::TypeB26MHz => 502_500_000,
+        }
+    }
+
+    fn join_channels(self) -> &'static [u8] {
+        match self {
+            Self::TypeA20MHz | Self::TypeB20MHz => &JOIN_CHANNELS_20MHZ,
+            Self::TypeA26MHz | Self::TypeB26MHz => &JOIN_CHANNELS_26MHZ,
+        }
+    }
+}
+
+/// State struct for the `CN470` region. This struct may be created directly if you wish to select
+/// the channel plan using [`set_plan`](Self::set_plan). Otherwise, join requests cycle through the
+/// common join channels of every plan and the plan of the network is learned from the join accept.
+/// This struct can then be turned into a [`Configuration`] as it implements [`Into<Configuration>`].
+///
+/// A join accept with a CFList of type 1 sets the enabled channels of the plan; without it, all
+/// channels of the plan are used.
+///
+/// # Example: Selecting a channel plan
+///
+/// ```
+/// use lorawan_device::region::{CN470Plan, Configuration, CN470};
+///
+/// let mut cn470 = CN470::new();
+/// cn470.set_plan(CN470Plan::TypeA26MHz);
+/// let configuration: Configuration = cn470.into();
+/// ```
+#[derive(Clone)]
+pub struct CN470 {
+    plan: Option<CN470Plan>,
+    /// Plan of the last uplink, which determines the receive window frequencies.
+    tx_plan: CN470Plan,
+    last_tx_channel: u8,
+    channel_mask: ChannelMask<9>,
+    join_attempts: usize,
+    join_channel_offset: usize,
+    rx1_dr_offset: u8,
+    rx2_dr: Option<u8>,
+    rx2_frequency: Option<u32>,
+    channel_selector: ChannelSelector,
+}
+
+impl Default for CN470 {
+    fn default() -> Self {
+        Self {
+            plan: None,
+            tx_plan: CN470Plan::TypeA20MHz,
+            last_tx_channel: 0,
+            channel_mask: ChannelMask::default(),
+            join_attempts: 0,
+            join_channel_offset: 0,
+            rx1_dr_offset: 0,
+            rx2_dr: None,
+            rx2_frequency: None,
+            channel_selector: ChannelSelector::default(),
+        }
+    }
+}
+
+impl CN470 {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Restricts the device to `plan`. This is required for ABP, since the plan cannot be learned
+    /// from a join accept.
+    pub fn set_plan(&mut self, plan: CN470Plan) {
+        self.plan = Some(plan);
+        self.tx_plan = plan;
+        self.join_attempts = 0;
+    }
+
+    /// The configured channel plan, or the one learned from the last join accept.
+    pub fn plan(&self) -> Option<CN470Plan> {
+        self.plan
+    }
+
+    pub fn get_max_payload_length(datarate: DR, repeater_compatible: bool, dwell_time: bool) -> u8 {
+        CN470Region::get_max_payload_length(datarate, repeater_compatible, dwell_time)
+    }
+
+    fn next_join_channel<RNG: RngCore>(&mut self, rng: &mut RNG) -> (CN470Plan, u8) {
+        if self.join_attempts == 0 {
+            self.join_channel_offset = rng.next_u32() as usize;
+        }
+        let (plan, round) = match self.plan {
+            Some(plan) => (plan, self.join_attempts),
+            None => (PLANS[self.join_attempts % PLANS.len()], self.join_attempts / PLANS.len()),
+        };
+        self.join_attempts += 1;
+        let join_channels = plan.join_channels();
+        let index = self.join_channel_offset.wrapping_add(round) % join_channels.len();
+        (plan, join_channels[index])
+    }
+
+    fn next_data_channel<RNG: RngCore>(
+        &mut self,
+        rng: &mut RNG,
+        plan: CN470Plan,
+    ) -> Result<u8, NoChannelAvailable> {
+        let enabled: heapless::Vec<u8, 64> = (0..plan.num_uplink_channels())
+            .filter(|channel| self.channel_mask.is_enabled(*channel as usize).unwrap())
+            .collect();
+        self.channel_selector.select(rng, &enabled)
+    }
+}
+
+/// Highest supported data rate, DR7 (FSK) being left out.
+const MAX_DATARATE: u8 = 6;
+
+/// Data rate of RX1 following an uplink at `datarate`. DR0 is not used, since it has no defined
+/// payload size, nor is DR7.
+fn rx1_datarate(datarate: u8, rx1_dr_offset: u8) -> u8 {
+    datarate.saturating_sub(rx1_dr_offset).clamp(1, MAX_DATARATE)
+}
+
+/// Parameters of `datarate` if supported for uplinks (DR1-DR6).
+fn uplink_datarate(datarate: DR) -> Result<Datarate, NoChannelAvailable> {
+    match DATARATES.get(datarate as usize) {
+        Some(Some(dr)) => Ok(dr.clone()),
+        _ => Err(NoChannelAvailable),
+    }
+}
+
+#[derive(Default, Clone)]
+pub(crate) struct CN470Region;
+
+impl ChannelRegion<8> for CN470Region {
+    fn datarates() -> &'static [Option<Datarate>; 8] {
+        &DATARATES
+    }
+}
+
+impl RegionHandler for CN470 {
+    fn process_join_accept(&mut self, cf_list: Option<CfList<'_>>) {
+        self.plan = Some(self.tx_plan);
+        self.join_attempts = 0;
+        self.channel_mask = match cf_list {
+            Some(CfList::FixedChannel(channel_mask)) => channel_mask,
+            _ => ChannelMask::default(),
+        };
+    }
+
+    fn handle_link_adr_channel_mask(
+        &mut self,
+        channel_mask_control: u8,
+        channel_mask: ChannelMask<2>,
+    ) {
+        match channel_mask_control {
+            // Blocks of 16 channels; blocks 4 and 5 are beyond the channels of any plan.
+            0..=3 => {
+                let base_index = channel_mask_control as usize * 2;
+                self.channel_mask.set_bank(base_index, channel_mask.get_index(0));
+                self.channel_mask.set_bank(base_index + 1, channel_mask.get_index(1));
+            }
+            6 => {
+                self.channel_mask = ChannelMask::default();
+            }
+            _ => {
+                // RFU
+            }
+        }
+    }
+
+    fn get_channel_mask(&self) -> &ChannelMask<9> {
+        &self.channel_mask
+    }
+
+    fn get_default_datarate(&self) -> DR {
+        DR::_1
+    }
+
+    fn get_tx_dr_and_frequency<RNG: RngCore>(
+        &mut self,
+        rng: &mut RNG,
+        datarate: DR,
+        frame: &Frame,
+    ) -> Result<(Datarate, u32), NoChannelAvailable> {
+        let data_rate = uplink_datarate(datarate)?;
+        let (plan, channel) = match (frame, self.plan) {
+            (Frame::Join, _) => self.next_join_channel(rng),
+            (Frame::Data, plan) => {
+                let plan = plan.unwrap_or(self.tx_plan);
+                (plan, self.next_data_channel(rng, plan)?)
+            }
+        };
+        self.tx_plan = plan;
+        self.last_tx_channel = channel;
+        Ok((data_rate, plan.uplink_frequency(channel)))
+    }
+
+    fn get_rx_frequency(&self, _frame: &Frame, window: &Window) -> u32 {
+        match window {
+            Window::_1 => self.tx_plan.downlink_frequency(self.last_tx_channel),
+            Window::_2 => self.rx2_frequency.unwrap_or_else(|| self.tx_plan.default_rx2()),
+        }
+    }
+
+    fn get_rx_datarate(&self, tx_datarate: DR, _frame: &Frame, window: &Window) -> Datarate {
+        let datarate = match window {
+            Window::_1 => rx1_datarate(tx_datarate as u8, self.rx1_dr_offset),
+            Window::_2 => self.rx2_dr.unwrap_or(DEFAULT_RX2_DATARATE),
+        };
+        DATARATES[datarate as usize].clone().unwrap()
+    }
+
+    fn set_rx_params(&mut self, dl_settings: DLSettings, rx2_frequency: Option<u32>) -> u8 {
+        let rx1_dr_offset = dl_settings.rx1_dr_offset();
+        let rx2_dr = dl_settings.rx2_data_rate();
+        let status = rx_param_setup_status(
+            rx2_frequency != Some(0),
+            matches!(DATARATES.get(rx2_dr as usize), Some(Some(_))),
+            rx1_dr_offset <= 5,
+        );
+        if status == 0b111 {
+            self.rx1_dr_offset = rx1_dr_offset;
+            self.rx2_dr = Some(rx2_dr);
+            self.rx2_frequency = rx2_frequency.or(self.rx2_frequency);
+        }
+        status
+    }
+
+    fn get_max_eirp(&self) -> i8 {
+        CN_MAX_EIRP
+    }
+
+    fn set_channel_selector(&mut self, selector: ChannelSelector) {
+        self.channel_selector = selector;
+    }
+
+    fn set_last_channel_busy(&mut self, busy: bool) {
+        self.channel_selector.set_busy(self.last_tx_channel, busy);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use lorawan::{
+        creator::JoinAcceptCreator,
+        default_crypto::DefaultFactory,
+        keys::AppKey,
+        parser::{parse, JoinAcceptPayload, PhyPayload},
+    };
+
+    #[test]
+    fn test_uplink_frequencies() {
+        let cases = [
+            (CN470Plan::TypeA20MHz, 0, 470_300_000),
+            (CN470Plan::TypeA20MHz, 31, 476_500_000),
+            (CN470Plan::TypeA20MHz, 32, 503_500_000),
+            (CN470Plan::TypeA20MHz, 63, 509_700_000),
+            (CN470Plan::TypeB20MHz, 0, 476_900_000),
+            (CN470Plan::TypeB20MHz, 31, 483_100_000),
+            (CN470Plan::TypeB20MHz, 32, 496_300_000),
+            (CN470Plan::TypeB20MHz, 63, 502_500_000),
+            (CN470Plan::TypeA26MHz, 0, 470_300_000),
+            (CN470Plan::TypeA26MHz, 47, 479_700_000),
+            (CN470Plan::TypeB26MHz, 0, 480_300_000),
+            (CN470Plan::TypeB26MHz, 47, 489_700_000),
+        ];
+        for (plan, channel, frequency) in cases {
+            assert_eq!(plan.uplink_frequency(channel), frequency, "{plan:?} channel {channel}");
+        }
+    }
+
+    #[test]
+    fn test_downlink_frequencies() {
+        let cases = [
+            (CN470Plan::TypeA20MHz, 0, 483_900_000),
+            (CN470Plan::TypeA20MHz, 31, 490_100_000),
+            // channels 32-63 share the downlink channels of 0-31
+            (CN470Plan::TypeA20MHz, 33, 484_100_000),
+            (CN470Plan::TypeB20MHz, 0, 490_300_000),
+            (CN470Plan::TypeB20MHz, 63, 496_500_000),
+            (CN470Plan::TypeA26MHz, 0, 490_100_000),
+            (CN470Plan::TypeA26MHz, 23, 494_700_000),
+            (CN470Plan::TypeA26MHz, 24, 490_100_000),
+            (CN470Plan::TypeB26MHz, 47, 504_700_000),
+        ];
+        for (plan, channel, frequency) in cases {
+            assert_eq!(plan.downlink_frequency(channel), frequency, "{plan:?} channel {channel}");
+        }
+    }
+
+    #[test]
+    fn test_join_iterates_plans() {
+        let mut cn470 = CN470::new();
+        let mut rng = crate::Prng::new(0);
+        let mut plans = [None; 8];
+        let mut channels = [0; 8];
+        for (plan, channel) in plans.iter_mut().zip(channels.iter_mut()) {
+            let (_, frequency) =
+                cn470.get_tx_dr_and_frequency(&mut rng, DR::_1, &Frame::Join).unwrap();
+            assert_eq!(frequency, cn470.tx_plan.uplink_frequency(cn470.last_tx_channel));
+            assert!(cn470.tx_plan.join_channels().contains(&cn470.last_tx_channel));
+            *plan = Some(cn470.tx_plan);
+            *channel = cn470.last_tx_channel;
+        }
+        assert_eq!(plans[..4], PLANS.map(Some));
+        assert_eq!(plans[4..], PLANS.map(Some));
+        // The second round moves on to the next join channel of each plan.
+        for i in 0..4 {
+            let join_channels = PLANS[i].join_channels();
+            let first = join_channels.iter().position(|c| *c == channels[i]).unwrap();
+            assert_eq!(join_channels[(first + 1) % join_channels.len()], channels[i + 4]);
+        }
+    }
+
+    #[test]
+    fn test_join_with_plan() {
+        let mut cn470 = CN470::new();
+        cn470.set_plan(CN470Plan::TypeB26MHz);
+        let mut rng = crate::Prng::new(0);
+        for _ in 0..JOIN_CHANNELS_26MHZ.len() * 2 {
+            cn470.get_tx_dr_and_frequency(&mut rng, DR::_1, &Frame::Join).unwrap();
+            assert_eq!(cn470.tx_plan, CN470Plan::TypeB26MHz);
+            assert!(JOIN_CHANNELS_26MHZ.contains(&cn470.last_tx_channel));
+        }
+        assert_eq!(cn470.get_rx_frequency(&Frame::Join, &Window::_2), 502_500_000);
+    }
+
+    #[test]
+    fn test_cf_list_type_1() {
+        let mut cn470 = CN470::new();
+        let mut rng = crate::Prng::new(0);
+        // Second attempt uses the 20 MHz type B plan.
+        cn470.get_tx_dr_and_frequency(&mut rng, DR::_1, &Frame::Join).unwrap();
+        cn470.get_tx_dr_and_frequency(&mut rng, DR::_1, &Frame::Join).unwrap();
+
+        let key = [0; 16];
+        let mut buffer = [0; 33];
+        let mut creator = JoinAcceptCreator::new(&mut buffer[..]).unwrap();
+        let channel_mask = ChannelMask::from([0, 0, 0x0F, 0, 0, 0, 0, 0, 0]);
+        creator.set_c_f_list_channel_mask(&channel_mask).unwrap();
+        let mut join_accept = creator.build(&key.into(), &DefaultFactory).unwrap().to_vec();
+        let Ok(PhyPayload::JoinAccept(JoinAcceptPayload::Encrypted(encrypted))) =
+            parse(&mut join_accept[..])
+        else {
+            panic!("Unable to parse join accept");
+        };
+        ChannelPlan::process_join_accept(
+            &mut cn470,
+            encrypted.decrypt(&AppKey::from(key)).c_f_list(),
+        );
+        assert_eq!(cn470.plan(), Some(CN470Plan::TypeB20MHz));
+
+        for _ in 0..32 {
+            let (_, frequency) =
+                cn470.get_tx_dr_and_frequency(&mut rng, DR::_2, &Frame::Data).unwrap();
+            assert!((16..20).contains(&cn470.last_tx_channel));
+            assert_eq!(frequency, 476_900_000 + cn470.last_tx_channel as u32 * CHANNEL_SPACING);
+        }
+    }
+
+    #[test]
+    fn test_data_channel_selection() {
+        let mut cn470 = CN470::new();
+        cn470.set_plan(CN470Plan::TypeA26MHz);
+        let selector = ChannelSelector::new(ChannelSelection::RoundRobin, false);
+        ChannelPlan::set_channel_selector(&mut cn470, selector);
+        let mut rng = crate::Prng::new(0);
+        // only channels 8 and 9 enabled
+        cn470.handle_link_adr_channel_mask(0, ChannelMask::new(&[0, 0b11]).unwrap());
+        cn470.handle_link_adr_channel_mask(1, ChannelMask::new(&[0, 0]).unwrap());
+        cn470.handle_link_adr_channel_mask(2, ChannelMask::new(&[0, 0]).unwrap());
+        let mut channels = [0; 4];
+        for channel in channels.iter_mut() {
+            cn470.get_tx_dr_and_frequency(&mut rng, DR::_2, &Frame::Data).unwrap();
+            *channel = cn470.last_tx_channel;
+        }
+        assert_eq!(channels, [8, 9, 8, 9]);
+
+        // no fallback to the join channels once every channel is disabled
+        cn470.handle_link_adr_channel_mask(0, ChannelMask::new(&[0, 0]).unwrap());
+        let tx = cn470.get_tx_dr_and_frequency(&mut rng, DR::_2, &Frame::Data);
+        assert_eq!(tx.err(), Some(NoChannelAvailable));
+    }
+
+    #[test]
+    fn test_rx1_datarate_table() {
+        // RX1 data rates for uplink DR1-DR7 and RX1DROffset 0-5, DR7 being capped to DR6.
+        const TABLE: [[u8; 6]; 7] = [
+            [1, 1, 1, 1, 1, 1],
+            [2, 1, 1, 1, 1, 1],
+            [3, 2, 1, 1, 1, 1],
+            [4, 3, 2, 1, 1, 1],
+            [5, 4, 3, 2, 1, 1],
+            [6, 5, 4, 3, 2, 1],
+            [6, 6, 5, 4, 3, 2],
+        ];
+        for (datarate, row) in TABLE.iter().enumerate() {
+            for (offset, expected) in row.iter().enumerate() {
+                assert_eq!(rx1_datarate(datarate as u8 + 1, offset as u8), *expected);
+            }
+        }
+    }
+
+    #[test]
+    fn test_unsupported_datarates() {
+        let mut cn470 = CN470::new();
+        let mut rng = crate::Prng::new(0);
+        for dr in [DR::_0, DR::_7, DR::_8] {
+            for frame in [Frame::Join, Frame::Data] {
+                let tx = cn470.get_tx_dr_and_frequency(&mut rng, dr, &frame);
+                assert_eq!(tx.err(), Some(NoChannelAvailable));
+            }
+            // the receive windows still use a supported data rate
+            cn470.get_rx_datarate(dr, &Frame::Data, &Window::_1);
+        }
+        assert_eq!(cn470.join_attempts, 0);
+        assert!(cn470.get_tx_dr_and_frequency(&mut rng, DR::_1, &Frame::Join).is_ok());
+    }
+}
@@ -13,4 +13,7 @@ pub(crate) const ACK_TIMEOUT: usize = 2; // random delay between 1 and 3 seconds
 pub(crate) const DEFAULT_BANDWIDTH: Bandwidth = Bandwidth::_125KHz;
 pub(crate) const DEFAULT_SPREADING_FACTOR: SpreadingFactor = SpreadingFactor::_7;
 pub(crate) const DEFAULT_CODING_RATE: CodingRate = CodingRate::_4_5;
-pub(crate) const DEFAULT_DBM: i8 = 14;
+/// MaxEIRP (dBm) of most regions, which TXPower index 0 transmits at.
+pub(crate) const DEFAULT_MAX_EIRP: i8 = 16;
+/// Highest TXPower index of most regions.
+pub(crate) const DEFAULT_MAX_TX_POWER: u8 = 7;
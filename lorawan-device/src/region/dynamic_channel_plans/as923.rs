@@ -1,11 +1,15 @@
 use super::*;
 
-const JOIN_CHANNELS: [u32; 2] = [923200000, 923200000];
+const JOIN_CHANNELS: [u32; 2] = [923_200_000, 923_400_000];
 
-pub(crate) type AS923_1 = DynamicChannelPlan<2, 7, AS923Region<923_200_000, 0>>;
-pub(crate) type AS923_2 = DynamicChannelPlan<2, 7, AS923Region<921_400_000, 1800000>>;
-pub(crate) type AS923_3 = DynamicChannelPlan<2, 7, AS923Region<916_600_000, 6600000>>;
-pub(crate) type AS923_4 = DynamicChannelPlan<2, 7, AS923Region<917_300_000, 5900000>>;
+#[cfg(feature = "region-as923-1")]
+dynamic_region!(AS923_1, DynamicChannelPlan<2, 7, AS923Region<923_200_000, 0>>);
+#[cfg(feature = "region-as923-2")]
+dynamic_region!(AS923_2, DynamicChannelPlan<2, 7, AS923Region<921_400_000, 1800000>>);
+#[cfg(feature = "region-as923-3")]
+dynamic_region!(AS923_3, DynamicChannelPlan<2, 7, AS923Region<916_600_000, 6600000>>);
+#[cfg(feature = "region-as923-4")]
+dynamic_region!(AS923_4, DynamicChannelPlan<2, 7, AS923Region<917_300_000, 5900000>>);
 
 #[derive(Default, Clone)]
 #[allow(clippy::upper_case_acronyms)]
@@ -23,12 +27,39 @@ impl<const DEFAULT_RX2: u32, const OFFSET: u32> DynamicChannelRegion<2, 7>
     for AS923Region<DEFAULT_RX2, OFFSET>
 {
     fn join_channels() -> [u32; 2] {
-        [JOIN_CHANNELS[0] + OFFSET, JOIN_CHANNELS[1] + OFFSET]
+        // AS923-2 to AS923-4 are AS923-1 shifted down by OFFSET
+        [JOIN_CHANNELS[0] - OFFSET, JOIN_CHANNELS[1] - OFFSET]
     }
 
     fn get_default_rx2() -> u32 {
         DEFAULT_RX2
     }
+
+    fn default_rx2_datarate() -> u8 {
+        2
+    }
+
+    /// AS923 devices must assume the 400 ms dwell time limit applies until told otherwise by a
+    /// TxParamSetupReq.
+    fn default_dwell_time() -> bool {
+        true
+    }
+
+    fn max_rx1_dr_offset() -> u8 {
+        7
+    }
+
+    /// Offsets 6 and 7 raise the data rate by 1 and 2; DR0 and DR1 are not used for downlinks
+    /// under the dwell time limit.
+    fn rx1_datarate(datarate: u8, rx1_dr_offset: u8, downlink_dwell_time: bool) -> u8 {
+        let min_dr = if downlink_dwell_time {
+            2
+        } else {
+            0
+        };
+        let effective_offset = [0, 1, 2, 3, 4, 5, -1, -2][rx1_dr_offset as usize];
+        (datarate as i8 - effective_offset).clamp(min_dr, 5) as u8
+    }
 }
 
 use super::{Bandwidth, Datarate, SpreadingFactor};
@@ -78,3 +109,144 @@ pub(crate) const DATARATES: [Option<Datarate>; 7] = [
     }),
     // TODO: ignore FSK data rate for now
 ];
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::region::{Configuration, Region};
+    use lorawan::{
+        creator::JoinAcceptCreator,
+        default_crypto::DefaultFactory,
+        keys::AppKey,
+        maccommands::ChannelMask,
+        parser::{parse, JoinAcceptPayload, PhyPayload},
+        types::Frequency,
+    };
+
+    fn join_accept(build: impl FnOnce(&mut JoinAcceptCreator<&mut [u8]>)) -> Vec<u8> {
+        let key = [0; 16];
+        let mut buffer = [0; 33];
+        let mut creator = JoinAcceptCreator::new(&mut buffer[..]).unwrap();
+        build(&mut creator);
+        creator.build(&key.into(), &DefaultFactory).unwrap().to_vec()
+    }
+
+    fn process_join_accept(region: &mut Configuration, mut join_accept: Vec<u8>) {
+        let Ok(PhyPayload::JoinAccept(JoinAcceptPayload::Encrypted(encrypted))) =
+            parse(&mut join_accept[..])
+        else {
+            panic!("Unable to parse join accept");
+        };
+        region.process_join_accept(encrypted.decrypt(&AppKey::from([0; 16])).c_f_list());
+    }
+
+    fn uplink_frequencies(region: &mut Configuration, datarate: DR) -> Vec<u32> {
+        let mut frequencies: Vec<u32> = (0..100)
+            .map(|_| {
+                region
+                    .create_tx_config(&mut rand_core::OsRng, datarate, 0, &Frame::Data)
+                    .unwrap()
+                    .rf
+                    .frequency
+            })
+            .collect();
+        frequencies.sort();
+        frequencies.dedup();
+        frequencies
+    }
+
+    #[test]
+    fn test_default_channels() {
+        for (region, rx2) in [
+            (Region::AS923_1, 923_200_000),
+            (Region::AS923_2, 921_400_000),
+            (Region::AS923_3, 916_600_000),
+            (Region::AS923_4, 917_300_000),
+        ] {
+            let mut region = Configuration::new(region);
+            // the RX2 frequency is the first default channel
+            assert_eq!(uplink_frequencies(&mut region, DR::_2), [rx2, rx2 + 200_000]);
+            let rx = region.get_rx_config(DR::_2, &Frame::Data, &Window::_2);
+            assert_eq!(rx.frequency, rx2);
+        }
+    }
+
+    #[test]
+    fn test_cf_list_channel_mask() {
+        let mut channel_mask = ChannelMask::<9>::new_from_raw(&[0; 9]);
+        channel_mask.set_channel(1, true);
+        let join_accept = join_accept(|creator| {
+            creator.set_c_f_list_channel_mask(&channel_mask).unwrap();
+        });
+        let mut region = Configuration::new(Region::AS923_1);
+        process_join_accept(&mut region, join_accept);
+        assert_eq!(region.get_channel_mask()[..2], [0b10, 0]);
+        assert_eq!(uplink_frequencies(&mut region, DR::_5), [923_400_000]);
+    }
+
+    #[test]
+    fn test_cf_list_channel_mask_without_usable_channel() {
+        let mut channel_mask = ChannelMask::<9>::new_from_raw(&[0; 9]);
+        // channel 5 is not defined without a CFList of type 0
+        channel_mask.set_channel(5, true);
+        let join_accept = join_accept(|creator| {
+            creator.set_c_f_list_channel_mask(&channel_mask).unwrap();
+        });
+        let mut region = Configuration::new(Region::AS923_1);
+        process_join_accept(&mut region, join_accept);
+        assert_eq!(region.get_channel_mask(), [0xFF; 9]);
+        assert_eq!(uplink_frequencies(&mut region, DR::_5), [923_200_000, 923_400_000]);
+    }
+
+    #[test]
+    fn test_cf_list_channels_datarate_range() {
+        let bytes = (923_600_000u32 / 100).to_le_bytes();
+        let join_accept = join_accept(|creator| {
+            creator.set_c_f_list([Frequency::new(&bytes[..3]).unwrap()]).unwrap();
+        });
+        let mut region = Configuration::new(Region::AS923_1);
+        process_join_accept(&mut region, join_accept);
+        let channels = [923_200_000, 923_400_000, 923_600_000];
+        assert_eq!(uplink_frequencies(&mut region, DR::_5), channels);
+        // no channel has DR6 in its range, so all of them remain usable
+        assert_eq!(uplink_frequencies(&mut region, DR::_6), channels);
+    }
+
+    #[test]
+    fn test_rx1_datarate_table() {
+        // RX1 data rates for uplink DR0-DR6 and RX1DROffset 0-7, without and with downlink dwell
+        // time.
+        const NO_DWELL_TIME: [[u8; 8]; 7] = [
+            [0, 0, 0, 0, 0, 0, 1, 2],
+            [1, 0, 0, 0, 0, 0, 2, 3],
+            [2, 1, 0, 0, 0, 0, 3, 4],
+            [3, 2, 1, 0, 0, 0, 4, 5],
+            [4, 3, 2, 1, 0, 0, 5, 5],
+            [5, 4, 3, 2, 1, 0, 5, 5],
+            [5, 5, 4, 3, 2, 1, 5, 5],
+        ];
+        const DWELL_TIME: [[u8; 8]; 7] = [
+            [2, 2, 2, 2, 2, 2, 2, 2],
+            [2, 2, 2, 2, 2, 2, 2, 3],
+            [2, 2, 2, 2, 2, 2, 3, 4],
+            [3, 2, 2, 2, 2, 2, 4, 5],
+            [4, 3, 2, 2, 2, 2, 5, 5],
+            [5, 4, 3, 2, 2, 2, 5, 5],
+            [5, 5, 4, 3, 2, 2, 5, 5],
+        ];
+        for (dwell_time, table) in [(false, NO_DWELL_TIME), (true, DWELL_TIME)] {
+            for (datarate, row) in table.iter().enumerate() {
+                for (offset, rx1_datarate) in row.iter().enumerate() {
+                    assert_eq!(
+                        AS923Region::<923_200_000, 0>::rx1_datarate(
+                            datarate as u8,
+                            offset as u8,
+                            dwell_time
+                        ),
+                        *rx1_datarate
+                    );
+                }
+            }
+        }
+    }
+}
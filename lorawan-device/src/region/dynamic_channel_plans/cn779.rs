@@ -0,0 +1,148 @@
+#![allow(dead_code)]
+use super::*;
+
+const JOIN_CHANNELS: [u32; 3] = [779_500_000, 779_700_000, 779_900_000];
+
+// 12.15 dBm, the default MaxEIRP of RP002-1.0.3, rounded down
+const CN_MAX_EIRP: i8 = 12;
+
+dynamic_region!(CN779, DynamicChannelPlan<3, 7, CN779Region>);
+
+#[derive(Default, Clone)]
+#[allow(clippy::upper_case_acronyms)]
+pub struct CN779Region;
+
+impl ChannelRegion<7> for CN779Region {
+    fn datarates() -> &'static [Option<Datarate>; 7] {
+        &DATARATES
+    }
+}
+
+impl DynamicChannelRegion<3, 7> for CN779Region {
+    fn join_channels() -> [u32; 3] {
+        JOIN_CHANNELS
+    }
+
+    fn get_default_rx2() -> u32 {
+        786_000_000
+    }
+
+    fn max_eirp() -> i8 {
+        CN_MAX_EIRP
+    }
+
+    fn max_tx_power() -> u8 {
+        5
+    }
+}
+
+use super::{Bandwidth, Datarate, SpreadingFactor};
+
+pub(crate) const DATARATES: [Option<Datarate>; 7] = [
+    Some(Datarate {
+        spreading_factor: SpreadingFactor::_12,
+        bandwidth: Bandwidth::_125KHz,
+        max_mac_payload_size: 59,
+        max_mac_payload_size_with_dwell_time: 59,
+    }),
+    Some(Datarate {
+        spreading_factor: SpreadingFactor::_11,
+        bandwidth: Bandwidth::_125KHz,
+        max_mac_payload_size: 59,
+        max_mac_payload_size_with_dwell_time: 59,
+    }),
+    Some(Datarate {
+        spreading_factor: SpreadingFactor::_10,
+        bandwidth: Bandwidth::_125KHz,
+        max_mac_payload_size: 59,
+        max_mac_payload_size_with_dwell_time: 59,
+    }),
+    Some(Datarate {
+        spreading_factor: SpreadingFactor::_9,
+        bandwidth: Bandwidth::_125KHz,
+        max_mac_payload_size: 123,
+        max_mac_payload_size_with_dwell_time: 123,
+    }),
+    Some(Datarate {
+        spreading_factor: SpreadingFactor::_8,
+        bandwidth: Bandwidth::_125KHz,
+        max_mac_payload_size: 250,
+        max_mac_payload_size_with_dwell_time: 250,
+    }),
+    Some(Datarate {
+        spreading_factor: SpreadingFactor::_7,
+        bandwidth: Bandwidth::_125KHz,
+        max_mac_payload_size: 250,
+        max_mac_payload_size_with_dwell_time: 250,
+    }),
+    Some(Datarate {
+        spreading_factor: SpreadingFactor::_7,
+        bandwidth: Bandwidth::_250KHz,
+        max_mac_payload_size: 250,
+        max_mac_payload_size_with_dwell_time: 250,
+    }),
+    // TODO: ignore FSK data rate for now
+];
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::region::{Configuration, Region};
+    use lorawan::{
+        creator::JoinAcceptCreator,
+        default_crypto::DefaultFactory,
+        keys::AppKey,
+        parser::{parse, JoinAcceptPayload, PhyPayload},
+        types::Frequency,
+    };
+
+    #[test]
+    fn test_default_channels() {
+        let mut region = Configuration::new(Region::CN779);
+        for _ in 0..20 {
+            let tx_config =
+                region.create_tx_config(&mut rand_core::OsRng, DR::_0, 0, &Frame::Join).unwrap();
+            assert!(JOIN_CHANNELS.contains(&tx_config.rf.frequency));
+            assert_eq!(tx_config.rf.bb.sf, SpreadingFactor::_12);
+            assert_eq!(tx_config.pw, 12);
+        }
+        let rx2 = region.get_rx_config(DR::_5, &Frame::Data, &Window::_2);
+        assert_eq!(rx2.frequency, 786_000_000);
+        assert_eq!(rx2.bb.sf, SpreadingFactor::_12);
+        assert_eq!(rx2.bb.bw, Bandwidth::_125KHz);
+    }
+
+    #[test]
+    fn test_cf_list() {
+        let key = [0; 16];
+        let mut buffer = [0; 33];
+        let mut creator = JoinAcceptCreator::new(&mut buffer[..]).unwrap();
+        let bytes = (780_100_000u32 / 100).to_le_bytes();
+        creator.set_c_f_list([Frequency::new(&bytes[..3]).unwrap()]).unwrap();
+        let mut join_accept = creator.build(&key.into(), &DefaultFactory).unwrap().to_vec();
+        let Ok(PhyPayload::JoinAccept(JoinAcceptPayload::Encrypted(encrypted))) =
+            parse(&mut join_accept[..])
+        else {
+            panic!("Unable to parse join accept");
+        };
+
+        let mut region = Configuration::new(Region::CN779);
+        region.process_join_accept(encrypted.decrypt(&AppKey::from(key)).c_f_list());
+        let mut used = [false; 4];
+        for _ in 0..100 {
+            let tx_config =
+                region.create_tx_config(&mut rand_core::OsRng, DR::_5, 0, &Frame::Data).unwrap();
+            match tx_config.rf.frequency {
+                779_500_000 => used[0] = true,
+                779_700_000 => used[1] = true,
+                779_900_000 => used[2] = true,
+                780_100_000 => used[3] = true,
+                f => panic!("Unexpected frequency {f}"),
+            }
+            // RX1 happens on the uplink channel
+            let rx1 = region.get_rx_config(DR::_5, &Frame::Data, &Window::_1);
+            assert_eq!(rx1.frequency, tx_config.rf.frequency);
+        }
+        assert_eq!(used, [true; 4]);
+    }
+}
@@ -3,7 +3,10 @@ use super::*;
 
 const JOIN_CHANNELS: [u32; 3] = [433_175_000, 433_375_000, 433_575_000];
 
-pub(crate) type EU433 = DynamicChannelPlan<3, 7, EU433Region>;
+// 12.15 dBm, rounded down
+const EU_MAX_EIRP: i8 = 12;
+
+dynamic_region!(EU433, DynamicChannelPlan<3, 7, EU433Region>);
 
 #[derive(Default, Clone)]
 #[allow(clippy::upper_case_acronyms)]
@@ -23,6 +26,14 @@ impl DynamicChannelRegion<3, 7> for EU433Region {
     fn get_default_rx2() -> u32 {
         434_665_000
     }
+
+    fn max_eirp() -> i8 {
+        EU_MAX_EIRP
+    }
+
+    fn max_tx_power() -> u8 {
+        5
+    }
 }
 
 use super::{Bandwidth, Datarate, SpreadingFactor};
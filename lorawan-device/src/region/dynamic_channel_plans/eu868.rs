@@ -3,7 +3,7 @@ use super::*;
 
 const JOIN_CHANNELS: [u32; 3] = [868_100_000, 868_300_000, 868_500_000];
 
-pub(crate) type EU868 = DynamicChannelPlan<3, 7, EU868Region>;
+dynamic_region!(EU868, DynamicChannelPlan<3, 7, EU868Region>);
 
 #[derive(Default, Clone)]
 #[allow(clippy::upper_case_acronyms)]
@@ -72,3 +72,113 @@ pub(crate) const DATARATES: [Option<Datarate>; 7] = [
     }),
     // TODO: ignore FSK data rate for now
 ];
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::region::{ChannelPlan, Configuration, Region};
+
+    #[test]
+    fn test_rx1_datarate_table() {
+        // RX1 data rates for uplink DR0-DR6 and RX1DROffset 0-5.
+        const TABLE: [[u8; 6]; 7] = [
+            [0, 0, 0, 0, 0, 0],
+            [1, 0, 0, 0, 0, 0],
+            [2, 1, 0, 0, 0, 0],
+            [3, 2, 1, 0, 0, 0],
+            [4, 3, 2, 1, 0, 0],
+            [5, 4, 3, 2, 1, 0],
+            [6, 5, 4, 3, 2, 1],
+        ];
+        for (datarate, row) in TABLE.iter().enumerate() {
+            for (offset, rx1_datarate) in row.iter().enumerate() {
+                assert_eq!(
+                    EU868Region::rx1_datarate(datarate as u8, offset as u8, false),
+                    *rx1_datarate
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_rx_param_setup() {
+        let mut region = Configuration::new(Region::EU868);
+        // RX1DROffset 6 is not defined for EU868: nothing is applied.
+        assert_eq!(region.set_rx_params(DLSettings::new((6 << 4) | 3), Some(869_100_000)), 0b011);
+        let rx2 = region.get_rx_config(DR::_5, &Frame::Data, &Window::_2);
+        assert_eq!(rx2.frequency, 869_525_000);
+        assert_eq!(rx2.bb.sf, SpreadingFactor::_12);
+
+        assert_eq!(region.set_rx_params(DLSettings::new((3 << 4) | 3), Some(869_100_000)), 0b111);
+        let rx1 = region.get_rx_config(DR::_5, &Frame::Data, &Window::_1);
+        assert_eq!(rx1.bb.sf, SpreadingFactor::_10);
+        let rx2 = region.get_rx_config(DR::_5, &Frame::Data, &Window::_2);
+        assert_eq!(rx2.frequency, 869_100_000);
+        assert_eq!(rx2.bb.sf, SpreadingFactor::_9);
+    }
+
+    #[test]
+    fn test_dl_channel() {
+        let mut region = Configuration::new(Region::EU868);
+        let mut rng = crate::Prng::new(0);
+        // Channel 3 is not defined.
+        assert_eq!(region.set_downlink_frequency(3, 869_100_000), Some(0b01));
+        assert_eq!(region.set_downlink_frequency(1, 869_100_000), Some(0b11));
+        for _ in 0..16 {
+            let tx = region.create_tx_config(&mut rng, DR::_0, 0, &Frame::Data).unwrap();
+            let rx1 = region.get_rx_config(DR::_0, &Frame::Data, &Window::_1);
+            if tx.rf.frequency == 868_300_000 {
+                assert_eq!(rx1.frequency, 869_100_000);
+            } else {
+                assert_eq!(rx1.frequency, tx.rf.frequency);
+            }
+        }
+    }
+
+    #[test]
+    fn test_tx_power() {
+        let mut region = Configuration::new(Region::EU868);
+        assert_eq!(region.get_max_tx_power(), 7);
+        for tx_power in 0..=7 {
+            let tx = region
+                .create_tx_config(&mut rand_core::OsRng, DR::_0, tx_power, &Frame::Data)
+                .unwrap();
+            assert_eq!(tx.pw, 16 - 2 * tx_power as i8);
+        }
+    }
+
+    #[test]
+    fn test_no_channel_available() {
+        let mut region = Configuration::new(Region::EU868);
+        region.set_channel_mask(0, ChannelMask::new(&[0, 0]).unwrap());
+        let tx = region.create_tx_config(&mut rand_core::OsRng, DR::_0, 0, &Frame::Data);
+        assert_eq!(tx, Err(NoChannelAvailable));
+        // joins always use the default channels
+        let tx = region.create_tx_config(&mut rand_core::OsRng, DR::_0, 0, &Frame::Join);
+        assert!(JOIN_CHANNELS.contains(&tx.unwrap().rf.frequency));
+    }
+
+    #[test]
+    fn test_round_robin() {
+        let mut region = Configuration::new(Region::EU868);
+        region.set_channel_selector(ChannelSelector::new(ChannelSelection::RoundRobin, false));
+        let mut rng = crate::Prng::new(0);
+        for frequency in JOIN_CHANNELS.iter().cycle().take(7) {
+            let tx = region.create_tx_config(&mut rng, DR::_0, 0, &Frame::Data).unwrap();
+            assert_eq!(tx.rf.frequency, *frequency);
+        }
+    }
+
+    #[test]
+    fn test_busy_channel_avoided() {
+        let mut region = Configuration::new(Region::EU868);
+        region.set_channel_selector(ChannelSelector::new(ChannelSelection::Random, true));
+        let mut rng = crate::Prng::new(0);
+        let busy = region.create_tx_config(&mut rng, DR::_0, 0, &Frame::Data).unwrap();
+        region.set_last_channel_busy(true);
+        for _ in 0..16 {
+            let tx = region.create_tx_config(&mut rng, DR::_0, 0, &Frame::Data).unwrap();
+            assert_ne!(tx.rf.frequency, busy.rf.frequency);
+        }
+    }
+}
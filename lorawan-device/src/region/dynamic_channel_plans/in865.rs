@@ -3,7 +3,9 @@ use super::*;
 
 const JOIN_CHANNELS: [u32; 3] = [865_062_500, 865_402_500, 865_985_000];
 
-pub(crate) type IN865 = DynamicChannelPlan<3, 6, IN865Region>;
+const IN_MAX_EIRP: i8 = 30;
+
+dynamic_region!(IN865, DynamicChannelPlan<3, 6, IN865Region>);
 
 #[derive(Default, Clone)]
 #[allow(clippy::upper_case_acronyms)]
@@ -23,6 +25,28 @@ impl DynamicChannelRegion<3, 6> for IN865Region {
     fn get_default_rx2() -> u32 {
         866_550_000
     }
+
+    fn default_rx2_datarate() -> u8 {
+        2
+    }
+
+    fn max_eirp() -> i8 {
+        IN_MAX_EIRP
+    }
+
+    fn max_tx_power() -> u8 {
+        10
+    }
+
+    fn max_rx1_dr_offset() -> u8 {
+        7
+    }
+
+    /// Offsets 6 and 7 raise the data rate by 1 and 2.
+    fn rx1_datarate(datarate: u8, rx1_dr_offset: u8, _downlink_dwell_time: bool) -> u8 {
+        let effective_offset = [0, 1, 2, 3, 4, 5, -1, -2][rx1_dr_offset as usize];
+        (datarate as i8 - effective_offset).clamp(0, 5) as u8
+    }
 }
 
 use super::{Bandwidth, Datarate, SpreadingFactor};
@@ -66,3 +90,29 @@ pub(crate) const DATARATES: [Option<Datarate>; 6] = [
     }),
     // TODO: ignore FSK data rate for now
 ];
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_rx1_datarate_table() {
+        // RX1 data rates for uplink DR0-DR5 and RX1DROffset 0-7.
+        const TABLE: [[u8; 8]; 6] = [
+            [0, 0, 0, 0, 0, 0, 1, 2],
+            [1, 0, 0, 0, 0, 0, 2, 3],
+            [2, 1, 0, 0, 0, 0, 3, 4],
+            [3, 2, 1, 0, 0, 0, 4, 5],
+            [4, 3, 2, 1, 0, 0, 5, 5],
+            [5, 4, 3, 2, 1, 0, 5, 5],
+        ];
+        for (datarate, row) in TABLE.iter().enumerate() {
+            for (offset, rx1_datarate) in row.iter().enumerate() {
+                assert_eq!(
+                    IN865Region::rx1_datarate(datarate as u8, offset as u8, false),
+                    *rx1_datarate
+                );
+            }
+        }
+    }
+}
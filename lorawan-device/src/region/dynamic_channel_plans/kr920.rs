@@ -0,0 +1,101 @@
+#![allow(dead_code)]
+use super::*;
+
+const JOIN_CHANNELS: [u32; 3] = [922_100_000, 922_300_000, 922_500_000];
+
+const KR_MAX_EIRP: i8 = 14;
+
+dynamic_region!(KR920, DynamicChannelPlan<3, 6, KR920Region>);
+
+#[derive(Default, Clone)]
+#[allow(clippy::upper_case_acronyms)]
+pub struct KR920Region;
+
+impl ChannelRegion<6> for KR920Region {
+    fn datarates() -> &'static [Option<Datarate>; 6] {
+        &DATARATES
+    }
+}
+
+impl DynamicChannelRegion<3, 6> for KR920Region {
+    fn join_channels() -> [u32; 3] {
+        JOIN_CHANNELS
+    }
+
+    fn get_default_rx2() -> u32 {
+        921_900_000
+    }
+
+    fn max_eirp() -> i8 {
+        KR_MAX_EIRP
+    }
+
+    /// Channels from 920.9 MHz to 921.9 MHz are limited to 10 dBm.
+    fn band_max_eirp(frequency: u32) -> i8 {
+        if frequency < 922_000_000 {
+            10
+        } else {
+            KR_MAX_EIRP
+        }
+    }
+
+    /// KR920 devices must listen before talking: a transmission may only happen if no signal
+    /// above -80 dBm was sensed on the channel for 5 ms.
+    fn listen_before_talk() -> Option<(i16, u32)> {
+        Some((-80, 5000))
+    }
+}
+
+use super::{Bandwidth, Datarate, SpreadingFactor};
+
+pub(crate) const DATARATES: [Option<Datarate>; 6] = [
+    Some(Datarate {
+        spreading_factor: SpreadingFactor::_12,
+        bandwidth: Bandwidth::_125KHz,
+        max_mac_payload_size: 59,
+        max_mac_payload_size_with_dwell_time: 59,
+    }),
+    Some(Datarate {
+        spreading_factor: SpreadingFactor::_11,
+        bandwidth: Bandwidth::_125KHz,
+        max_mac_payload_size: 59,
+        max_mac_payload_size_with_dwell_time: 59,
+    }),
+    Some(Datarate {
+        spreading_factor: SpreadingFactor::_10,
+        bandwidth: Bandwidth::_125KHz,
+        max_mac_payload_size: 59,
+        max_mac_payload_size_with_dwell_time: 59,
+    }),
+    Some(Datarate {
+        spreading_factor: SpreadingFactor::_9,
+        bandwidth: Bandwidth::_125KHz,
+        max_mac_payload_size: 123,
+        max_mac_payload_size_with_dwell_time: 123,
+    }),
+    Some(Datarate {
+        spreading_factor: SpreadingFactor::_8,
+        bandwidth: Bandwidth::_125KHz,
+        max_mac_payload_size: 250,
+        max_mac_payload_size_with_dwell_time: 250,
+    }),
+    Some(Datarate {
+        spreading_factor: SpreadingFactor::_7,
+        bandwidth: Bandwidth::_125KHz,
+        max_mac_payload_size: 250,
+        max_mac_payload_size_with_dwell_time: 250,
+    }),
+];
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_band_max_eirp() {
+        let region = KR920::default();
+        assert_eq!(RegionHandler::get_eirp(&region.0, 0, 921_100_000), 10);
+        assert_eq!(RegionHandler::get_eirp(&region.0, 3, 921_100_000), 8);
+        assert_eq!(RegionHandler::get_eirp(&region.0, 0, 922_100_000), 14);
+    }
+}
@@ -1,6 +1,29 @@
 use super::*;
 use core::marker::PhantomData;
 
+/// Declares the public state struct of a region with a dynamic channel plan.
+macro_rules! dynamic_region {
+    ($name:ident, $plan:ty) => {
+        #[doc = concat!("State struct for the `", stringify!($name), "` region.")]
+        ///
+        /// Using it as the [`ChannelPlan`] of a device, instead of a [`Configuration`], only
+        /// builds the code of this region into the firmware.
+        #[derive(Default, Clone)]
+        #[allow(non_camel_case_types)]
+        pub struct $name(pub(crate) $plan);
+
+        impl $name {
+            pub fn get_max_payload_length(
+                datarate: DR,
+                repeater_compatible: bool,
+                dwell_time: bool,
+            ) -> u8 {
+                <$plan>::get_max_payload_length(datarate, repeater_compatible, dwell_time)
+            }
+        }
+    };
+}
+
 #[cfg(any(
     feature = "region-as923-1",
     feature = "region-as923-2",
@@ -8,27 +31,39 @@ use core::marker::PhantomData;
     feature = "region-as923-4"
 ))]
 mod as923;
+#[cfg(feature = "region-cn779")]
+mod cn779;
 #[cfg(feature = "region-eu433")]
 mod eu433;
 #[cfg(feature = "region-eu868")]
 mod eu868;
 #[cfg(feature = "region-in865")]
 mod in865;
+#[cfg(feature = "region-kr920")]
+mod kr920;
+#[cfg(feature = "region-ru864")]
+mod ru864;
 
 #[cfg(feature = "region-as923-1")]
-pub(crate) use as923::AS923_1;
+pub use as923::AS923_1;
 #[cfg(feature = "region-as923-2")]
-pub(crate) use as923::AS923_2;
+pub use as923::AS923_2;
 #[cfg(feature = "region-as923-3")]
-pub(crate) use as923::AS923_3;
+pub use as923::AS923_3;
 #[cfg(feature = "region-as923-4")]
-pub(crate) use as923::AS923_4;
+pub use as923::AS923_4;
+#[cfg(feature = "region-cn779")]
+pub use cn779::CN779;
 #[cfg(feature = "region-eu433")]
-pub(crate) use eu433::EU433;
+pub use eu433::EU433;
 #[cfg(feature = "region-eu868")]
-pub(crate) use eu868::EU868;
+pub use eu868::EU868;
 #[cfg(feature = "region-in865")]
-pub(crate) use in865::IN865;
+pub use in865::IN865;
+#[cfg(feature = "region-kr920")]
+pub use kr920::KR920;
+#[cfg(feature = "region-ru864")]
+pub use ru864::RU864;
 
 #[derive(Default, Clone)]
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
@@ -37,12 +72,37 @@ pub(crate) struct DynamicChannelPlan<
     const NUM_DATARATES: usize,
     R: DynamicChannelRegion<NUM_JOIN_CHANNELS, NUM_DATARATES>,
 > {
-    additional_channels: [Option<u32>; 5],
+    additional_channels: [Option<Channel>; 5],
     channel_mask: ChannelMask<9>,
     last_tx_channel: u8,
     _fixed_channel_region: PhantomData<R>,
-    rx1_offset: usize,
-    rx2_dr: usize,
+    rx1_dr_offset: u8,
+    rx2_dr: Option<u8>,
+    rx2_frequency: Option<u32>,
+    downlink_frequencies: [Option<u32>; 8],
+    channel_selector: ChannelSelector,
+}
+
+/// A channel of the plan: its frequency and the range of data rates it may be used with.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub(crate) struct Channel {
+    frequency: u32,
+    min_dr: u8,
+    max_dr: u8,
+}
+
+impl Channel {
+    fn with_default_datarates<const J: usize, const D: usize, R: DynamicChannelRegion<J, D>>(
+        frequency: u32,
+    ) -> Self {
+        let (min_dr, max_dr) = R::default_datarate_range();
+        Self { frequency, min_dr: min_dr as u8, max_dr: max_dr as u8 }
+    }
+
+    fn supports(&self, datarate: DR) -> bool {
+        (self.min_dr..=self.max_dr).contains(&(datarate as u8))
+    }
 }
 
 impl<
@@ -51,34 +111,28 @@ impl<
         R: DynamicChannelRegion<NUM_JOIN_CHANNELS, NUM_DATARATES>,
     > DynamicChannelPlan<NUM_JOIN_CHANNELS, NUM_DATARATES, R>
 {
-    fn get_channel(&self, channel: usize) -> Option<u32> {
+    fn get_channel(&self, channel: usize) -> Option<Channel> {
         if channel < NUM_JOIN_CHANNELS {
-            Some(R::join_channels()[channel])
+            Some(Channel::with_default_datarates::<NUM_JOIN_CHANNELS, NUM_DATARATES, R>(
+                R::join_channels()[channel],
+            ))
         } else {
-            self.additional_channels[channel - NUM_JOIN_CHANNELS]
+            // regions with less than 3 join channels can draw indices beyond the CFList channels
+            self.additional_channels.get(channel - NUM_JOIN_CHANNELS).copied().flatten()
         }
     }
 
-    fn highest_additional_channel_index_plus_one(&self) -> usize {
-        let mut index_plus_one = 0;
-        for (i, channel) in self.additional_channels.iter().enumerate() {
-            if channel.is_some() {
-                index_plus_one = i + 1;
-            }
+    /// Frequency of `channel` if it is defined, enabled and, unless `datarate` is `None`, allows
+    /// `datarate`.
+    fn get_usable_frequency(&self, channel: usize, datarate: Option<DR>) -> Option<u32> {
+        if !self.channel_mask.is_enabled(channel).unwrap_or(false) {
+            return None;
+        }
+        let channel = self.get_channel(channel)?;
+        match datarate {
+            Some(datarate) if !channel.supports(datarate) => None,
+            _ => Some(channel.frequency),
         }
-        index_plus_one
-    }
-
-    fn get_random_in_range<RNG: RngCore>(&self, rng: &mut RNG) -> usize {
-        let range = self.highest_additional_channel_index_plus_one() + NUM_JOIN_CHANNELS;
-        let cm = if range > 16 {
-            0b11111
-        } else if range > 8 {
-            0b1111
-        } else {
-            0b111
-        };
-        (rng.next_u32() as usize) & cm
     }
 
     pub fn get_max_payload_length(datarate: DR, repeater_compatible: bool, dwell_time: bool) -> u8 {
@@ -91,6 +145,43 @@ pub(crate) trait DynamicChannelRegion<const NUM_JOIN_CHANNELS: usize, const NUM_
 {
     fn join_channels() -> [u32; NUM_JOIN_CHANNELS];
     fn get_default_rx2() -> u32;
+    /// Default MaxEIRP of the region, in dBm. TXPower index `n` transmits at MaxEIRP - 2n dB.
+    fn max_eirp() -> i8 {
+        DEFAULT_MAX_EIRP
+    }
+    fn max_tx_power() -> u8 {
+        DEFAULT_MAX_TX_POWER
+    }
+    /// Highest EIRP allowed on `frequency` by the band it belongs to, when it is lower than the
+    /// MaxEIRP of the region.
+    fn band_max_eirp(_frequency: u32) -> i8 {
+        Self::max_eirp()
+    }
+    /// Data rates of the default channels and of the channels created by a CFList, which is
+    /// DR0 to DR5 in all dynamic channel plans of RP002.
+    fn default_datarate_range() -> (DR, DR) {
+        (DR::_0, DR::_5)
+    }
+    fn default_rx2_datarate() -> u8 {
+        0
+    }
+    /// Whether the 400 ms dwell time limit applies to uplinks and downlinks by default.
+    fn default_dwell_time() -> bool {
+        false
+    }
+    fn max_rx1_dr_offset() -> u8 {
+        5
+    }
+    /// Data rate of RX1 following an uplink at `datarate`, as defined by the RX1DROffset table of
+    /// the region.
+    fn rx1_datarate(datarate: u8, rx1_dr_offset: u8, _downlink_dwell_time: bool) -> u8 {
+        datarate.saturating_sub(rx1_dr_offset)
+    }
+    /// Energy detection threshold (dBm) and sensing time (µs) of listen-before-talk, if the
+    /// region requires it.
+    fn listen_before_talk() -> Option<(i16, u32)> {
+        None
+    }
 }
 
 impl<
@@ -99,11 +190,8 @@ impl<
         R: DynamicChannelRegion<NUM_JOIN_CHANNELS, NUM_DATARATES>,
     > RegionHandler for DynamicChannelPlan<NUM_JOIN_CHANNELS, NUM_DATARATES, R>
 {
-    fn process_join_accept<T: AsRef<[u8]>, C>(
-        &mut self,
-        join_accept: &DecryptedJoinAcceptPayload<T, C>,
-    ) {
-        match join_accept.c_f_list() {
+    fn process_join_accept(&mut self, cf_list: Option<CfList<'_>>) {
+        match cf_list {
             Some(CfList::DynamicChannel(cf_list)) => {
                 // If CfList of Type 0 is present, it may contain up to 5 frequencies
                 // which define channels J to (J+4)
@@ -111,15 +199,30 @@ impl<
                     let value = freq.value();
                     // unused channels are set to 0
                     if value != 0 {
-                        self.additional_channels[index] = Some(value);
+                        self.additional_channels[index] = Some(Channel::with_default_datarates::<
+                            NUM_JOIN_CHANNELS,
+                            NUM_DATARATES,
+                            R,
+                        >(value));
                     } else {
                         self.additional_channels[index] = None;
                     }
+                    self.downlink_frequencies[NUM_JOIN_CHANNELS + index] = None;
                 }
             }
-            Some(CfList::FixedChannel(_cf_list)) => {
-                //TODO: dynamic channel plans have corresponding fixed channel lists,
-                // however, this feature is entirely optional
+            Some(CfList::FixedChannel(cf_list)) => {
+                // In dynamic channel plans, a CFList of Type 1 holds the mask of channels 0 to 15;
+                // a mask which leaves no usable channel is ignored.
+                let mut channel_mask = self.channel_mask.clone();
+                channel_mask.set_bank(0, cf_list.get_index(0));
+                channel_mask.set_bank(1, cf_list.get_index(1));
+                let usable = (0..16).any(|channel| {
+                    channel_mask.is_enabled(channel).unwrap_or(false)
+                        && self.get_channel(channel).is_some()
+                });
+                if usable {
+                    self.channel_mask = channel_mask;
+                }
             }
             None => {}
         }
@@ -161,55 +264,116 @@ impl<
         }
     }
 
+    fn get_channel_mask(&self) -> &ChannelMask<9> {
+        &self.channel_mask
+    }
+
     fn get_tx_dr_and_frequency<RNG: RngCore>(
         &mut self,
         rng: &mut RNG,
         datarate: DR,
         frame: &Frame,
-    ) -> (Datarate, u32) {
-        match frame {
+    ) -> Result<(Datarate, u32), NoChannelAvailable> {
+        let frequency = match frame {
             Frame::Join => {
-                // there are at most 8 join channels
-                let mut channel = (rng.next_u32() & 0b111) as u8;
-                // keep sampling until we select a join channel depending
-                // on the frequency plan
-                while channel as usize >= NUM_JOIN_CHANNELS {
-                    channel = (rng.next_u32() & 0b111) as u8;
-                }
-                self.last_tx_channel = channel;
-                (
-                    R::datarates()[datarate as usize].clone().unwrap(),
-                    R::join_channels()[channel as usize],
-                )
+                let channel = rng.next_u32() as usize % NUM_JOIN_CHANNELS;
+                self.last_tx_channel = channel as u8;
+                R::join_channels()[channel]
             }
             Frame::Data => {
-                let mut channel = self.get_random_in_range(rng);
-                loop {
-                    if self.channel_mask.is_enabled(channel).unwrap() {
-                        if let Some(freq) = self.get_channel(channel) {
-                            self.last_tx_channel = channel as u8;
-                            return (R::datarates()[datarate as usize].clone().unwrap(), freq);
-                        }
-                    }
-                    channel = self.get_random_in_range(rng)
-                }
+                // only fall back to channels without `datarate` in their range when none has it
+                let range = NUM_JOIN_CHANNELS + self.additional_channels.len();
+                let datarate_filter = (0..range)
+                    .any(|channel| self.get_usable_frequency(channel, Some(datarate)).is_some())
+                    .then_some(datarate);
+                let enabled: heapless::Vec<u8, 16> = (0..range)
+                    .filter(|channel| {
+                        self.get_usable_frequency(*channel, datarate_filter).is_some()
+                    })
+                    .map(|channel| channel as u8)
+                    .collect();
+                let channel = self.channel_selector.select(rng, &enabled)?;
+                self.last_tx_channel = channel;
+                self.get_channel(channel as usize).unwrap().frequency
             }
-        }
+        };
+        Ok((R::datarates()[datarate as usize].clone().unwrap(), frequency))
     }
 
     fn get_rx_frequency(&self, _frame: &Frame, window: &Window) -> u32 {
+        let channel = self.last_tx_channel as usize;
         match window {
-            // TODO: implement RxOffset but first need to implement RxOffset MacCommand
-            Window::_1 => self.get_channel(self.last_tx_channel as usize).unwrap(),
-            Window::_2 => R::get_default_rx2(),
+            Window::_1 => self.downlink_frequencies[channel]
+                .unwrap_or_else(|| self.get_channel(channel).unwrap().frequency),
+            Window::_2 => self.rx2_frequency.unwrap_or_else(R::get_default_rx2),
         }
     }
 
     fn get_rx_datarate(&self, tx_datarate: DR, _frame: &Frame, window: &Window) -> Datarate {
         let datarate = match window {
-            Window::_1 => tx_datarate as usize + self.rx1_offset,
-            Window::_2 => self.rx2_dr,
+            Window::_1 => {
+                R::rx1_datarate(tx_datarate as u8, self.rx1_dr_offset, R::default_dwell_time())
+            }
+            Window::_2 => self.rx2_dr.unwrap_or_else(R::default_rx2_datarate),
         };
-        R::datarates()[datarate].clone().unwrap()
+        R::datarates()[datarate as usize].clone().unwrap()
+    }
+
+    fn get_max_eirp(&self) -> i8 {
+        R::max_eirp()
+    }
+
+    fn get_max_tx_power(&self) -> u8 {
+        R::max_tx_power()
+    }
+
+    fn get_band_max_eirp(&self, frequency: u32) -> i8 {
+        R::band_max_eirp(frequency)
+    }
+
+    fn get_default_uplink_dwell_time(&self) -> bool {
+        R::default_dwell_time()
+    }
+
+    fn get_clear_channel_config(&self, rf: RfConfig) -> Option<ClearChannelConfig> {
+        R::listen_before_talk().map(|(threshold_dbm, sensing_time_us)| ClearChannelConfig {
+            rf,
+            threshold_dbm,
+            sensing_time_us,
+        })
+    }
+
+    fn set_rx_params(&mut self, dl_settings: DLSettings, rx2_frequency: Option<u32>) -> u8 {
+        let rx1_dr_offset = dl_settings.rx1_dr_offset();
+        let rx2_dr = dl_settings.rx2_data_rate();
+        let status = rx_param_setup_status(
+            rx2_frequency != Some(0),
+            matches!(R::datarates().get(rx2_dr as usize), Some(Some(_))),
+            rx1_dr_offset <= R::max_rx1_dr_offset(),
+        );
+        if status == 0b111 {
+            self.rx1_dr_offset = rx1_dr_offset;
+            self.rx2_dr = Some(rx2_dr);
+            self.rx2_frequency = rx2_frequency.or(self.rx2_frequency);
+        }
+        status
+    }
+
+    fn set_channel_selector(&mut self, selector: ChannelSelector) {
+        self.channel_selector = selector;
+    }
+
+    fn set_last_channel_busy(&mut self, busy: bool) {
+        self.channel_selector.set_busy(self.last_tx_channel, busy);
+    }
+
+    fn set_downlink_frequency(&mut self, channel: u8, frequency: u32) -> Option<u8> {
+        let channel = channel as usize;
+        let status =
+            u8::from(frequency != 0) | (u8::from(self.get_channel(channel).is_some()) << 1);
+        if status == 0b11 {
+            self.downlink_frequencies[channel] = Some(frequency);
+        }
+        Some(status)
     }
 }
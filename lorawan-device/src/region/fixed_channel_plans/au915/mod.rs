@@ -6,7 +6,7 @@ use frequencies::*;
 mod datarates;
 use datarates::*;
 
-const AU_DBM: i8 = 21;
+const AU_MAX_EIRP: i8 = 30;
 const DEFAULT_RX2: u32 = 923_300_000;
 
 /// State struct for the `AU915` region. This struct may be created directly if you wish to fine-tune some parameters.
@@ -16,9 +16,10 @@ const DEFAULT_RX2: u32 = 923_300_000;
 ///
 /// # Note:
 ///
-/// Only [`US915`] and [`AU915`] can be created using this method, because they are the only ones which have
-/// parameters that may be fine-tuned at the region level. To create a [`Configuration`] for other regions, use
-/// [`Configuration::new`] and specify the region using the [`Region`] enum.
+/// Only [`US915`], [`AU915`] and [`CN470`] have parameters that may be fine-tuned at the region level. To create a
+/// [`Configuration`] for other regions, use [`Configuration::new`] and specify the region using the [`Region`] enum.
+/// Like the state structs of the other regions, this struct also implements [`ChannelPlan`] and may be used by a
+/// device directly, which leaves the code of the other regions out.
 ///
 /// # Example: Setting up join bias
 ///
@@ -58,27 +59,47 @@ impl FixedChannelRegion<16> for AU915Region {
     fn get_default_rx2() -> u32 {
         DEFAULT_RX2
     }
-    fn get_rx_datarate(tx_datarate: DR, _frame: &Frame, window: &Window) -> Datarate {
-        let datarate = match window {
-            Window::_1 => {
-                // no support for RX1 DR Offset
-                match tx_datarate {
-                    DR::_0 => DR::_8,
-                    DR::_1 => DR::_9,
-                    DR::_2 => DR::_10,
-                    DR::_3 => DR::_11,
-                    DR::_4 => DR::_12,
-                    DR::_5 => DR::_13,
-                    DR::_6 => DR::_13,
-                    DR::_7 => DR::_9,
-                    _ => panic!("Invalid TX datarate"),
-                }
-            }
-            Window::_2 => DR::_8,
+    fn max_rx1_dr_offset() -> u8 {
+        5
+    }
+    fn rx1_datarate(datarate: u8, rx1_dr_offset: u8) -> u8 {
+        // DR7 (LR-FHSS) downlinks like DR1
+        let datarate = if datarate == 7 {
+            1
+        } else {
+            datarate
         };
-        DATARATES[datarate as usize].clone().unwrap()
+        (8 + datarate as i8 - rx1_dr_offset as i8).clamp(8, 13) as u8
+    }
+    fn max_eirp() -> i8 {
+        AU_MAX_EIRP
+    }
+    fn get_join_500khz_datarate() -> DR {
+        DR::_6
     }
-    fn get_dbm() -> i8 {
-        AU_DBM
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_rx1_datarate_table() {
+        // RX1 data rates for uplink DR0-DR7 and RX1DROffset 0-5.
+        const TABLE: [[u8; 6]; 8] = [
+            [8, 8, 8, 8, 8, 8],
+            [9, 8, 8, 8, 8, 8],
+            [10, 9, 8, 8, 8, 8],
+            [11, 10, 9, 8, 8, 8],
+            [12, 11, 10, 9, 8, 8],
+            [13, 12, 11, 10, 9, 8],
+            [13, 13, 12, 11, 10, 9],
+            [9, 8, 8, 8, 8, 8],
+        ];
+        for (datarate, row) in TABLE.iter().enumerate() {
+            for (offset, rx1_datarate) in row.iter().enumerate() {
+                assert_eq!(AU915Region::rx1_datarate(datarate as u8, offset as u8), *rx1_datarate);
+            }
+        }
     }
 }
@@ -14,6 +14,9 @@ pub(crate) struct JoinChannels {
     pub(crate) available_channels: AvailableChannels,
     /// The channel used for the previous join request.
     pub(crate) previous_channel: u8,
+    /// The 500 kHz channel of the subband used by the previous 125 kHz join request, which is
+    /// attempted next.
+    pending_wide_channel: Option<u8>,
 }
 
 impl JoinChannels {
@@ -56,6 +59,29 @@ impl JoinChannels {
     pub(crate) fn reset(&mut self) {
         self.num_retries = 0;
         self.available_channels = AvailableChannels::default();
+        self.pending_wide_channel = None;
+    }
+
+    /// Channel for the next join request. As recommended by RP002, 125 kHz channels are picked
+    /// from a different subband each time and every 125 kHz attempt is followed by one on the
+    /// 500 kHz channel of the same subband. With `wide_only`, only the 500 kHz channels are used,
+    /// still walking the subbands.
+    pub(crate) fn get_next_join_channel(&mut self, rng: &mut impl RngCore, wide_only: bool) -> u8 {
+        let channel = match self.pending_wide_channel.take() {
+            Some(channel) => channel,
+            None => {
+                let channel = self.get_next_channel(rng);
+                let wide_channel = 64 + channel / 8;
+                if wide_only {
+                    wide_channel
+                } else {
+                    self.pending_wide_channel = Some(wide_channel);
+                    channel
+                }
+            }
+        };
+        self.previous_channel = channel;
+        channel
     }
 
     pub(crate) fn get_next_channel(&mut self, rng: &mut impl RngCore) -> u8 {
@@ -92,8 +118,8 @@ pub(crate) struct AvailableChannels {
 
 impl AvailableChannels {
     fn is_exhausted(&self) -> bool {
-        // check if every underlying byte is entirely cleared to 0
-        for byte in self.data.as_ref() {
+        // check if every byte of the 125 kHz channels is entirely cleared to 0
+        for byte in &self.data.as_ref()[..8] {
             if *byte != 0 {
                 return false;
             }
@@ -116,8 +142,8 @@ impl AvailableChannels {
 
     fn get_next_channel_inner(&mut self, rng: &mut impl RngCore) -> u8 {
         if let Some(previous) = self.previous {
-            // choose the next one by possibly wrapping around
-            let next = (previous + 8) % 72;
+            // choose the same channel on the next subband by possibly wrapping around
+            let next = (previous + 8) % 64;
             // if the channel is valid, great!
             if self.data.is_enabled(next.into()).unwrap() {
                 next
@@ -171,8 +197,9 @@ macro_rules! impl_join_bias {
             }
 
             /// Specify a preferred subband when joining the network. Only the first join attempt
-            /// will occur on this subband. After that, each bank will be attempted sequentially
-            /// as described in the US915/AU915 regional specifications.
+            /// will occur on this subband (followed by its 500 kHz channel). After that, each
+            /// subband will be attempted sequentially as described in the US915/AU915 regional
+            /// specifications.
             pub fn set_join_bias(&mut self, subband: Subband) {
                 self.0.join_channels.set_join_bias(subband, 1)
             }
@@ -215,11 +242,12 @@ mod test {
     use super::*;
     use crate::mac::Response;
     use crate::{
-        mac::{Mac, SendData},
+        downlink::DownlinkQueue,
+        mac::{Mac, Reception, SendData},
+        radio::{RadioBuffer, RxQuality},
         test_util::{get_key, handle_join_request, Uplink},
-        AppEui, AppKey, DevEui, NetworkCredentials,
+        AppEui, AppKey, DevEui, NetworkCredentials, RxWindow,
     };
-    use heapless::Vec;
     use lorawan::default_crypto::DefaultFactory;
 
     #[test]
@@ -232,15 +260,14 @@ mod test {
             // the first channel is always in the bottom 64
             assert!(first_channel < 64);
             let next_channel = join_channels.get_next_channel(&mut rng);
-            // the next channel is always incremented by 8, since we always have
-            // the fat bank (channels 64-71)
-            assert_eq!(next_channel, first_channel + 8);
+            // the next channel is the same one on the next subband
+            assert_eq!(next_channel, (first_channel + 8) % 64);
             // we generate 6 more channels
-            for _ in 0..7 {
+            for _ in 0..6 {
                 let c = join_channels.get_next_channel(&mut rng);
-                assert!(c < 72);
+                assert!(c < 64);
             }
-            // after 8 tries, we should be back at the original bank but on a different channel
+            // after 8 tries, we should be back at the original subband but on a different channel
             let ninth_channel = join_channels.get_next_channel(&mut rng);
             assert_eq!(ninth_channel / 8, first_channel / 8);
             assert_ne!(ninth_channel, first_channel);
@@ -256,13 +283,12 @@ mod test {
         // the first channel is always in the bottom 64
         assert!(first_channel < 64);
         let next_channel = join_channels.get_next_channel(&mut rng);
-        // the next channel is always incremented by 8, since we always have
-        // the fat bank (channels 64-71)
-        assert_eq!(next_channel, first_channel + 8);
+        // the next channel is the same one on the next subband
+        assert_eq!(next_channel, (first_channel + 8) % 64);
         // we generate 6000
         for _ in 0..6000 {
             let c = join_channels.get_next_channel(&mut rng);
-            assert!(c < 72);
+            assert!(c < 64);
         }
     }
 
@@ -278,37 +304,108 @@ mod test {
             assert!(first_channel > 7);
             assert!(first_channel < 16);
             let next_channel = join_channels.get_next_channel(&mut rng);
-            // the next channel is always incremented by 8, since we always have
-            // the fat bank (channels 64-71)
-            assert_eq!(next_channel, first_channel + 8);
+            // the next channel is the same one on the next subband
+            assert_eq!(next_channel, (first_channel + 8) % 64);
             // we generate 6 more channels
-            for _ in 0..7 {
+            for _ in 0..6 {
                 let c = join_channels.get_next_channel(&mut rng);
-                assert!(c < 72);
+                assert!(c < 64);
             }
-            // after 8 tries, we should be back at the biased bank but on a different channel
+            // after 8 tries, we should be back at the biased subband but on a different channel
             let ninth_channel = join_channels.get_next_channel(&mut rng);
             assert_eq!(ninth_channel / 8, first_channel / 8);
             assert_ne!(ninth_channel, first_channel);
         }
     }
 
+    #[test]
+    fn test_join_channels_alternate_500khz() {
+        let mut rng = rand_core::OsRng;
+        for _ in 0..100 {
+            let mut join_channels = JoinChannels::default();
+            let mut subbands = std::vec::Vec::new();
+            for _ in 0..8 {
+                let narrow = join_channels.get_next_join_channel(&mut rng, false);
+                assert!(narrow < 64);
+                // followed by the 500 kHz channel of the same subband
+                let wide = join_channels.get_next_join_channel(&mut rng, false);
+                assert_eq!(wide, 64 + narrow / 8);
+                subbands.push(narrow / 8);
+            }
+            // every subband was attempted
+            subbands.sort();
+            assert_eq!(subbands, [0, 1, 2, 3, 4, 5, 6, 7]);
+        }
+    }
+
+    #[test]
+    fn test_join_channels_wide_only() {
+        let mut rng = rand_core::OsRng;
+        let mut join_channels = JoinChannels::default();
+        join_channels.set_join_bias(Subband::_2, 1);
+        // the bias is honored on the 500 kHz channels as well
+        assert_eq!(join_channels.get_next_join_channel(&mut rng, true), 65);
+        let mut channels: std::vec::Vec<_> =
+            (0..7).map(|_| join_channels.get_next_join_channel(&mut rng, true)).collect();
+        channels.sort();
+        assert_eq!(channels, [64, 66, 67, 68, 69, 70, 71]);
+    }
+
+    #[test]
+    fn test_full_mac_join_datarate() {
+        let credentials = NetworkCredentials::new(
+            AppEui::from([0x0; 8]),
+            DevEui::from([0x0; 8]),
+            AppKey::from(get_key()),
+        );
+        let mut mac: Mac = Mac::new(US915::new().into(), 21, 2);
+        let mut buf: RadioBuffer<255> = RadioBuffer::new();
+        // a 125 kHz datarate alternates with DR4 on the 500 kHz channels
+        mac.configuration.data_rate = DR::_3;
+        for expected in [SpreadingFactor::_7, SpreadingFactor::_8, SpreadingFactor::_7] {
+            let (tx_config, _) = mac
+                .join_otaa::<DefaultFactory, _, 255>(
+                    &mut rand::rngs::OsRng,
+                    credentials.clone(),
+                    &mut buf,
+                )
+                .unwrap();
+            assert_eq!(tx_config.rf.bb.sf, expected);
+        }
+        // a 500 kHz datarate only uses the 500 kHz channels
+        mac.configuration.data_rate = DR::_4;
+        for _ in 0..3 {
+            let (tx_config, _) = mac
+                .join_otaa::<DefaultFactory, _, 255>(
+                    &mut rand::rngs::OsRng,
+                    credentials.clone(),
+                    &mut buf,
+                )
+                .unwrap();
+            assert_eq!(tx_config.rf.bb.bw, Bandwidth::_500KHz);
+            assert!(tx_config.rf.frequency >= 903_000_000);
+            assert!(tx_config.rf.frequency <= 914_200_000);
+        }
+    }
+
     #[test]
     fn test_full_mac_compliant_bias() {
         let mut us915 = US915::new();
         us915.set_join_bias(Subband::_2);
-        let mut mac = Mac::new(us915.into(), 21, 2);
+        let mut mac: Mac = Mac::new(us915.into(), 21, 2);
 
         let mut buf: RadioBuffer<255> = RadioBuffer::new();
-        let (tx_config, _len) = mac.join_otaa::<DefaultFactory, _, 255>(
-            &mut rand::rngs::OsRng,
-            NetworkCredentials::new(
-                AppEui::from([0x0; 8]),
-                DevEui::from([0x0; 8]),
-                AppKey::from(get_key()),
-            ),
-            &mut buf,
-        );
+        let (tx_config, _len) = mac
+            .join_otaa::<DefaultFactory, _, 255>(
+                &mut rand::rngs::OsRng,
+                NetworkCredentials::new(
+                    AppEui::from([0x0; 8]),
+                    DevEui::from([0x0; 8]),
+                    AppKey::from(get_key()),
+                ),
+                &mut buf,
+            )
+            .unwrap();
         // Confirm that the join request occurs on our subband
         assert!(
             tx_config.rf.frequency >= 903_900_000,
@@ -320,7 +417,7 @@ mod test {
             "Unexpected frequency: {} is above 905.3 MHz!",
             tx_config.rf.frequency
         );
-        let mut downlinks: Vec<_, 3> = Vec::new();
+        let mut downlinks = DownlinkQueue::<3>::new();
         let mut data = std::vec::Vec::new();
         data.extend_from_slice(buf.as_ref_for_read());
         let uplink = Uplink::new(buf.as_ref_for_read(), tx_config).unwrap();
@@ -329,7 +426,12 @@ mod test {
         let len = handle_join_request::<0>(Some(uplink), tx_config.rf, &mut rx_buf);
         buf.clear();
         buf.extend_from_slice(&rx_buf[..len]).unwrap();
-        let response = mac.handle_rx::<DefaultFactory, 255, 3>(&mut buf, &mut downlinks);
+        let response = mac.handle_rx::<DefaultFactory, 255, 3>(
+            &mut buf,
+            &mut downlinks,
+            Reception { window: RxWindow::Rx1, quality: RxQuality::new(0, 0) },
+            &mut (),
+        );
         if let Response::JoinSuccess = response {
         } else {
             panic!("Did not receive join success");
@@ -358,18 +460,20 @@ mod test {
     fn test_full_mac_non_compliant_bias() {
         let mut us915 = US915::new();
         us915.set_join_bias_and_noncompliant_retries(Subband::_2, 8);
-        let mut mac = Mac::new(us915.into(), 21, 2);
+        let mut mac: Mac = Mac::new(us915.into(), 21, 2);
 
         let mut buf: RadioBuffer<255> = RadioBuffer::new();
-        let (tx_config, _len) = mac.join_otaa::<DefaultFactory, _, 255>(
-            &mut rand::rngs::OsRng,
-            NetworkCredentials::new(
-                AppEui::from([0x0; 8]),
-                DevEui::from([0x0; 8]),
-                AppKey::from(get_key()),
-            ),
-            &mut buf,
-        );
+        let (tx_config, _len) = mac
+            .join_otaa::<DefaultFactory, _, 255>(
+                &mut rand::rngs::OsRng,
+                NetworkCredentials::new(
+                    AppEui::from([0x0; 8]),
+                    DevEui::from([0x0; 8]),
+                    AppKey::from(get_key()),
+                ),
+                &mut buf,
+            )
+            .unwrap();
         // Confirm that the join request occurs on our subband
         assert!(
             tx_config.rf.frequency >= 903_900_000,
@@ -381,7 +485,7 @@ mod test {
             "Unexpected frequency: {} is above 905.3 MHz!",
             tx_config.rf.frequency
         );
-        let mut downlinks: Vec<_, 3> = Vec::new();
+        let mut downlinks = DownlinkQueue::<3>::new();
         let mut data = std::vec::Vec::new();
         data.extend_from_slice(buf.as_ref_for_read());
         let uplink = Uplink::new(buf.as_ref_for_read(), tx_config).unwrap();
@@ -390,7 +494,12 @@ mod test {
         let len = handle_join_request::<0>(Some(uplink), tx_config.rf, &mut rx_buf);
         buf.clear();
         buf.extend_from_slice(&rx_buf[..len]).unwrap();
-        let response = mac.handle_rx::<DefaultFactory, 255, 3>(&mut buf, &mut downlinks);
+        let response = mac.handle_rx::<DefaultFactory, 255, 3>(
+            &mut buf,
+            &mut downlinks,
+            Reception { window: RxWindow::Rx1, quality: RxQuality::new(0, 0) },
+            &mut (),
+        );
         if let Response::JoinSuccess = response {
         } else {
             panic!("Did not receive JoinSuccess")
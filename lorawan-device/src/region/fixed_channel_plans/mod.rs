@@ -44,8 +44,18 @@ pub(crate) struct FixedChannelPlan<const NUM_DR: usize, F: FixedChannelRegion<NU
     channel_mask: ChannelMask<9>,
     _fixed_channel_region: PhantomData<F>,
     join_channels: JoinChannels,
+    rx1_dr_offset: u8,
+    rx2_dr: Option<u8>,
+    rx2_frequency: Option<u32>,
+    channel_selector: ChannelSelector,
 }
 
+/// RX2 data rate of both US915 and AU915, the first of their downlink data rates.
+const DEFAULT_RX2_DATARATE: u8 = 8;
+
+/// Highest TXPower index of both US915 and AU915 (MaxEIRP - 28 dB).
+const MAX_TX_POWER: u8 = 14;
+
 impl<const D: usize, F: FixedChannelRegion<D>> FixedChannelPlan<D, F> {
     pub fn set_125k_channels(&mut self, enabled: bool) {
         let mask = if enabled {
@@ -63,6 +73,15 @@ impl<const D: usize, F: FixedChannelRegion<D>> FixedChannelPlan<D, F> {
         self.channel_mask.set_bank(7, mask);
     }
 
+    /// Parameters of `datarate` if the channels allow it for uplinks: data rates which are RFU,
+    /// unsupported (eg: LR-FHSS) or reserved to downlinks are not.
+    fn uplink_datarate(datarate: DR) -> Result<Datarate, NoChannelAvailable> {
+        match F::datarates().get(datarate as usize) {
+            Some(Some(dr)) if (datarate as u8) < DEFAULT_RX2_DATARATE => Ok(dr.clone()),
+            _ => Err(NoChannelAvailable),
+        }
+    }
+
     #[allow(unused)]
     pub fn get_max_payload_length(datarate: DR, repeater_compatible: bool, dwell_time: bool) -> u8 {
         F::get_max_payload_length(datarate, repeater_compatible, dwell_time)
@@ -73,16 +92,19 @@ pub(crate) trait FixedChannelRegion<const D: usize>: ChannelRegion<D> {
     fn uplink_channels() -> &'static [u32; 72];
     fn downlink_channels() -> &'static [u32; 8];
     fn get_default_rx2() -> u32;
-    fn get_rx_datarate(tx_datarate: DR, frame: &Frame, window: &Window) -> Datarate;
-    fn get_dbm() -> i8;
+    fn max_rx1_dr_offset() -> u8;
+    /// Data rate of RX1 following an uplink at `datarate`, as defined by the RX1DROffset table of
+    /// the region.
+    fn rx1_datarate(datarate: u8, rx1_dr_offset: u8) -> u8;
+    /// Default MaxEIRP of the region, in dBm.
+    fn max_eirp() -> i8;
+    /// Data rate of join requests on the 500 kHz channels (64-71).
+    fn get_join_500khz_datarate() -> DR;
 }
 
 impl<const D: usize, F: FixedChannelRegion<D>> RegionHandler for FixedChannelPlan<D, F> {
-    fn process_join_accept<T: AsRef<[u8]>, C>(
-        &mut self,
-        join_accept: &DecryptedJoinAcceptPayload<T, C>,
-    ) {
-        if let Some(CfList::FixedChannel(channel_mask)) = join_accept.c_f_list() {
+    fn process_join_accept(&mut self, cf_list: Option<CfList<'_>>) {
+        if let Some(CfList::FixedChannel(channel_mask)) = cf_list {
             // Reset the join channels state
             self.join_channels.reset();
             self.channel_mask = channel_mask;
@@ -126,23 +148,30 @@ impl<const D: usize, F: FixedChannelRegion<D>> RegionHandler for FixedChannelPla
         }
     }
 
+    fn get_channel_mask(&self) -> &ChannelMask<9> {
+        &self.channel_mask
+    }
+
     fn get_tx_dr_and_frequency<RNG: RngCore>(
         &mut self,
         rng: &mut RNG,
         datarate: DR,
         frame: &Frame,
-    ) -> (Datarate, u32) {
+    ) -> Result<(Datarate, u32), NoChannelAvailable> {
         match frame {
             Frame::Join => {
-                let channel = self.join_channels.get_next_channel(rng);
-                let dr = if channel < 64 {
-                    DR::_0
+                // A 125 kHz datarate is used on the 125 kHz channels, alternating with the
+                // 500 kHz channels. Requesting a 500 kHz datarate restricts joins to the latter.
+                let data_rate = Self::uplink_datarate(datarate)?;
+                let wide_only = data_rate.bandwidth == Bandwidth::_500KHz;
+                let channel = self.join_channels.get_next_join_channel(rng, wide_only);
+                let data_rate = if channel < 64 || wide_only {
+                    data_rate
                 } else {
-                    DR::_4
+                    Self::uplink_datarate(F::get_join_500khz_datarate())?
                 };
                 self.last_tx_channel = channel;
-                let data_rate = F::datarates()[dr as usize].clone().unwrap();
-                (data_rate, F::uplink_channels()[channel as usize])
+                Ok((data_rate, F::uplink_channels()[channel as usize]))
             }
             Frame::Data => {
                 // The join bias gets reset after receiving CFList in Join Frame
@@ -154,36 +183,30 @@ impl<const D: usize, F: FixedChannelRegion<D>> RegionHandler for FixedChannelPla
                     let dr = if channel < 64 {
                         DR::_0
                     } else {
-                        DR::_4
+                        F::get_join_500khz_datarate()
                     };
-                    (F::datarates()[dr as usize].clone().unwrap(), channel)
+                    (Self::uplink_datarate(dr)?, channel)
                 // Alternatively, we will ask JoinChannel logic to determine a channel from the
                 // subband that  the join succeeded on.
                 } else if let Some(channel) = self.join_channels.first_data_channel(rng) {
-                    (F::datarates()[datarate as usize].clone().unwrap(), channel)
+                    (Self::uplink_datarate(datarate)?, channel)
                 } else {
                     // For the data frame, the datarate impacts which channel sets we can choose
                     // from. If the datarate bandwidth is 500 kHz, we must use
                     // channels 64-71. Else, we must use 0-63
-                    let datarate = F::datarates()[datarate as usize].clone().unwrap();
-                    if datarate.bandwidth == Bandwidth::_500KHz {
-                        let mut channel = (rng.next_u32() & 0b111) as u8;
-                        // keep selecting a random channel until we find one that is enabled
-                        while !self.channel_mask.is_enabled(channel.into()).unwrap() {
-                            channel = (rng.next_u32() & 0b111) as u8;
-                        }
-                        (datarate, 64 + channel)
+                    let datarate = Self::uplink_datarate(datarate)?;
+                    let channels = if datarate.bandwidth == Bandwidth::_500KHz {
+                        64..72
                     } else {
-                        let mut channel = (rng.next_u32() & 0b111111) as u8;
-                        // keep selecting a random channel until we find one that is enabled
-                        while !self.channel_mask.is_enabled(channel.into()).unwrap() {
-                            channel = (rng.next_u32() & 0b111111) as u8;
-                        }
-                        (datarate, channel)
-                    }
+                        0..64
+                    };
+                    let enabled: heapless::Vec<u8, 64> = channels
+                        .filter(|channel| self.channel_mask.is_enabled(*channel as usize).unwrap())
+                        .collect();
+                    (datarate, self.channel_selector.select(rng, &enabled)?)
                 };
                 self.last_tx_channel = channel;
-                (data_rate, F::uplink_channels()[channel as usize])
+                Ok((data_rate, F::uplink_channels()[channel as usize]))
             }
         }
     }
@@ -192,15 +215,48 @@ impl<const D: usize, F: FixedChannelRegion<D>> RegionHandler for FixedChannelPla
         let channel = self.last_tx_channel % 8;
         match window {
             Window::_1 => F::downlink_channels()[channel as usize],
-            Window::_2 => F::get_default_rx2(),
+            Window::_2 => self.rx2_frequency.unwrap_or_else(F::get_default_rx2),
         }
     }
 
-    fn get_dbm(&self) -> i8 {
-        F::get_dbm()
+    fn get_max_eirp(&self) -> i8 {
+        F::max_eirp()
     }
 
-    fn get_rx_datarate(&self, tx_datarate: DR, frame: &Frame, window: &Window) -> Datarate {
-        F::get_rx_datarate(tx_datarate, frame, window)
+    fn set_channel_selector(&mut self, selector: ChannelSelector) {
+        self.channel_selector = selector;
+    }
+
+    fn set_last_channel_busy(&mut self, busy: bool) {
+        self.channel_selector.set_busy(self.last_tx_channel, busy);
+    }
+
+    fn get_max_tx_power(&self) -> u8 {
+        MAX_TX_POWER
+    }
+
+    fn get_rx_datarate(&self, tx_datarate: DR, _frame: &Frame, window: &Window) -> Datarate {
+        let datarate = match window {
+            Window::_1 => F::rx1_datarate(tx_datarate as u8, self.rx1_dr_offset),
+            Window::_2 => self.rx2_dr.unwrap_or(DEFAULT_RX2_DATARATE),
+        };
+        F::datarates()[datarate as usize].clone().unwrap()
+    }
+
+    fn set_rx_params(&mut self, dl_settings: DLSettings, rx2_frequency: Option<u32>) -> u8 {
+        let rx1_dr_offset = dl_settings.rx1_dr_offset();
+        let rx2_dr = dl_settings.rx2_data_rate();
+        let status = rx_param_setup_status(
+            rx2_frequency != Some(0),
+            rx2_dr >= DEFAULT_RX2_DATARATE
+                && matches!(F::datarates().get(rx2_dr as usize), Some(Some(_))),
+            rx1_dr_offset <= F::max_rx1_dr_offset(),
+        );
+        if status == 0b111 {
+            self.rx1_dr_offset = rx1_dr_offset;
+            self.rx2_dr = Some(rx2_dr);
+            self.rx2_frequency = rx2_frequency.or(self.rx2_frequency);
+        }
+        status
     }
 }
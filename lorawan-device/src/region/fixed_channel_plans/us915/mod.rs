@@ -6,7 +6,7 @@ use frequencies::*;
 mod datarates;
 use datarates::*;
 
-const US_DBM: i8 = 21;
+const US_MAX_EIRP: i8 = 30;
 const DEFAULT_RX2: u32 = 923_300_000;
 
 /// State struct for the `US915` region. This struct may be created directly if you wish to fine-tune some parameters.
@@ -16,9 +16,10 @@ const DEFAULT_RX2: u32 = 923_300_000;
 ///
 /// # Note:
 ///
-/// Only [`US915`] and [`AU915`] can be created using this method, because they are the only ones which have
-/// parameters that may be fine-tuned at the region level. To create a [`Configuration`] for other regions, use
-/// [`Configuration::new`] and specify the region using the [`Region`] enum.
+/// Only [`US915`], [`AU915`] and [`CN470`] have parameters that may be fine-tuned at the region level. To create a
+/// [`Configuration`] for other regions, use [`Configuration::new`] and specify the region using the [`Region`] enum.
+/// Like the state structs of the other regions, this struct also implements [`ChannelPlan`] and may be used by a
+/// device directly, which leaves the code of the other regions out.
 ///
 /// # Example: Setting up join bias
 ///
@@ -58,24 +59,78 @@ impl FixedChannelRegion<14> for US915Region {
     fn get_default_rx2() -> u32 {
         DEFAULT_RX2
     }
-    fn get_rx_datarate(tx_datarate: DR, _frame: &Frame, window: &Window) -> Datarate {
-        let datarate = match window {
-            Window::_1 => {
-                // no support for RX1 DR Offset
-                match tx_datarate {
-                    DR::_0 => DR::_10,
-                    DR::_1 => DR::_11,
-                    DR::_2 => DR::_12,
-                    DR::_3 => DR::_13,
-                    DR::_4 => DR::_13,
-                    _ => panic!("Invalid TX datarate"),
-                }
-            }
-            Window::_2 => DR::_8,
+    fn max_rx1_dr_offset() -> u8 {
+        3
+    }
+    fn rx1_datarate(datarate: u8, rx1_dr_offset: u8) -> u8 {
+        // DR5 and DR6 (LR-FHSS) downlink like DR0 and DR1
+        let datarate = if datarate >= 5 {
+            datarate - 5
+        } else {
+            datarate
         };
-        DATARATES[datarate as usize].clone().unwrap()
+        (10 + datarate as i8 - rx1_dr_offset as i8).clamp(8, 13) as u8
+    }
+    fn max_eirp() -> i8 {
+        US_MAX_EIRP
+    }
+    fn get_join_500khz_datarate() -> DR {
+        DR::_4
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_rx1_datarate_table() {
+        // RX1 data rates for uplink DR0-DR6 and RX1DROffset 0-3.
+        const TABLE: [[u8; 4]; 7] = [
+            [10, 9, 8, 8],
+            [11, 10, 9, 8],
+            [12, 11, 10, 9],
+            [13, 12, 11, 10],
+            [13, 13, 12, 11],
+            [10, 9, 8, 8],
+            [11, 10, 9, 8],
+        ];
+        for (datarate, row) in TABLE.iter().enumerate() {
+            for (offset, rx1_datarate) in row.iter().enumerate() {
+                assert_eq!(US915Region::rx1_datarate(datarate as u8, offset as u8), *rx1_datarate);
+            }
+        }
+    }
+
+    #[test]
+    fn test_tx_power() {
+        let mut region = US915::default();
+        assert_eq!(region.get_max_tx_power(), 14);
+        let tx = region.create_tx_config(&mut rand_core::OsRng, DR::_0, 14, &Frame::Data).unwrap();
+        assert_eq!(tx.pw, 2);
     }
-    fn get_dbm() -> i8 {
-        US_DBM
+
+    #[test]
+    fn test_no_channel_available() {
+        let mut region = US915::default();
+        // all 125 kHz channels off
+        region.set_channel_mask(7, ChannelMask::new(&[0, 0]).unwrap());
+        let tx = region.create_tx_config(&mut rand_core::OsRng, DR::_0, 0, &Frame::Data);
+        assert_eq!(tx, Err(NoChannelAvailable));
+        // the 500 kHz channels are still on
+        let tx = region.create_tx_config(&mut rand_core::OsRng, DR::_4, 0, &Frame::Data).unwrap();
+        assert!(tx.rf.frequency >= 903_000_000);
+    }
+
+    #[test]
+    fn test_join_datarate_not_for_uplinks() {
+        let mut region = US915::default();
+        // DR5 (LR-FHSS) is not supported and DR8 is a downlink data rate
+        for dr in [DR::_5, DR::_8] {
+            let tx = region.create_tx_config(&mut rand_core::OsRng, dr, 0, &Frame::Join);
+            assert_eq!(tx, Err(NoChannelAvailable));
+        }
+        let tx = region.create_tx_config(&mut rand_core::OsRng, DR::_4, 0, &Frame::Join).unwrap();
+        assert!(tx.rf.frequency >= 903_000_000);
     }
 }
@@ -1,11 +1,13 @@
 //! LoRaWAN device region definitions (eg: EU868, US915, etc).
-use lora_modulation::{Bandwidth, BaseBandModulationParams, CodingRate, SpreadingFactor};
-use lorawan::{maccommands::ChannelMask, parser::CfList};
+pub use lora_modulation::{Bandwidth, BaseBandModulationParams, CodingRate, SpreadingFactor};
+pub use lorawan::{maccommands::ChannelMask, parser::CfList, types::DLSettings};
 use rand_core::RngCore;
 
-use crate::mac::{Frame, Window};
+pub use crate::mac::{Frame, Window};
+mod channel_selector;
 pub(crate) mod constants;
-pub(crate) use crate::radio::*;
+pub use crate::radio::{ClearChannelConfig, RfConfig, TxConfig};
+pub use channel_selector::{ChannelSelection, ChannelSelector, NoChannelAvailable};
 use constants::*;
 
 #[cfg(not(any(
@@ -16,7 +18,11 @@ use constants::*;
     feature = "region-eu433",
     feature = "region-eu868",
     feature = "region-in865",
+    feature = "region-kr920",
+    feature = "region-ru864",
+    feature = "region-cn779",
     feature = "region-au915",
+    feature = "region-cn470",
     feature = "region-us915"
 )))]
 compile_error!("You must enable at least one region! eg: `region-eu868`, `region-us915`...");
@@ -28,23 +34,32 @@ compile_error!("You must enable at least one region! eg: `region-eu868`, `region
     feature = "region-as923-4",
     feature = "region-eu433",
     feature = "region-eu868",
-    feature = "region-in865"
+    feature = "region-in865",
+    feature = "region-kr920",
+    feature = "region-ru864",
+    feature = "region-cn779"
 ))]
 mod dynamic_channel_plans;
 #[cfg(feature = "region-as923-1")]
-pub(crate) use dynamic_channel_plans::AS923_1;
+pub use dynamic_channel_plans::AS923_1;
 #[cfg(feature = "region-as923-2")]
-pub(crate) use dynamic_channel_plans::AS923_2;
+pub use dynamic_channel_plans::AS923_2;
 #[cfg(feature = "region-as923-3")]
-pub(crate) use dynamic_channel_plans::AS923_3;
+pub use dynamic_channel_plans::AS923_3;
 #[cfg(feature = "region-as923-4")]
-pub(crate) use dynamic_channel_plans::AS923_4;
+pub use dynamic_channel_plans::AS923_4;
+#[cfg(feature = "region-cn779")]
+pub use dynamic_channel_plans::CN779;
 #[cfg(feature = "region-eu433")]
-pub(crate) use dynamic_channel_plans::EU433;
+pub use dynamic_channel_plans::EU433;
 #[cfg(feature = "region-eu868")]
-pub(crate) use dynamic_channel_plans::EU868;
+pub use dynamic_channel_plans::EU868;
 #[cfg(feature = "region-in865")]
-pub(crate) use dynamic_channel_plans::IN865;
+pub use dynamic_channel_plans::IN865;
+#[cfg(feature = "region-kr920")]
+pub use dynamic_channel_plans::KR920;
+#[cfg(feature = "region-ru864")]
+pub use dynamic_channel_plans::RU864;
 
 #[cfg(any(feature = "region-us915", feature = "region-au915"))]
 mod fixed_channel_plans;
@@ -55,6 +70,11 @@ pub use fixed_channel_plans::AU915;
 #[cfg(feature = "region-us915")]
 pub use fixed_channel_plans::US915;
 
+#[cfg(feature = "region-cn470")]
+mod cn470;
+#[cfg(feature = "region-cn470")]
+pub use cn470::{CN470Plan, CN470};
+
 pub(crate) trait ChannelRegion<const D: usize> {
     fn datarates() -> &'static [Option<Datarate>; D];
 
@@ -79,7 +99,7 @@ pub(crate) trait ChannelRegion<const D: usize> {
 /// Contains LoRaWAN region-specific configuration; is required for creating a LoRaWAN Device.
 ///
 /// Generally constructed using the [`Region`] enum, unless You need to do region-specific
-/// fine-tuning, like for example [`US915`] or [`AU915`].
+/// fine-tuning, like for example [`US915`], [`AU915`] or [`CN470`].
 pub struct Configuration {
     state: State,
 }
@@ -102,7 +122,7 @@ seq_macro::seq!(
 );
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
-/// Regions supported by this crate: AS923_1, AS923_2, AS923_3, AS923_4, AU915, EU868, EU433, IN865, US915.
+/// Regions supported by this crate: AS923_1, AS923_2, AS923_3, AS923_4, AU915, CN470, CN779, EU868, EU433, IN865, KR920, RU864, US915.
 ///
 /// Each region is individually feature-gated (eg: `region-eu868`), however, by default, all regions are enabled.
 ///
@@ -117,12 +137,20 @@ pub enum Region {
     AS923_4,
     #[cfg(feature = "region-au915")]
     AU915,
+    #[cfg(feature = "region-cn470")]
+    CN470,
     #[cfg(feature = "region-eu868")]
     EU868,
     #[cfg(feature = "region-eu433")]
     EU433,
     #[cfg(feature = "region-in865")]
     IN865,
+    #[cfg(feature = "region-kr920")]
+    KR920,
+    #[cfg(feature = "region-ru864")]
+    RU864,
+    #[cfg(feature = "region-cn779")]
+    CN779,
     #[cfg(feature = "region-us915")]
     US915,
 }
@@ -139,12 +167,20 @@ enum State {
     AS923_4(AS923_4),
     #[cfg(feature = "region-au915")]
     AU915(AU915),
+    #[cfg(feature = "region-cn470")]
+    CN470(CN470),
     #[cfg(feature = "region-eu868")]
     EU868(EU868),
     #[cfg(feature = "region-eu433")]
     EU433(EU433),
     #[cfg(feature = "region-in865")]
     IN865(IN865),
+    #[cfg(feature = "region-kr920")]
+    KR920(KR920),
+    #[cfg(feature = "region-ru864")]
+    RU864(RU864),
+    #[cfg(feature = "region-cn779")]
+    CN779(CN779),
     #[cfg(feature = "region-us915")]
     US915(US915),
 }
@@ -162,12 +198,20 @@ impl State {
             Region::AS923_4 => State::AS923_4(AS923_4::default()),
             #[cfg(feature = "region-au915")]
             Region::AU915 => State::AU915(AU915::default()),
+            #[cfg(feature = "region-cn470")]
+            Region::CN470 => State::CN470(CN470::default()),
             #[cfg(feature = "region-eu868")]
             Region::EU868 => State::EU868(EU868::default()),
             #[cfg(feature = "region-eu433")]
             Region::EU433 => State::EU433(EU433::default()),
             #[cfg(feature = "region-in865")]
             Region::IN865 => State::IN865(IN865::default()),
+            #[cfg(feature = "region-kr920")]
+            Region::KR920 => State::KR920(KR920::default()),
+            #[cfg(feature = "region-ru864")]
+            Region::RU864 => State::RU864(RU864::default()),
+            #[cfg(feature = "region-cn779")]
+            Region::CN779 => State::CN779(CN779::default()),
             #[cfg(feature = "region-us915")]
             Region::US915 => State::US915(US915::default()),
         }
@@ -186,12 +230,20 @@ impl State {
             Self::AS923_4(_) => Region::AS923_4,
             #[cfg(feature = "region-au915")]
             Self::AU915(_) => Region::AU915,
+            #[cfg(feature = "region-cn470")]
+            Self::CN470(_) => Region::CN470,
             #[cfg(feature = "region-eu433")]
             Self::EU433(_) => Region::EU433,
             #[cfg(feature = "region-eu868")]
             Self::EU868(_) => Region::EU868,
             #[cfg(feature = "region-in865")]
             Self::IN865(_) => Region::IN865,
+            #[cfg(feature = "region-kr920")]
+            Self::KR920(_) => Region::KR920,
+            #[cfg(feature = "region-ru864")]
+            Self::RU864(_) => Region::RU864,
+            #[cfg(feature = "region-cn779")]
+            Self::CN779(_) => Region::CN779,
             #[cfg(feature = "region-us915")]
             Self::US915(_) => Region::US915,
         }
@@ -211,45 +263,61 @@ macro_rules! mut_region_dispatch {
   ($s:expr, $t:tt) => {
       match &mut $s.state {
         #[cfg(feature = "region-as923-1")]
-        State::AS923_1(state) => state.$t(),
+        State::AS923_1(state) => ChannelPlan::$t(state),
         #[cfg(feature = "region-as923-2")]
-        State::AS923_2(state) => state.$t(),
+        State::AS923_2(state) => ChannelPlan::$t(state),
         #[cfg(feature = "region-as923-3")]
-        State::AS923_3(state) => state.$t(),
+        State::AS923_3(state) => ChannelPlan::$t(state),
         #[cfg(feature = "region-as923-4")]
-        State::AS923_4(state) => state.$t(),
+        State::AS923_4(state) => ChannelPlan::$t(state),
         #[cfg(feature = "region-au915")]
-        State::AU915(state) => state.0.$t(),
+        State::AU915(state) => ChannelPlan::$t(state),
+        #[cfg(feature = "region-cn470")]
+        State::CN470(state) => ChannelPlan::$t(state),
         #[cfg(feature = "region-eu868")]
-        State::EU868(state) => state.$t(),
+        State::EU868(state) => ChannelPlan::$t(state),
         #[cfg(feature = "region-eu433")]
-        State::EU433(state) => state.$t(),
+        State::EU433(state) => ChannelPlan::$t(state),
         #[cfg(feature = "region-in865")]
-        State::IN865(state) => state.$t(),
+        State::IN865(state) => ChannelPlan::$t(state),
+        #[cfg(feature = "region-kr920")]
+        State::KR920(state) => ChannelPlan::$t(state),
+        #[cfg(feature = "region-ru864")]
+        State::RU864(state) => ChannelPlan::$t(state),
+        #[cfg(feature = "region-cn779")]
+        State::CN779(state) => ChannelPlan::$t(state),
         #[cfg(feature = "region-us915")]
-        State::US915(state) => state.0.$t(),
+        State::US915(state) => ChannelPlan::$t(state),
     }
   };
   ($s:expr, $t:tt, $($arg:tt)*) => {
       match &mut $s.state {
         #[cfg(feature = "region-as923-1")]
-        State::AS923_1(state) => state.$t($($arg)*),
+        State::AS923_1(state) => ChannelPlan::$t(state, $($arg)*),
         #[cfg(feature = "region-as923-2")]
-        State::AS923_2(state) => state.$t($($arg)*),
+        State::AS923_2(state) => ChannelPlan::$t(state, $($arg)*),
         #[cfg(feature = "region-as923-3")]
-        State::AS923_3(state) => state.$t($($arg)*),
+        State::AS923_3(state) => ChannelPlan::$t(state, $($arg)*),
         #[cfg(feature = "region-as923-4")]
-        State::AS923_4(state) => state.$t($($arg)*),
+        State::AS923_4(state) => ChannelPlan::$t(state, $($arg)*),
         #[cfg(feature = "region-au915")]
-        State::AU915(state) => state.0.$t($($arg)*),
+        State::AU915(state) => ChannelPlan::$t(state, $($arg)*),
+        #[cfg(feature = "region-cn470")]
+        State::CN470(state) => ChannelPlan::$t(state, $($arg)*),
         #[cfg(feature = "region-eu868")]
-        State::EU868(state) => state.$t($($arg)*),
+        State::EU868(state) => ChannelPlan::$t(state, $($arg)*),
         #[cfg(feature = "region-eu433")]
-        State::EU433(state) => state.$t($($arg)*),
+        State::EU433(state) => ChannelPlan::$t(state, $($arg)*),
         #[cfg(feature = "region-in865")]
-        State::IN865(state) => state.$t($($arg)*),
+        State::IN865(state) => ChannelPlan::$t(state, $($arg)*),
+        #[cfg(feature = "region-kr920")]
+        State::KR920(state) => ChannelPlan::$t(state, $($arg)*),
+        #[cfg(feature = "region-ru864")]
+        State::RU864(state) => ChannelPlan::$t(state, $($arg)*),
+        #[cfg(feature = "region-cn779")]
+        State::CN779(state) => ChannelPlan::$t(state, $($arg)*),
         #[cfg(feature = "region-us915")]
-        State::US915(state) => state.0.$t($($arg)*),
+        State::US915(state) => ChannelPlan::$t(state, $($arg)*),
     }
   };
 }
@@ -258,45 +326,61 @@ macro_rules! region_dispatch {
   ($s:expr, $t:tt) => {
       match &$s.state {
         #[cfg(feature = "region-as923-1")]
-        State::AS923_1(state) => state.$t(),
+        State::AS923_1(state) => ChannelPlan::$t(state),
         #[cfg(feature = "region-as923-2")]
-        State::AS923_2(state) => state.$t(),
+        State::AS923_2(state) => ChannelPlan::$t(state),
         #[cfg(feature = "region-as923-3")]
-        State::AS923_3(state) => state.$t(),
+        State::AS923_3(state) => ChannelPlan::$t(state),
         #[cfg(feature = "region-as923-4")]
-        State::AS923_4(state) => state.$t(),
+        State::AS923_4(state) => ChannelPlan::$t(state),
         #[cfg(feature = "region-au915")]
-        State::AU915(state) => state.0.$t(),
+        State::AU915(state) => ChannelPlan::$t(state),
+        #[cfg(feature = "region-cn470")]
+        State::CN470(state) => ChannelPlan::$t(state),
         #[cfg(feature = "region-eu868")]
-        State::EU868(state) => state.$t(),
+        State::EU868(state) => ChannelPlan::$t(state),
         #[cfg(feature = "region-eu433")]
-        State::EU433(state) => state.$t(),
+        State::EU433(state) => ChannelPlan::$t(state),
         #[cfg(feature = "region-in865")]
-        State::IN865(state) => state.$t(),
+        State::IN865(state) => ChannelPlan::$t(state),
+        #[cfg(feature = "region-kr920")]
+        State::KR920(state) => ChannelPlan::$t(state),
+        #[cfg(feature = "region-ru864")]
+        State::RU864(state) => ChannelPlan::$t(state),
+        #[cfg(feature = "region-cn779")]
+        State::CN779(state) => ChannelPlan::$t(state),
         #[cfg(feature = "region-us915")]
-        State::US915(state) => state.0.$t(),
+        State::US915(state) => ChannelPlan::$t(state),
     }
   };
   ($s:expr, $t:tt, $($arg:tt)*) => {
       match &$s.state {
         #[cfg(feature = "region-as923-1")]
-        State::AS923_1(state) => state.$t($($arg)*),
+        State::AS923_1(state) => ChannelPlan::$t(state, $($arg)*),
         #[cfg(feature = "region-as923-2")]
-        State::AS923_2(state) => state.$t($($arg)*),
+        State::AS923_2(state) => ChannelPlan::$t(state, $($arg)*),
         #[cfg(feature = "region-as923-3")]
-        State::AS923_3(state) => state.$t($($arg)*),
+        State::AS923_3(state) => ChannelPlan::$t(state, $($arg)*),
         #[cfg(feature = "region-as923-4")]
-        State::AS923_4(state) => state.$t($($arg)*),
+        State::AS923_4(state) => ChannelPlan::$t(state, $($arg)*),
         #[cfg(feature = "region-au915")]
-        State::AU915(state) => state.0.$t($($arg)*),
+        State::AU915(state) => ChannelPlan::$t(state, $($arg)*),
+        #[cfg(feature = "region-cn470")]
+        State::CN470(state) => ChannelPlan::$t(state, $($arg)*),
         #[cfg(feature = "region-eu868")]
-        State::EU868(state) => state.$t($($arg)*),
+        State::EU868(state) => ChannelPlan::$t(state, $($arg)*),
         #[cfg(feature = "region-eu433")]
-        State::EU433(state) => state.$t($($arg)*),
+        State::EU433(state) => ChannelPlan::$t(state, $($arg)*),
         #[cfg(feature = "region-in865")]
-        State::IN865(state) => state.$t($($arg)*),
+        State::IN865(state) => ChannelPlan::$t(state, $($arg)*),
+        #[cfg(feature = "region-kr920")]
+        State::KR920(state) => ChannelPlan::$t(state, $($arg)*),
+        #[cfg(feature = "region-ru864")]
+        State::RU864(state) => ChannelPlan::$t(state, $($arg)*),
+        #[cfg(feature = "region-cn779")]
+        State::CN779(state) => ChannelPlan::$t(state, $($arg)*),
         #[cfg(feature = "region-us915")]
-        State::US915(state) => state.0.$t($($arg)*),
+        State::US915(state) => ChannelPlan::$t(state, $($arg)*),
     }
   };
 }
@@ -314,12 +398,20 @@ macro_rules! region_static_dispatch {
         State::AS923_4(_) => dynamic_channel_plans::AS923_4::$t(),
         #[cfg(feature = "region-au915")]
         State::AU915(_) => fixed_channel_plans::AU915::$t(),
+        #[cfg(feature = "region-cn470")]
+        State::CN470(_) => cn470::CN470::$t(),
         #[cfg(feature = "region-eu868")]
         State::EU868(_) => dynamic_channel_plans::EU868::$t(),
         #[cfg(feature = "region-eu433")]
         State::EU433(_) => dynamic_channel_plans::EU433::$t(),
         #[cfg(feature = "region-in865")]
         State::IN865(_) => dynamic_channel_plans::IN865::$t(),
+        #[cfg(feature = "region-kr920")]
+        State::KR920(_) => dynamic_channel_plans::KR920::$t(),
+        #[cfg(feature = "region-ru864")]
+        State::RU864(_) => dynamic_channel_plans::RU864::$t(),
+        #[cfg(feature = "region-cn779")]
+        State::CN779(_) => dynamic_channel_plans::CN779::$t(),
         #[cfg(feature = "region-us915")]
         State::US915(_) => fixed_channel_plans::US915::$t(),
     }
@@ -336,12 +428,20 @@ macro_rules! region_static_dispatch {
         State::AS923_4(_) => dynamic_channel_plans::AS923_4::$t($($arg)*),
         #[cfg(feature = "region-au915")]
         State::AU915(_) => fixed_channel_plans::AU915::$t($($arg)*),
+        #[cfg(feature = "region-cn470")]
+        State::CN470(_) => cn470::CN470::$t($($arg)*),
         #[cfg(feature = "region-eu868")]
         State::EU868(_) => dynamic_channel_plans::EU868::$t($($arg)*),
         #[cfg(feature = "region-eu433")]
         State::EU433(_) => dynamic_channel_plans::EU433::$t($($arg)*),
         #[cfg(feature = "region-in865")]
         State::IN865(_) => dynamic_channel_plans::IN865::$t($($arg)*),
+        #[cfg(feature = "region-kr920")]
+        State::KR920(_) => dynamic_channel_plans::KR920::$t($($arg)*),
+        #[cfg(feature = "region-ru864")]
+        State::RU864(_) => dynamic_channel_plans::RU864::$t($($arg)*),
+        #[cfg(feature = "region-cn779")]
+        State::CN779(_) => dynamic_channel_plans::CN779::$t($($arg)*),
         #[cfg(feature = "region-us915")]
         State::US915(_) => fixed_channel_plans::US915::$t($($arg)*),
     }
@@ -372,102 +472,78 @@ impl Configuration {
         )
     }
 
-    pub(crate) fn create_tx_config<RNG: RngCore>(
-        &mut self,
-        rng: &mut RNG,
+    #[allow(dead_code)]
+    pub(crate) fn get_current_region(&self) -> super::region::Region {
+        self.state.region()
+    }
+}
+
+impl ChannelPlan for Configuration {
+    fn get_default_datarate(&self) -> DR {
+        region_dispatch!(self, get_default_datarate)
+    }
+
+    fn get_default_uplink_dwell_time(&self) -> bool {
+        region_dispatch!(self, get_default_uplink_dwell_time)
+    }
+
+    fn get_max_payload_length(
+        &self,
         datarate: DR,
-        frame: &Frame,
-    ) -> TxConfig {
-        let (dr, frequency) = self.get_tx_dr_and_frequency(rng, datarate, frame);
-        TxConfig {
-            pw: self.get_dbm(),
-            rf: RfConfig {
-                frequency,
-                bb: BaseBandModulationParams::new(
-                    dr.spreading_factor,
-                    dr.bandwidth,
-                    self.get_coding_rate(),
-                ),
-            },
-        }
+        repeater_compatible: bool,
+        dwell_time: bool,
+    ) -> u8 {
+        Configuration::get_max_payload_length(self, datarate, repeater_compatible, dwell_time)
     }
 
-    fn get_tx_dr_and_frequency<RNG: RngCore>(
+    fn create_tx_config<RNG: RngCore>(
         &mut self,
         rng: &mut RNG,
         datarate: DR,
+        tx_power: u8,
         frame: &Frame,
-    ) -> (Datarate, u32) {
-        mut_region_dispatch!(self, get_tx_dr_and_frequency, rng, datarate, frame)
+    ) -> Result<TxConfig, NoChannelAvailable> {
+        mut_region_dispatch!(self, create_tx_config, rng, datarate, tx_power, frame)
     }
 
-    pub(crate) fn get_rx_config(&self, datarate: DR, frame: &Frame, window: &Window) -> RfConfig {
-        let dr = self.get_rx_datarate(datarate, frame, window);
-        RfConfig {
-            frequency: self.get_rx_frequency(frame, window),
-            bb: BaseBandModulationParams::new(
-                dr.spreading_factor,
-                dr.bandwidth,
-                self.get_coding_rate(),
-            ),
-        }
+    fn get_max_tx_power(&self) -> u8 {
+        region_dispatch!(self, get_max_tx_power)
     }
 
-    pub(crate) fn process_join_accept<T: AsRef<[u8]>, C>(
-        &mut self,
-        join_accept: &DecryptedJoinAcceptPayload<T, C>,
-    ) {
-        mut_region_dispatch!(self, process_join_accept, join_accept)
+    fn get_rx_config(&self, datarate: DR, frame: &Frame, window: &Window) -> RfConfig {
+        region_dispatch!(self, get_rx_config, datarate, frame, window)
     }
 
-    pub(crate) fn set_channel_mask(
-        &mut self,
-        channel_mask_control: u8,
-        channel_mask: ChannelMask<2>,
-    ) {
-        mut_region_dispatch!(self, handle_link_adr_channel_mask, channel_mask_control, channel_mask)
+    fn process_join_accept(&mut self, cf_list: Option<CfList<'_>>) {
+        mut_region_dispatch!(self, process_join_accept, cf_list)
     }
 
-    pub(crate) fn get_rx_frequency(&self, frame: &Frame, window: &Window) -> u32 {
-        region_dispatch!(self, get_rx_frequency, frame, window)
+    fn set_channel_mask(&mut self, channel_mask_control: u8, channel_mask: ChannelMask<2>) {
+        mut_region_dispatch!(self, set_channel_mask, channel_mask_control, channel_mask)
     }
 
-    pub(crate) fn get_default_datarate(&self) -> DR {
-        region_dispatch!(self, get_default_datarate)
+    fn get_channel_mask(&self) -> [u8; 9] {
+        region_dispatch!(self, get_channel_mask)
     }
 
-    pub(crate) fn get_rx_datarate(&self, datarate: DR, frame: &Frame, window: &Window) -> Datarate {
-        region_dispatch!(self, get_rx_datarate, datarate, frame, window)
+    fn get_clear_channel_config(&self, rf: RfConfig) -> Option<ClearChannelConfig> {
+        region_dispatch!(self, get_clear_channel_config, rf)
     }
 
-    // Unicast: The RXC parameters are identical to the RX2 parameters, and they use the same
-    // channel and data rate. Modifying the RX2 parameters using the appropriate MAC
-    // commands also modifies the RXC parameters.
-    #[cfg(feature = "class-c")]
-    pub(crate) fn get_rxc_config(&self, datarate: DR) -> RfConfig {
-        let dr = self.get_rx_datarate(datarate, &Frame::Data, &Window::_2);
-        let frequency = self.get_rx_frequency(&Frame::Data, &Window::_2);
-        RfConfig {
-            frequency,
-            bb: BaseBandModulationParams::new(
-                dr.spreading_factor,
-                dr.bandwidth,
-                self.get_coding_rate(),
-            ),
-        }
+    fn set_rx_params(&mut self, dl_settings: DLSettings, rx2_frequency: Option<u32>) -> u8 {
+        mut_region_dispatch!(self, set_rx_params, dl_settings, rx2_frequency)
     }
 
-    pub(crate) fn get_dbm(&self) -> i8 {
-        region_dispatch!(self, get_dbm)
+    fn set_downlink_frequency(&mut self, channel: u8, frequency: u32) -> Option<u8> {
+        mut_region_dispatch!(self, set_downlink_frequency, channel, frequency)
     }
 
-    pub(crate) fn get_coding_rate(&self) -> CodingRate {
-        region_dispatch!(self, get_coding_rate)
+    fn set_channel_selector(&mut self, selector: ChannelSelector) {
+        mut_region_dispatch!(self, set_channel_selector, selector)
     }
 
-    #[allow(dead_code)]
-    pub(crate) fn get_current_region(&self) -> super::region::Region {
-        self.state.region()
+    fn set_last_channel_busy(&mut self, busy: bool) {
+        mut_region_dispatch!(self, set_last_channel_busy, busy)
     }
 }
 
@@ -491,8 +567,16 @@ from_region!(AS923_3);
 from_region!(AS923_4);
 #[cfg(feature = "region-in865")]
 from_region!(IN865);
+#[cfg(feature = "region-kr920")]
+from_region!(KR920);
+#[cfg(feature = "region-ru864")]
+from_region!(RU864);
+#[cfg(feature = "region-cn779")]
+from_region!(CN779);
 #[cfg(feature = "region-au915")]
 from_region!(AU915);
+#[cfg(feature = "region-cn470")]
+from_region!(CN470);
 #[cfg(feature = "region-eu868")]
 from_region!(EU868);
 #[cfg(feature = "region-eu433")]
@@ -500,13 +584,174 @@ from_region!(EU433);
 #[cfg(feature = "region-us915")]
 from_region!(US915);
 
-use lorawan::parser::DecryptedJoinAcceptPayload;
+/// A channel plan: the frequencies, data rates and transmit power a device may use, and how the
+/// network may change them.
+///
+/// [`Configuration`] implements this trait for the regions built into this crate, selected at
+/// runtime. The state struct of each region (eg: [`EU868`], [`US915`]) implements it too, which
+/// lets a device that only ever runs in one region leave the code of the others out. A private
+/// network or a test setup that does not follow any region (eg: a single channel gateway) may
+/// implement it instead of forking the crate, and create the device with
+/// [`async_device::Device::new_with_channel_plan`](crate::async_device::Device::new_with_channel_plan)
+/// or [`nb_device::Device::new_with_channel_plan`](crate::nb_device::Device::new_with_channel_plan).
+///
+/// # Example: Single channel plan
+///
+/// ```
+/// use lorawan_device::region::{
+///     BaseBandModulationParams, Bandwidth, CfList, ChannelMask, ChannelPlan, CodingRate, Frame,
+///     NoChannelAvailable, RfConfig, SpreadingFactor, TxConfig, Window, DR,
+/// };
+/// use lorawan_device::RngCore;
+///
+/// struct SingleChannel;
+///
+/// impl SingleChannel {
+///     fn rf(&self) -> RfConfig {
+///         RfConfig {
+///             frequency: 868_100_000,
+///             bb: BaseBandModulationParams::new(
+///                 SpreadingFactor::_9,
+///                 Bandwidth::_125KHz,
+///                 CodingRate::_4_5,
+///             ),
+///         }
+///     }
+/// }
+///
+/// impl ChannelPlan for SingleChannel {
+///     fn get_max_payload_length(&self, _: DR, _: bool, _: bool) -> u8 {
+///         123
+///     }
+///
+///     fn create_tx_config<RNG: RngCore>(
+///         &mut self,
+///         _: &mut RNG,
+///         _: DR,
+///         tx_power: u8,
+///         _: &Frame,
+///     ) -> Result<TxConfig, NoChannelAvailable> {
+///         Ok(TxConfig { pw: 14 - 2 * tx_power as i8, rf: self.rf() })
+///     }
+///
+///     fn get_rx_config(&self, _: DR, _: &Frame, _: &Window) -> RfConfig {
+///         self.rf()
+///     }
+///
+///     fn process_join_accept(&mut self, _: Option<CfList<'_>>) {}
+///
+///     fn set_channel_mask(&mut self, _: u8, _: ChannelMask<2>) {}
+///
+///     fn get_channel_mask(&self) -> [u8; 9] {
+///         [0xFF; 9]
+///     }
+/// }
+/// ```
+pub trait ChannelPlan {
+    /// Data rate used until the application or the network selects another one.
+    fn get_default_datarate(&self) -> DR {
+        DR::_0
+    }
+
+    /// Whether the 400 ms uplink dwell time limit applies until the network says otherwise.
+    fn get_default_uplink_dwell_time(&self) -> bool {
+        false
+    }
 
-pub(crate) trait RegionHandler {
-    fn process_join_accept<T: AsRef<[u8]>, C>(
+    /// Largest MAC payload allowed at `datarate`. `repeater_compatible` limits it to what a
+    /// repeater can forward, `dwell_time` to what fits the 400 ms dwell time.
+    fn get_max_payload_length(
+        &self,
+        datarate: DR,
+        repeater_compatible: bool,
+        dwell_time: bool,
+    ) -> u8;
+
+    /// Radio configuration of the next uplink, which is where the plan selects a channel. Fails
+    /// when no channel is allowed.
+    ///
+    /// `tx_power` is the TXPower index set by the network, 0 being the highest power. The power of
+    /// the returned configuration is an EIRP in dBm, which the device turns into the output power
+    /// of the radio by subtracting the antenna gain of the board.
+    fn create_tx_config<RNG: RngCore>(
         &mut self,
-        join_accept: &DecryptedJoinAcceptPayload<T, C>,
-    );
+        rng: &mut RNG,
+        datarate: DR,
+        tx_power: u8,
+        frame: &Frame,
+    ) -> Result<TxConfig, NoChannelAvailable>;
+
+    /// Highest TXPower index a LinkADRReq may select. Only index 0 is accepted by default.
+    fn get_max_tx_power(&self) -> u8 {
+        0
+    }
+
+    /// Radio configuration of a receive window following the last uplink, which was sent at
+    /// `datarate`.
+    fn get_rx_config(&self, datarate: DR, frame: &Frame, window: &Window) -> RfConfig;
+
+    /// Radio configuration of the Class C receive window, which defaults to the RX2 parameters.
+    #[cfg(feature = "class-c")]
+    fn get_rxc_config(&self, datarate: DR) -> RfConfig {
+        self.get_rx_config(datarate, &Frame::Data, &Window::_2)
+    }
+
+    /// Applies the CFList of a join accept, if any.
+    fn process_join_accept(&mut self, cf_list: Option<CfList<'_>>);
+
+    /// Applies the channel mask of a LinkADRReq.
+    fn set_channel_mask(&mut self, channel_mask_control: u8, channel_mask: ChannelMask<2>);
+
+    /// Currently enabled channels, one bit per channel.
+    fn get_channel_mask(&self) -> [u8; 9];
+
+    /// Listen-before-talk parameters for a transmission on `rf`, if the plan requires it.
+    fn get_clear_channel_config(&self, _rf: RfConfig) -> Option<ClearChannelConfig> {
+        None
+    }
+
+    /// Applies the RX1 data rate offset and RX2 data rate of the DLSettings of a join accept or
+    /// RXParamSetupReq, along with the RX2 frequency of the latter. Nothing is applied unless all
+    /// of them are valid.
+    ///
+    /// Returns the status of the RXParamSetupAns: bit 0 acknowledges the RX2 frequency, bit 1 the
+    /// RX2 data rate and bit 2 the RX1 data rate offset. All of them are rejected by default.
+    fn set_rx_params(&mut self, _dl_settings: DLSettings, _rx2_frequency: Option<u32>) -> u8 {
+        0
+    }
+
+    /// Sets the RX1 frequency following uplinks on `channel`, as requested by a DlChannelReq.
+    ///
+    /// Returns the status of the DlChannelAns: bit 0 acknowledges the frequency and bit 1 the
+    /// existence of the uplink channel. `None`, the default, means the plan does not support the
+    /// command.
+    fn set_downlink_frequency(&mut self, _channel: u8, _frequency: u32) -> Option<u8> {
+        None
+    }
+
+    /// Sets how data uplinks are spread over the enabled channels, for plans which select them
+    /// with a [`ChannelSelector`].
+    fn set_channel_selector(&mut self, _selector: ChannelSelector) {}
+
+    /// Marks the channel of the last uplink as busy, when listen-before-talk found it busy or a
+    /// confirmed uplink on it was not acknowledged, or as clear again once an uplink on it is
+    /// acknowledged.
+    fn set_last_channel_busy(&mut self, _busy: bool) {}
+}
+
+/// Status of a RXParamSetupAns.
+pub(crate) fn rx_param_setup_status(
+    rx2_frequency_ack: bool,
+    rx2_datarate_ack: bool,
+    rx1_dr_offset_ack: bool,
+) -> u8 {
+    u8::from(rx2_frequency_ack)
+        | (u8::from(rx2_datarate_ack) << 1)
+        | (u8::from(rx1_dr_offset_ack) << 2)
+}
+
+pub(crate) trait RegionHandler {
+    fn process_join_accept(&mut self, cf_list: Option<CfList<'_>>);
 
     fn handle_link_adr_channel_mask(
         &mut self,
@@ -514,6 +759,8 @@ pub(crate) trait RegionHandler {
         channel_mask: ChannelMask<2>,
     );
 
+    fn get_channel_mask(&self) -> &ChannelMask<9>;
+
     fn get_default_datarate(&self) -> DR {
         DR::_0
     }
@@ -522,14 +769,208 @@ pub(crate) trait RegionHandler {
         rng: &mut RNG,
         datarate: DR,
         frame: &Frame,
-    ) -> (Datarate, u32);
+    ) -> Result<(Datarate, u32), NoChannelAvailable>;
 
     fn get_rx_frequency(&self, frame: &Frame, window: &Window) -> u32;
     fn get_rx_datarate(&self, datarate: DR, frame: &Frame, window: &Window) -> Datarate;
-    fn get_dbm(&self) -> i8 {
-        DEFAULT_DBM
+    /// MaxEIRP of the region, in dBm, which TXPower index 0 transmits at.
+    fn get_max_eirp(&self) -> i8 {
+        DEFAULT_MAX_EIRP
+    }
+    /// Highest TXPower index defined by the region.
+    fn get_max_tx_power(&self) -> u8 {
+        DEFAULT_MAX_TX_POWER
+    }
+    /// Highest EIRP allowed on `frequency` by the band it belongs to.
+    fn get_band_max_eirp(&self, _frequency: u32) -> i8 {
+        self.get_max_eirp()
+    }
+    /// EIRP of TXPower index `tx_power` on `frequency`: 2 dB less per index than MaxEIRP, capped
+    /// by the limit of the band.
+    fn get_eirp(&self, tx_power: u8, frequency: u32) -> i8 {
+        let eirp = self.get_max_eirp() - 2 * tx_power.min(self.get_max_tx_power()) as i8;
+        core::cmp::min(eirp, self.get_band_max_eirp(frequency))
     }
     fn get_coding_rate(&self) -> CodingRate {
         DEFAULT_CODING_RATE
     }
+    fn get_default_uplink_dwell_time(&self) -> bool {
+        false
+    }
+    fn get_clear_channel_config(&self, _rf: RfConfig) -> Option<ClearChannelConfig> {
+        None
+    }
+    fn set_rx_params(&mut self, dl_settings: DLSettings, rx2_frequency: Option<u32>) -> u8;
+    fn set_downlink_frequency(&mut self, _channel: u8, _frequency: u32) -> Option<u8> {
+        None
+    }
+    fn set_channel_selector(&mut self, _selector: ChannelSelector) {}
+    fn set_last_channel_busy(&mut self, _busy: bool) {}
+
+    fn create_tx_config<RNG: RngCore>(
+        &mut self,
+        rng: &mut RNG,
+        datarate: DR,
+        tx_power: u8,
+        frame: &Frame,
+    ) -> Result<TxConfig, NoChannelAvailable> {
+        let (dr, frequency) = self.get_tx_dr_and_frequency(rng, datarate, frame)?;
+        Ok(TxConfig {
+            pw: self.get_eirp(tx_power, frequency),
+            rf: RfConfig {
+                frequency,
+                bb: BaseBandModulationParams::new(
+                    dr.spreading_factor,
+                    dr.bandwidth,
+                    self.get_coding_rate(),
+                ),
+            },
+        })
+    }
+
+    fn get_rx_config(&self, datarate: DR, frame: &Frame, window: &Window) -> RfConfig {
+        let dr = self.get_rx_datarate(datarate, frame, window);
+        RfConfig {
+            frequency: self.get_rx_frequency(frame, window),
+            bb: BaseBandModulationParams::new(
+                dr.spreading_factor,
+                dr.bandwidth,
+                self.get_coding_rate(),
+            ),
+        }
+    }
+}
+
+/// Implements [`ChannelPlan`] for the state struct of a built-in region, forwarding to the
+/// [`RegionHandler`] of the struct (or of its field `$f`).
+macro_rules! impl_channel_plan {
+    (@ref $s:ident) => { $s };
+    (@ref $s:ident $f:tt) => { &$s.$f };
+    (@mut $s:ident) => { $s };
+    (@mut $s:ident $f:tt) => { &mut $s.$f };
+    ($r:ident $(, $f:tt)?) => {
+        impl ChannelPlan for $r {
+            fn get_default_datarate(&self) -> DR {
+                RegionHandler::get_default_datarate(impl_channel_plan!(@ref self $($f)?))
+            }
+
+            fn get_default_uplink_dwell_time(&self) -> bool {
+                RegionHandler::get_default_uplink_dwell_time(impl_channel_plan!(@ref self $($f)?))
+            }
+
+            fn get_max_payload_length(
+                &self,
+                datarate: DR,
+                repeater_compatible: bool,
+                dwell_time: bool,
+            ) -> u8 {
+                $r::get_max_payload_length(datarate, repeater_compatible, dwell_time)
+            }
+
+            fn create_tx_config<RNG: RngCore>(
+                &mut self,
+                rng: &mut RNG,
+                datarate: DR,
+                tx_power: u8,
+                frame: &Frame,
+            ) -> Result<TxConfig, NoChannelAvailable> {
+                RegionHandler::create_tx_config(
+                    impl_channel_plan!(@mut self $($f)?),
+                    rng,
+                    datarate,
+                    tx_power,
+                    frame,
+                )
+            }
+
+            fn get_max_tx_power(&self) -> u8 {
+                RegionHandler::get_max_tx_power(impl_channel_plan!(@ref self $($f)?))
+            }
+
+            fn get_rx_config(&self, datarate: DR, frame: &Frame, window: &Window) -> RfConfig {
+                RegionHandler::get_rx_config(impl_channel_plan!(@ref self $($f)?), datarate, frame, window)
+            }
+
+            fn process_join_accept(&mut self, cf_list: Option<CfList<'_>>) {
+                RegionHandler::process_join_accept(impl_channel_plan!(@mut self $($f)?), cf_list)
+            }
+
+            fn set_channel_mask(
+                &mut self,
+                channel_mask_control: u8,
+                channel_mask: ChannelMask<2>,
+            ) {
+                RegionHandler::handle_link_adr_channel_mask(
+                    impl_channel_plan!(@mut self $($f)?),
+                    channel_mask_control,
+                    channel_mask,
+                )
+            }
+
+            fn get_channel_mask(&self) -> [u8; 9] {
+                let mut mask = [0; 9];
+                mask.copy_from_slice(RegionHandler::get_channel_mask(impl_channel_plan!(@ref self $($f)?)).as_ref());
+                mask
+            }
+
+            fn get_clear_channel_config(&self, rf: RfConfig) -> Option<ClearChannelConfig> {
+                RegionHandler::get_clear_channel_config(impl_channel_plan!(@ref self $($f)?), rf)
+            }
+
+            fn set_rx_params(
+                &mut self,
+                dl_settings: DLSettings,
+                rx2_frequency: Option<u32>,
+            ) -> u8 {
+                RegionHandler::set_rx_params(
+                    impl_channel_plan!(@mut self $($f)?),
+                    dl_settings,
+                    rx2_frequency,
+                )
+            }
+
+            fn set_downlink_frequency(&mut self, channel: u8, frequency: u32) -> Option<u8> {
+                RegionHandler::set_downlink_frequency(
+                    impl_channel_plan!(@mut self $($f)?),
+                    channel,
+                    frequency,
+                )
+            }
+
+            fn set_channel_selector(&mut self, selector: ChannelSelector) {
+                RegionHandler::set_channel_selector(impl_channel_plan!(@mut self $($f)?), selector)
+            }
+
+            fn set_last_channel_busy(&mut self, busy: bool) {
+                RegionHandler::set_last_channel_busy(impl_channel_plan!(@mut self $($f)?), busy)
+            }
+        }
+    };
 }
+
+#[cfg(feature = "region-as923-1")]
+impl_channel_plan!(AS923_1, 0);
+#[cfg(feature = "region-as923-2")]
+impl_channel_plan!(AS923_2, 0);
+#[cfg(feature = "region-as923-3")]
+impl_channel_plan!(AS923_3, 0);
+#[cfg(feature = "region-as923-4")]
+impl_channel_plan!(AS923_4, 0);
+#[cfg(feature = "region-in865")]
+impl_channel_plan!(IN865, 0);
+#[cfg(feature = "region-kr920")]
+impl_channel_plan!(KR920, 0);
+#[cfg(feature = "region-ru864")]
+impl_channel_plan!(RU864, 0);
+#[cfg(feature = "region-cn779")]
+impl_channel_plan!(CN779, 0);
+#[cfg(feature = "region-au915")]
+impl_channel_plan!(AU915, 0);
+#[cfg(feature = "region-cn470")]
+impl_channel_plan!(CN470);
+#[cfg(feature = "region-eu868")]
+impl_channel_plan!(EU868, 0);
+#[cfg(feature = "region-eu433")]
+impl_channel_plan!(EU433, 0);
+#[cfg(feature = "region-us915")]
+impl_channel_plan!(US915, 0);
@@ -5,8 +5,8 @@ use lorawan::maccommands::{
 use lorawan::parser::{self, DataHeader};
 use lorawan::{
     default_crypto::DefaultFactory,
-    maccommandcreator::LinkADRReqCreator,
-    maccommands::LinkADRReqPayload,
+    maccommandcreator::{LinkADRReqCreator, RXParamSetupReqCreator},
+    maccommands::{LinkADRReqPayload, RXParamSetupReqPayload},
     parser::{parse, DataPayload, JoinAcceptPayload, PhyPayload},
 };
 use mac::Session;
@@ -25,7 +25,6 @@ use std::{
 #[derive(Debug, Clone)]
 pub struct Uplink {
     data: Vec<u8>,
-    #[allow(unused)]
     tx_config: TxConfig,
 }
 
@@ -38,6 +37,10 @@ impl Uplink {
         Ok(Self { data, tx_config })
     }
 
+    pub fn get_tx_config(&self) -> TxConfig {
+        self.tx_config
+    }
+
     pub fn get_payload(&mut self) -> PhyPayload<&mut [u8], DefaultFactory> {
         match parse(self.data.as_mut_slice()) {
             Ok(p) => p,
@@ -70,6 +73,26 @@ pub fn get_abp_credentials() -> JoinMode {
     }
 }
 
+/// Restores a session through [`LorawanDevice`], as application code generic over the front-end
+/// would after a reset.
+pub fn restore_session(device: &mut impl LorawanDevice) {
+    assert!(!device.ready_to_send_data());
+    assert!(device.get_session_keys().is_none());
+    device.set_session(Session {
+        nwkskey: NwkSKey::from(get_key()),
+        appskey: AppSKey::from(get_key()),
+        devaddr: get_dev_addr(),
+        fcnt_up: 7,
+        fcnt_down: 3,
+        confirmed: false,
+        uplink: Default::default(),
+    });
+    assert!(device.ready_to_send_data());
+    assert_eq!(device.get_fcnt_up(), Some(7));
+    assert!(device.get_session_keys().is_some());
+    assert_eq!(device.get_session().map(|session| session.devaddr()), Some(&get_dev_addr()));
+}
+
 pub type RxTxHandler = fn(Option<Uplink>, RfConfig, &mut [u8]) -> usize;
 
 static SESSION: LazyLock<Mutex<HashMap<usize, Session>>> =
@@ -149,6 +172,12 @@ pub fn handle_data_uplink_with_link_adr_req<const FCNT_UP: u16, const FCNT_DOWN:
             ];
             let cmd: Vec<&dyn SerializableMacCommand> = vec![&mac_cmds[0], &mac_cmds[1]];
             let mut phy = lorawan::creator::DataPayloadCreator::new(rx_buffer).unwrap();
+            // acknowledge confirmed uplinks
+            let mut fctrl = parser::FCtrl::new(0, false);
+            if uplink.is_confirmed() {
+                fctrl.set_ack();
+            }
+            phy.set_fctrl(&fctrl);
             phy.set_confirmed(uplink.is_confirmed());
             phy.set_f_port(4);
             phy.set_dev_addr(&[0; 4]);
@@ -199,6 +228,30 @@ pub fn handle_class_c_uplink_after_join(
     }
 }
 
+/// Handle an uplink and respond with an empty downlink which does not acknowledge it
+pub fn handle_data_uplink_without_ack(
+    uplink: Option<Uplink>,
+    _config: RfConfig,
+    rx_buffer: &mut [u8],
+) -> usize {
+    if let Some(mut uplink) = uplink {
+        if let PhyPayload::Data(DataPayload::Encrypted(data)) = uplink.get_payload() {
+            let fcnt = data.fhdr().fcnt() as u32;
+            assert!(data.validate_mic(&get_key().into(), fcnt));
+            let mut phy = lorawan::creator::DataPayloadCreator::new(rx_buffer).unwrap();
+            phy.set_dev_addr(&[0; 4]);
+            phy.set_uplink(false);
+            let finished =
+                phy.build(&[], &[], &get_key().into(), &get_key().into(), &DefaultFactory).unwrap();
+            finished.len()
+        } else {
+            panic!("Did not decode PhyPayload::Data!");
+        }
+    } else {
+        panic!("No uplink passed to handle_data_uplink_without_ack");
+    }
+}
+
 fn link_adr_req_with_bank_ctrl(cm: u16) -> LinkADRReqCreator {
     // prepare a confirmed downlink
     let mut adr_req = LinkADRReqCreator::new();
@@ -269,3 +322,183 @@ pub fn class_c_downlink<const FCNT_DOWN: u32>(
         phy.build(&[1, 2, 3], &[], &get_key().into(), &get_key().into(), &DefaultFactory).unwrap();
     finished.len()
 }
+
+/// Handle an uplink and respond with a LinkADRReq setting TXPower index `TX_POWER` on channels
+/// 0 to 7
+pub fn handle_data_uplink_with_tx_power_req<const TX_POWER: u8>(
+    uplink: Option<Uplink>,
+    _config: RfConfig,
+    rx_buffer: &mut [u8],
+) -> usize {
+    if let Some(mut uplink) = uplink {
+        if let PhyPayload::Data(DataPayload::Encrypted(data)) = uplink.get_payload() {
+            let fcnt = data.fhdr().fcnt() as u32;
+            assert!(data.validate_mic(&get_key().into(), fcnt));
+            let mut creator = LinkADRReqCreator::new();
+            creator.set_data_rate(0).unwrap();
+            creator.set_tx_power(TX_POWER).unwrap();
+            creator.set_redundancy(0);
+            creator.set_channel_mask(ChannelMask::new(&[0xFF, 0]).unwrap());
+            // drop the CID byte when building the MAC Command (ie: [1..])
+            let mac_cmd = DownlinkMacCommand::LinkADRReq(
+                LinkADRReqPayload::new(&creator.build()[1..]).unwrap(),
+            );
+            let cmd: Vec<&dyn SerializableMacCommand> = vec![&mac_cmd];
+            let mut phy = lorawan::creator::DataPayloadCreator::new(rx_buffer).unwrap();
+            phy.set_dev_addr(&[0; 4]);
+            phy.set_uplink(false);
+            phy.set_fcnt(0);
+            let finished = phy
+                .build(&[], &cmd, &get_key().into(), &get_key().into(), &DefaultFactory)
+                .unwrap();
+            finished.len()
+        } else {
+            panic!("Did not decode PhyPayload::Data!");
+        }
+    } else {
+        panic!("No uplink passed to handle_data_uplink_with_tx_power_req");
+    }
+}
+
+/// Looks for a LinkADRAns whose power acknowledgement is `POWER_ACK`, in an uplink transmitted at
+/// `PW` dBm
+pub fn handle_data_uplink_with_tx_power_ans<const POWER_ACK: bool, const PW: i8>(
+    uplink: Option<Uplink>,
+    _config: RfConfig,
+    rx_buffer: &mut [u8],
+) -> usize {
+    if let Some(mut uplink) = uplink {
+        assert_eq!(uplink.tx_config.pw, PW);
+        if let PhyPayload::Data(DataPayload::Encrypted(data)) = uplink.get_payload() {
+            let fcnt = data.fhdr().fcnt() as u32;
+            assert!(data.validate_mic(&get_key().into(), fcnt));
+            let uplink =
+                data.decrypt(Some(&get_key().into()), Some(&get_key().into()), fcnt).unwrap();
+            let fhdr = uplink.fhdr();
+            let mac_cmds: Vec<UplinkMacCommand<'_>> =
+                MacCommandIterator::<UplinkMacCommand<'_>>::new(fhdr.data()).collect();
+            assert_eq!(mac_cmds.len(), 1);
+            let UplinkMacCommand::LinkADRAns(ans) = &mac_cmds[0] else {
+                panic!("Expected a LinkADRAns");
+            };
+            assert!(ans.channel_mask_ack());
+            assert!(ans.data_rate_ack());
+            assert_eq!(ans.powert_ack(), POWER_ACK);
+
+            rx_buffer.iter_mut().for_each(|x| *x = 0);
+            let mut phy = lorawan::creator::DataPayloadCreator::new(rx_buffer).unwrap();
+            phy.set_dev_addr(&[0; 4]);
+            phy.set_uplink(false);
+            phy.set_fcnt(1);
+            let finished =
+                phy.build(&[], &[], &get_key().into(), &get_key().into(), &DefaultFactory).unwrap();
+            finished.len()
+        } else {
+            panic!("Did not decode PhyPayload::Data!");
+        }
+    } else {
+        panic!("No uplink passed to handle_data_uplink_with_tx_power_ans");
+    }
+}
+
+/// Handle an uplink and respond with a RXParamSetupReq: RX1DROffset 2, RX2 on DR10 at 923.9 MHz
+pub fn handle_data_uplink_with_rx_param_setup_req(
+    uplink: Option<Uplink>,
+    _config: RfConfig,
+    rx_buffer: &mut [u8],
+) -> usize {
+    if let Some(mut uplink) = uplink {
+        if let PhyPayload::Data(DataPayload::Encrypted(data)) = uplink.get_payload() {
+            let fcnt = data.fhdr().fcnt() as u32;
+            assert!(data.validate_mic(&get_key().into(), fcnt));
+            let mut creator = RXParamSetupReqCreator::new();
+            let frequency = (923_900_000u32 / 100).to_le_bytes();
+            let frequency = [frequency[0], frequency[1], frequency[2]];
+            creator.set_dl_settings((2 << 4) | 10).set_frequency(&frequency);
+            // drop the CID byte when building the MAC Command (ie: [1..])
+            let mac_cmd = DownlinkMacCommand::RXParamSetupReq(
+                RXParamSetupReqPayload::new(&creator.build()[1..]).unwrap(),
+            );
+            let cmd: Vec<&dyn SerializableMacCommand> = vec![&mac_cmd];
+            let mut phy = lorawan::creator::DataPayloadCreator::new(rx_buffer).unwrap();
+            phy.set_dev_addr(&[0; 4]);
+            phy.set_uplink(false);
+            phy.set_fcnt(0);
+            let finished = phy
+                .build(&[], &cmd, &get_key().into(), &get_key().into(), &DefaultFactory)
+                .unwrap();
+            finished.len()
+        } else {
+            panic!("Did not decode PhyPayload::Data!");
+        }
+    } else {
+        panic!("No uplink passed to handle_data_uplink_with_rx_param_setup_req");
+    }
+}
+
+/// Looks for a RXParamSetupAns acknowledging all parameters, in an uplink at DR0 whose RX1 is now
+/// on DR8 (SF12, 500 kHz)
+pub fn handle_data_uplink_with_rx_param_setup_ans(
+    uplink: Option<Uplink>,
+    config: RfConfig,
+    rx_buffer: &mut [u8],
+) -> usize {
+    if let Some(mut uplink) = uplink {
+        if let PhyPayload::Data(DataPayload::Encrypted(data)) = uplink.get_payload() {
+            let fcnt = data.fhdr().fcnt() as u32;
+            assert!(data.validate_mic(&get_key().into(), fcnt));
+            let uplink =
+                data.decrypt(Some(&get_key().into()), Some(&get_key().into()), fcnt).unwrap();
+            let fhdr = uplink.fhdr();
+            let mac_cmds: Vec<UplinkMacCommand<'_>> =
+                MacCommandIterator::<UplinkMacCommand<'_>>::new(fhdr.data()).collect();
+            assert_eq!(mac_cmds.len(), 1);
+            let UplinkMacCommand::RXParamSetupAns(ans) = &mac_cmds[0] else {
+                panic!("Expected a RXParamSetupAns");
+            };
+            assert!(ans.ack());
+
+            assert_eq!(config.bb.sf, lora_modulation::SpreadingFactor::_12);
+            assert_eq!(config.bb.bw, lora_modulation::Bandwidth::_500KHz);
+
+            rx_buffer.iter_mut().for_each(|x| *x = 0);
+            let mut phy = lorawan::creator::DataPayloadCreator::new(rx_buffer).unwrap();
+            phy.set_dev_addr(&[0; 4]);
+            phy.set_uplink(false);
+            phy.set_fcnt(1);
+            let finished =
+                phy.build(&[], &[], &get_key().into(), &get_key().into(), &DefaultFactory).unwrap();
+            finished.len()
+        } else {
+            panic!("Did not decode PhyPayload::Data!");
+        }
+    } else {
+        panic!("No uplink passed to handle_data_uplink_with_rx_param_setup_ans");
+    }
+}
+
+/// Looks for an uplink without MAC commands, and responds with an empty downlink
+pub fn handle_data_uplink_without_mac_cmds<const FCNT_DOWN: u32>(
+    uplink: Option<Uplink>,
+    _config: RfConfig,
+    rx_buffer: &mut [u8],
+) -> usize {
+    if let Some(mut uplink) = uplink {
+        if let PhyPayload::Data(DataPayload::Encrypted(data)) = uplink.get_payload() {
+            let fcnt = data.fhdr().fcnt() as u32;
+            assert!(data.validate_mic(&get_key().into(), fcnt));
+            assert!(data.fhdr().data().is_empty());
+            let mut phy = lorawan::creator::DataPayloadCreator::new(rx_buffer).unwrap();
+            phy.set_dev_addr(&[0; 4]);
+            phy.set_uplink(false);
+            phy.set_fcnt(FCNT_DOWN);
+            let finished =
+                phy.build(&[], &[], &get_key().into(), &get_key().into(), &DefaultFactory).unwrap();
+            finished.len()
+        } else {
+            panic!("Did not decode PhyPayload::Data!");
+        }
+    } else {
+        panic!("No uplink passed to handle_data_uplink_without_mac_cmds");
+    }
+}
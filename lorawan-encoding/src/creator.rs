@@ -11,7 +11,7 @@ use crate::packet_length::phy::join::{
     JOIN_ACCEPT_LEN, JOIN_ACCEPT_WITH_CFLIST_LEN, JOIN_REQUEST_LEN,
 };
 use crate::packet_length::phy::{MIC_LEN, PHY_PAYLOAD_MIN_LEN};
-use crate::types::{DLSettings, Frequency};
+use crate::types::{ChannelMask, DLSettings, Frequency};
 
 #[derive(Debug, PartialEq)]
 #[cfg_attr(feature = "defmt-03", derive(defmt::Format))]
@@ -24,6 +24,8 @@ pub enum Error {
 }
 
 const PIGGYBACK_MAC_COMMANDS_MAX_LEN: usize = 15;
+/// Offset of the CFList type, the last byte of the CFList.
+const JOIN_ACCEPT_CFLIST_TYPE: usize = JOIN_ACCEPT_WITH_CFLIST_LEN - MIC_LEN - 1;
 
 /// JoinAcceptCreator serves for creating binary representation of Physical
 /// Payload of JoinAccept.
@@ -160,7 +162,30 @@ impl<D: AsMut<[u8]>> JoinAcceptCreator<D> {
             d[15 + i * 3] = ((v >> 16) & 0xff) as u8;
         });
         // set cflist type
-        d[JOIN_ACCEPT_WITH_CFLIST_LEN - 1] = 0;
+        d[JOIN_ACCEPT_CFLIST_TYPE] = 0;
+        self.with_c_f_list = true;
+
+        Ok(self)
+    }
+
+    /// Sets the CFList of the JoinAccept to a channel mask (CFList type 1), as used by regions
+    /// with fixed channel plans.
+    ///
+    /// # Argument
+    ///
+    /// * channel_mask - the channels to be enabled on the device.
+    pub fn set_c_f_list_channel_mask(
+        &mut self,
+        channel_mask: &ChannelMask<9>,
+    ) -> Result<&mut Self, Error> {
+        let d = self.data.as_mut();
+        if d.len() < JOIN_ACCEPT_WITH_CFLIST_LEN {
+            return Err(Error::BufferTooShort);
+        }
+        d[13..22].copy_from_slice(channel_mask.as_ref());
+        d[22..JOIN_ACCEPT_CFLIST_TYPE].fill(0);
+        // set cflist type
+        d[JOIN_ACCEPT_CFLIST_TYPE] = 1;
         self.with_c_f_list = true;
 
         Ok(self)
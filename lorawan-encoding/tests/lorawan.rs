@@ -4,7 +4,7 @@ use lorawan::keys::*;
 use lorawan::maccommandcreator::*;
 use lorawan::maccommands::*;
 use lorawan::parser::*;
-use lorawan::types::{DLSettings, Frequency};
+use lorawan::types::{ChannelMask, DLSettings, Frequency};
 
 fn phy_join_request_payload() -> Vec<u8> {
     let mut res = Vec::new();
@@ -674,6 +674,25 @@ fn test_join_accept_creator_with_cflist() {
     assert_eq!(decrypted.c_f_list(), Some(CfList::DynamicChannel(freqs)))
 }
 
+#[test]
+#[cfg(feature = "default-crypto")]
+fn test_join_accept_creator_with_channel_mask_cflist() {
+    let mut buf = [0u8; 17 + 16];
+    let mut phy = JoinAcceptCreator::new(&mut buf[..]).unwrap();
+    let key: AppKey = AppKey::from(app_key());
+    let channel_mask = ChannelMask::from([0xFF, 0, 0x0F, 0, 0, 0, 0, 0, 0x01]);
+    phy.set_app_nonce(&[0xc7, 0x0b, 0x57])
+        .set_net_id(&[0x01, 0x11, 0x22])
+        .set_dev_addr(&[0x80, 0x19, 0x03, 0x02])
+        .set_c_f_list_channel_mask(&channel_mask)
+        .unwrap();
+    phy.build(key.inner(), &DefaultFactory).unwrap();
+    let encrypted = EncryptedJoinAcceptPayload::new(buf).unwrap();
+    let decrypted = encrypted.decrypt(&key);
+    assert!(decrypted.validate_mic(&key));
+    assert_eq!(decrypted.c_f_list(), Some(CfList::FixedChannel(channel_mask)))
+}
+
 #[test]
 fn test_join_request_creator() {
     let buf = [0u8; 23];